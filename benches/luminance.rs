@@ -0,0 +1,32 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use viuer::color::{luminance, weighted_luminance};
+
+// The pixels of a 512x512 image with varied colors.
+fn pixels() -> Vec<(u8, u8, u8)> {
+    (0..512 * 512u32)
+        .map(|i| (i as u8, (i >> 8) as u8, (i >> 16) as u8 ^ i as u8))
+        .collect()
+}
+
+fn bench_luminance(c: &mut Criterion) {
+    let pixels = pixels();
+    c.bench_function("luminance fixed point", |b| {
+        b.iter(|| {
+            black_box(&pixels)
+                .iter()
+                .map(|&rgb| luminance(rgb) as u32)
+                .sum::<u32>()
+        })
+    });
+    c.bench_function("luminance weighted", |b| {
+        b.iter(|| {
+            black_box(&pixels)
+                .iter()
+                .map(|&rgb| weighted_luminance(rgb, [0.2126, 0.7152, 0.0722]) as u32)
+                .sum::<u32>()
+        })
+    });
+}
+
+criterion_group!(benches, bench_luminance);
+criterion_main!(benches);
@@ -0,0 +1,1274 @@
+use crate::error::{ViuError, ViuResult};
+use crate::Config;
+use image::codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder};
+use image::error::{DecodingError, ImageFormatHint, UnsupportedError, UnsupportedErrorKind};
+use image::{
+    AnimationDecoder, Delay, DynamicImage, Frames, ImageDecoder, ImageError, ImageFormat, RgbaImage,
+};
+use std::borrow::Borrow;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Metadata of an animated image, gathered without printing any of its frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationInfo {
+    /// Number of frames in the animation.
+    pub frame_count: usize,
+    /// Sum of the delays of all frames.
+    pub total_duration: Duration,
+    /// Width and height of the animation in pixels.
+    pub dimensions: (u32, u32),
+}
+
+/// Read an animated GIF, APNG or WebP file and collect its [AnimationInfo].
+///
+/// The frames of GIFs are not decoded, only their metadata is read. Delays are counted like
+/// during playback with the default `min_frame_interval` of [Config].
+///
+/// ## Example
+/// ```no_run
+/// use viuer::animation_info_from_file;
+///
+/// let info = animation_info_from_file("img.gif").expect("Could not read animation.");
+/// println!("{} frames, {:.1}s", info.frame_count, info.total_duration.as_secs_f32());
+/// ```
+pub fn animation_info_from_file<P: AsRef<Path>>(filename: P) -> ViuResult<AnimationInfo> {
+    let reader = BufReader::new(File::open(filename)?);
+    animation_info(reader)
+}
+
+/// Decides how long each frame of an animation stays on screen, e.g. to sync playback to an
+/// external clock.
+pub trait FrameScheduler {
+    /// Return the delay after the frame at `frame_index`, given the delay stored in the
+    /// animation itself.
+    fn next_delay(&mut self, frame_index: usize, intrinsic: Duration) -> Duration;
+}
+
+/// [FrameScheduler] keeping the delays stored in the animation. Used by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IntrinsicTiming;
+
+impl FrameScheduler for IntrinsicTiming {
+    fn next_delay(&mut self, _frame_index: usize, intrinsic: Duration) -> Duration {
+        intrinsic
+    }
+}
+
+/// Play an animated GIF, APNG or WebP as often as `loop_count` of the config asks, decoding and
+/// printing one frame at a time.
+///
+/// Only the frame being printed is kept in memory, together with the canvas the decoder needs
+/// to compose frames. Hence, it is suitable for large animations. To loop the animation, the
+/// reader is moved back to where the animation starts and it is decoded again. Returns the
+/// dimensions of the last frame in terminal cells.
+///
+/// The time between frames is decided by the `scheduler` if one is provided, otherwise the
+/// delays stored in the animation are used.
+///
+/// ## Example
+/// ```no_run
+/// use std::{fs::File, io::BufReader};
+/// use viuer::{stream_animation, Config};
+///
+/// let reader = BufReader::new(File::open("img.gif").expect("Could not open file."));
+/// stream_animation(reader, &Config::default(), None).expect("Animation printing failed.");
+/// ```
+pub fn stream_animation<R: BufRead + Seek>(
+    reader: R,
+    config: &Config,
+    scheduler: Option<&mut dyn FrameScheduler>,
+) -> ViuResult<(u32, u32)> {
+    let mut intrinsic = IntrinsicTiming;
+    let scheduler = scheduler.unwrap_or(&mut intrinsic);
+    stream_frames(reader, config, scheduler, crate::print, std::thread::sleep)
+}
+
+// Decode and draw the frames of the animation for every loop, see play_sequence.
+fn stream_frames<R: BufRead + Seek>(
+    mut reader: R,
+    config: &Config,
+    scheduler: &mut dyn FrameScheduler,
+    mut draw: impl FnMut(&DynamicImage, &Config) -> ViuResult<(u32, u32)>,
+    mut wait: impl FnMut(Duration),
+) -> ViuResult<(u32, u32)> {
+    let start = reader.stream_position()?;
+    play_looped(config, |playback| {
+        reader.seek(SeekFrom::Start(start))?;
+        let (_, frames) = decode_frames(&mut reader)?;
+        let sequence = decoded_sequence(frames, config.min_frame_interval);
+        play_sequence(sequence, config, scheduler, playback, &mut draw, &mut wait)
+    })
+}
+
+/// Play a sequence of frames as often as `loop_count` of the config asks, e.g. a spinner or a
+/// plot generated on the fly, each frame printed at the same position as the first one.
+///
+/// Every frame stays on screen for the delay at the same index, raised to
+/// `min_frame_interval` if shorter. Returns the dimensions of the last frame in terminal cells.
+///
+/// ## Example
+/// ```no_run
+/// use std::time::Duration;
+/// use viuer::{print_frames, Config};
+///
+/// let frames: Vec<_> = (0..4u8)
+///     .map(|i| image::RgbaImage::from_pixel(8, 8, image::Rgba([60 * i, 0, 0, 255])))
+///     .map(image::DynamicImage::ImageRgba8)
+///     .collect();
+/// let delays = vec![Duration::from_millis(100); frames.len()];
+/// print_frames(&frames, &delays, &Config::default()).expect("Animation printing failed.");
+/// ```
+pub fn print_frames(
+    frames: &[DynamicImage],
+    delays: &[Duration],
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    play_images(frames, delays, config, crate::print, std::thread::sleep)
+}
+
+fn play_images(
+    frames: &[DynamicImage],
+    delays: &[Duration],
+    config: &Config,
+    mut draw: impl FnMut(&DynamicImage, &Config) -> ViuResult<(u32, u32)>,
+    mut wait: impl FnMut(Duration),
+) -> ViuResult<(u32, u32)> {
+    if frames.len() != delays.len() {
+        return Err(ViuError::InvalidConfiguration(format!(
+            "{} frames were given with {} delays",
+            frames.len(),
+            delays.len()
+        )));
+    }
+
+    let min_interval = config.min_frame_interval;
+    play_looped(config, |playback| {
+        let sequence = frames
+            .iter()
+            .zip(delays)
+            .map(|(frame, delay)| Ok((frame, std::cmp::max(*delay, min_interval))));
+        play_sequence(
+            sequence,
+            config,
+            &mut IntrinsicTiming,
+            playback,
+            &mut draw,
+            &mut wait,
+        )
+    })
+}
+
+// Draw the decoded frames once, see play_sequence. Frames are consumed lazily, so only one of
+// them is alive at a time.
+fn play_frames(
+    frames: Frames,
+    config: &Config,
+    scheduler: &mut dyn FrameScheduler,
+    draw: impl FnMut(&DynamicImage, &Config) -> ViuResult<(u32, u32)>,
+    wait: impl FnMut(Duration),
+) -> ViuResult<(u32, u32)> {
+    let sequence = decoded_sequence(frames, config.min_frame_interval);
+    let mut playback = Playback::default();
+    play_sequence(sequence, config, scheduler, &mut playback, draw, wait)?;
+    Ok(playback.dimensions)
+}
+
+// The decoded frames with their delays, raised to the minimum interval.
+fn decoded_sequence(
+    frames: Frames<'_>,
+    min_interval: Duration,
+) -> impl Iterator<Item = ViuResult<(DynamicImage, Duration)>> + '_ {
+    frames.map(move |frame| {
+        let frame = frame?;
+        let delay = delay_to_duration(frame.delay(), min_interval);
+        Ok((DynamicImage::ImageRgba8(frame.into_buffer()), delay))
+    })
+}
+
+// Progress of the playback of an animation, carried over from one loop to the next.
+#[derive(Default)]
+struct Playback {
+    // The config the frames after the first one are drawn with
+    redraw_config: Option<Config>,
+    // Dimensions of the last frame in terminal cells
+    dimensions: (u32, u32),
+    frames: usize,
+    elapsed: Duration,
+}
+
+impl Playback {
+    // Whether `max_frames` are drawn or `max_duration` has passed.
+    fn finished(&self, config: &Config) -> bool {
+        config.max_frames.is_some_and(|max| self.frames >= max)
+            || config.max_duration.is_some_and(|max| self.elapsed >= max)
+    }
+}
+
+// Play the animation with `play_loop` as many times as `loop_count` asks, or until the playback
+// is finished. Returns the dimensions of the last frame in terminal cells.
+fn play_looped(
+    config: &Config,
+    mut play_loop: impl FnMut(&mut Playback) -> ViuResult,
+) -> ViuResult<(u32, u32)> {
+    let mut playback = Playback::default();
+    let mut pass = 1;
+    while config.loop_count.plays(pass) && !playback.finished(config) {
+        let drawn = playback.frames;
+        play_loop(&mut playback)?;
+        // an animation without frames would be looped forever
+        if playback.frames == drawn {
+            break;
+        }
+        pass += 1;
+    }
+    Ok(playback.dimensions)
+}
+
+// Draw the frames one after another at the same position, waiting for each frame's delay in
+// between. Stops early once the playback is finished, see `max_frames` and `max_duration`.
+fn play_sequence<F: Borrow<DynamicImage>>(
+    mut frames: impl Iterator<Item = ViuResult<(F, Duration)>>,
+    config: &Config,
+    scheduler: &mut dyn FrameScheduler,
+    playback: &mut Playback,
+    mut draw: impl FnMut(&DynamicImage, &Config) -> ViuResult<(u32, u32)>,
+    mut wait: impl FnMut(Duration),
+) -> ViuResult {
+    let mut index = 0;
+    while !playback.finished(config) {
+        let Some(frame) = frames.next() else {
+            break;
+        };
+        let (img, intrinsic) = frame?;
+        let delay = scheduler.next_delay(index, intrinsic);
+
+        let frame_config = playback.redraw_config.as_ref().unwrap_or(config);
+        let dimensions = draw(img.borrow(), frame_config)?;
+        playback.dimensions = dimensions;
+
+        if playback.redraw_config.is_none() {
+            playback.redraw_config = Some(Config {
+                // With a relative offset, the next frames move back up to where the first one
+                // started. An absolute offset already points there.
+                y: match config.absolute_offset {
+                    true => config.y,
+                    false => -crate::utils::saturating_i16(dimensions.1),
+                },
+                // The frames are composed already, so whatever shows through their transparent
+                // pixels is left over from the previous frame and has to be cleared.
+                transparent_as_space: config.transparent_as_space || config.transparent,
+                ..config.clone()
+            });
+        }
+
+        wait(delay);
+        playback.frames += 1;
+        playback.elapsed += delay;
+        index += 1;
+    }
+
+    Ok(())
+}
+
+/// Render the frame at `index` of an animated GIF, APNG or WebP with half blocks like
+/// [crate::to_ansi], without playing the animation.
+///
+/// The frames before it are decoded and composed as well, respecting their disposal, so the
+/// result looks like the frame does during playback. To seek repeatedly, e.g. for a scrubber,
+/// use a [FrameSeeker], which continues from the last frame when seeking forward.
+///
+/// ## Example
+/// ```no_run
+/// use std::{fs::File, io::BufReader};
+/// use viuer::{render_frame_at, Config};
+///
+/// let reader = BufReader::new(File::open("img.gif").expect("Could not open file."));
+/// let ansi = render_frame_at(reader, 2, &Config::default()).expect("Rendering failed.");
+/// print!("{}", ansi);
+/// ```
+pub fn render_frame_at<R: BufRead + Seek>(
+    reader: R,
+    index: usize,
+    config: &Config,
+) -> ViuResult<String> {
+    let (_, mut frames) = decode_frames(reader)?;
+    let frame = frames
+        .nth(index)
+        .ok_or_else(|| missing_frame(index))??
+        .into_buffer();
+    render_ansi(&DynamicImage::ImageRgba8(frame), config)
+}
+
+/// Print a single frame of an animated GIF, APNG or WebP as a still image, e.g. as the preview
+/// of an animation in a file browser.
+///
+/// The frame at `poster_index` is printed if set, otherwise the first frame that is not fully
+/// transparent, falling back to the first frame. A [crate::Badge] makes it recognizable as an
+/// animation. Returns the dimensions of the frame in terminal cells.
+///
+/// ## Example
+/// ```no_run
+/// use std::{fs::File, io::BufReader};
+/// use viuer::{poster_frame, Badge, Config};
+///
+/// let reader = BufReader::new(File::open("img.gif").expect("Could not open file."));
+/// let conf = Config {
+///     badge: Some(Badge::new("▶")),
+///     ..Default::default()
+/// };
+/// poster_frame(reader, &conf).expect("Poster printing failed.");
+/// ```
+pub fn poster_frame<R: BufRead + Seek>(reader: R, config: &Config) -> ViuResult<(u32, u32)> {
+    draw_poster(reader, config, crate::print)
+}
+
+fn draw_poster<R: BufRead + Seek>(
+    reader: R,
+    config: &Config,
+    draw: impl FnOnce(&DynamicImage, &Config) -> ViuResult<(u32, u32)>,
+) -> ViuResult<(u32, u32)> {
+    let (_, mut frames) = decode_frames(reader)?;
+    let poster = match config.poster_index {
+        Some(index) => frames.nth(index).ok_or_else(|| missing_frame(index))??,
+        None => {
+            let (mut first, mut poster) = (None, None);
+            for frame in frames {
+                let frame = frame?;
+                if frame.buffer().pixels().any(|pixel| pixel[3] != 0) {
+                    poster = Some(frame);
+                    break;
+                }
+                first.get_or_insert(frame);
+            }
+            poster.or(first).ok_or_else(|| missing_frame(0))?
+        }
+    };
+    draw(&DynamicImage::ImageRgba8(poster.into_buffer()), config)
+}
+
+/// Seeks to frames of an animated GIF, APNG or WebP, keeping the last composed frame so that
+/// seeking to the same or a later frame only decodes the frames in between. Seeking backwards
+/// starts decoding from the first frame again.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{Config, FrameSeeker};
+///
+/// let file = std::fs::File::open("img.gif").expect("Could not open file.");
+/// let mut seeker = FrameSeeker::new(file).expect("Could not read animation.");
+/// for index in [3, 4, 5, 1] {
+///     let ansi = seeker.render(index, &Config::default()).expect("Rendering failed.");
+///     print!("{}", ansi);
+/// }
+/// ```
+pub struct FrameSeeker {
+    data: Rc<[u8]>,
+    frames: Frames<'static>,
+    // index of the frame the decoder returns next
+    next: usize,
+    current: Option<RgbaImage>,
+}
+
+impl FrameSeeker {
+    /// Read the whole animation into memory and prepare to decode it.
+    pub fn new(mut reader: impl std::io::Read) -> ViuResult<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let data: Rc<[u8]> = data.into();
+        let (_, frames) = decode_frames(std::io::Cursor::new(data.clone()))?;
+        Ok(Self {
+            data,
+            frames,
+            next: 0,
+            current: None,
+        })
+    }
+
+    /// Return the frame at `index`, composed with the frames before it.
+    pub fn frame(&mut self, index: usize) -> ViuResult<DynamicImage> {
+        if index + 1 < self.next || (index + 1 == self.next && self.current.is_none()) {
+            self.frames = decode_frames(std::io::Cursor::new(self.data.clone()))?.1;
+            self.next = 0;
+            self.current = None;
+        }
+        while self.next <= index {
+            let frame = self.frames.next().ok_or_else(|| missing_frame(index))??;
+            self.current = Some(frame.into_buffer());
+            self.next += 1;
+        }
+        match &self.current {
+            Some(frame) => Ok(DynamicImage::ImageRgba8(frame.clone())),
+            None => Err(missing_frame(index)),
+        }
+    }
+
+    /// Render the frame at `index` with half blocks, see [render_frame_at].
+    pub fn render(&mut self, index: usize, config: &Config) -> ViuResult<String> {
+        let frame = self.frame(index)?;
+        render_ansi(&frame, config)
+    }
+}
+
+fn missing_frame(index: usize) -> ViuError {
+    ViuError::InvalidConfiguration(format!("the animation has no frame {}", index))
+}
+
+fn render_ansi(img: &DynamicImage, config: &Config) -> ViuResult<String> {
+    let mut out = crate::string_writer::StringWriter::new();
+    crate::printer::BlockPrinter::write(&mut out, img, config)?;
+    Ok(out.read())
+}
+
+/// Print `frames` evenly spaced frames of an animated GIF, APNG or WebP next to each other,
+/// separated by transparent gaps about a terminal cell wide, to preview it without playing it.
+///
+/// The strip is sized like a single image with the config, by default to fit in the terminal.
+/// Two passes are made over the source, one to count the frames and one to extract the chosen
+/// ones. Returns the dimensions of the strip in terminal cells.
+///
+/// ## Example
+/// ```no_run
+/// use std::{fs::File, io::BufReader};
+/// use viuer::{print_filmstrip, Config};
+///
+/// let reader = BufReader::new(File::open("img.gif").expect("Could not open file."));
+/// print_filmstrip(reader, 4, &Config::default()).expect("Filmstrip printing failed.");
+/// ```
+pub fn print_filmstrip<R: BufRead + Seek>(
+    reader: R,
+    frames: usize,
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    let cols = config
+        .width
+        .unwrap_or_else(|| crate::terminal_size().0 as u32);
+    let strip = filmstrip(reader, frames, cols)?;
+    crate::print(&strip, config)
+}
+
+// Lay out the chosen frames from left to right, leaving a gap of about one of `cols` cells
+// between them.
+fn filmstrip<R: BufRead + Seek>(
+    mut reader: R,
+    frames: usize,
+    cols: u32,
+) -> ViuResult<DynamicImage> {
+    if frames == 0 {
+        return Err(ViuError::InvalidConfiguration(
+            "a filmstrip needs at least one frame".to_owned(),
+        ));
+    }
+
+    let total = decode_frames(&mut reader)?.1.count();
+    reader.rewind()?;
+    let ((width, height), decoded) = decode_frames(&mut reader)?;
+
+    let frames = frames.min(total).max(1);
+    let chosen: Vec<usize> = (0..frames)
+        .map(|i| match frames {
+            1 => 0,
+            _ => i * total.saturating_sub(1) / (frames - 1),
+        })
+        .collect();
+
+    // every frame gets the same share of the columns, a cell less for each gap
+    let frames = frames as u32;
+    let frame_cols = std::cmp::max(1, cols.saturating_sub(frames - 1) / frames);
+    let gap = width.div_ceil(frame_cols);
+
+    let mut strip = RgbaImage::new(frames * width + (frames - 1) * gap, height);
+    let mut placed = 0;
+    for (index, frame) in decoded.enumerate() {
+        if chosen.get(placed) != Some(&index) {
+            continue;
+        }
+        let buffer = frame?.into_buffer();
+        // the same frame is chosen repeatedly when there are few of them
+        while chosen.get(placed) == Some(&index) {
+            let x = placed as u32 * (width + gap);
+            image::imageops::replace(&mut strip, &buffer, x as i64, 0);
+            placed += 1;
+        }
+        if placed == chosen.len() {
+            break;
+        }
+    }
+    Ok(DynamicImage::ImageRgba8(strip))
+}
+
+// First line of a transcoded animation, identifying the framing format and its version.
+const TRANSCODED_HEADER: &str = "VIUER-ANSI 1\n";
+
+// Largest payload of a transcoded frame that is played back, far above what any terminal could
+// show, so that a corrupt length does not exhaust the memory.
+const MAX_TRANSCODED_FRAME_LEN: usize = 64 << 20;
+
+/// Render an animated GIF to a stream of ANSI frames that can be played back with
+/// [play_transcoded], e.g. to do the decoding and resizing on a server and only replay the
+/// frames on a client.
+///
+/// The frames are rendered with half blocks like [crate::to_ansi], each one printed at the same
+/// position as the first. The stream starts with the line `VIUER-ANSI 1`, followed by one
+/// record per frame: a line with the frame's delay in milliseconds and the length of its
+/// payload in bytes, separated by a space, then the payload itself. Frames are written as soon
+/// as they are rendered. Returns the dimensions of the last frame in terminal cells.
+///
+/// ## Example
+/// ```no_run
+/// use std::fs::File;
+/// use viuer::{transcode_gif, Config};
+///
+/// let gif = File::open("img.gif").expect("Could not open file.");
+/// let mut out = File::create("img.ansi").expect("Could not create file.");
+/// transcode_gif(gif, &mut out, &Config::default()).expect("Transcoding failed.");
+/// ```
+pub fn transcode_gif(
+    reader: impl Read,
+    sink: &mut impl Write,
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    config.validate()?;
+    let frames = GifDecoder::new(reader)?.into_frames();
+
+    // the scheduler only records the delay of the frame being drawn, for its record
+    let delay = std::cell::Cell::new(Duration::ZERO);
+    let mut recorder = RecordDelay(&delay);
+
+    sink.write_all(TRANSCODED_HEADER.as_bytes())?;
+    let draw = |img: &DynamicImage, config: &Config| {
+        let mut payload = termcolor::Ansi::new(Vec::new());
+        let dimensions = crate::printer::BlockPrinter::write(&mut payload, img, config)?;
+        let payload = payload.into_inner();
+        writeln!(sink, "{} {}", delay.get().as_millis(), payload.len())?;
+        sink.write_all(&payload)?;
+        sink.flush()?;
+        Ok(dimensions)
+    };
+    play_frames(frames, config, &mut recorder, draw, |_| {})
+}
+
+struct RecordDelay<'a>(&'a std::cell::Cell<Duration>);
+
+impl FrameScheduler for RecordDelay<'_> {
+    fn next_delay(&mut self, _frame_index: usize, intrinsic: Duration) -> Duration {
+        self.0.set(intrinsic);
+        intrinsic
+    }
+}
+
+/// Play an animation transcoded with [transcode_gif] once, printing its frames in order.
+///
+/// Delays shorter than `min_frame_interval` are raised to it, and `use_stderr` and
+/// `override_writer` choose where the frames are printed. The other options of the config were
+/// already applied when transcoding.
+///
+/// ## Example
+/// ```no_run
+/// use std::{fs::File, io::BufReader};
+/// use viuer::{play_transcoded, Config};
+///
+/// let reader = BufReader::new(File::open("img.ansi").expect("Could not open file."));
+/// play_transcoded(reader, &Config::default()).expect("Playback failed.");
+/// ```
+pub fn play_transcoded(reader: impl BufRead, config: &Config) -> ViuResult {
+    let mut stdout = crate::Writer::from_config(config);
+    play_transcoded_to(reader, &mut stdout, config, std::thread::sleep)
+}
+
+fn play_transcoded_to(
+    mut reader: impl BufRead,
+    stdout: &mut impl Write,
+    config: &Config,
+    mut wait: impl FnMut(Duration),
+) -> ViuResult {
+    let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_owned());
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if line != TRANSCODED_HEADER {
+        return Err(invalid("not a transcoded animation").into());
+    }
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let (delay, len) = line
+            .trim_end()
+            .split_once(' ')
+            .and_then(|(delay, len)| Some((delay.parse().ok()?, len.parse().ok()?)))
+            .ok_or_else(|| invalid("invalid frame record"))?;
+        if len > MAX_TRANSCODED_FRAME_LEN {
+            return Err(invalid("frame too large").into());
+        }
+
+        let mut payload = Vec::new();
+        (&mut reader).take(len as u64).read_to_end(&mut payload)?;
+        if payload.len() != len {
+            return Err(invalid("truncated frame").into());
+        }
+        stdout.write_all(&payload)?;
+        stdout.flush()?;
+        wait(std::cmp::max(
+            Duration::from_millis(delay),
+            config.min_frame_interval,
+        ));
+    }
+}
+
+// Convert the delay of a frame to a duration without losing precision, raising delays that are
+// too short to be played back (most often zero) to the minimum interval.
+fn delay_to_duration(delay: Delay, min_interval: Duration) -> Duration {
+    let (numer, denom_ms) = delay.numer_denom_ms();
+    let nanos = u64::from(numer) * 1_000_000 / u64::from(denom_ms);
+    std::cmp::max(Duration::from_nanos(nanos), min_interval)
+}
+
+fn animation_info<R: BufRead + Seek>(reader: R) -> ViuResult<AnimationInfo> {
+    let reader = image::io::Reader::new(reader).with_guessed_format()?;
+    if reader.format() == Some(ImageFormat::Gif) {
+        return gif_info(reader.into_inner());
+    }
+    let (dimensions, frames) = decode_frames(reader.into_inner())?;
+
+    let min_interval = Config::default().min_frame_interval;
+    let mut frame_count = 0;
+    let mut total_duration = Duration::ZERO;
+    for frame in frames {
+        frame_count += 1;
+        total_duration += delay_to_duration(frame?.delay(), min_interval);
+    }
+
+    Ok(AnimationInfo {
+        frame_count,
+        total_duration,
+        dimensions,
+    })
+}
+
+// Collect the info of a GIF from the descriptors of its frames, skipping their pixel data.
+fn gif_info(reader: impl Read) -> ViuResult<AnimationInfo> {
+    let mut options = gif::DecodeOptions::new();
+    options.skip_frame_decoding(true);
+    let mut decoder = options.read_info(reader).map_err(gif_error)?;
+    let dimensions = (u32::from(decoder.width()), u32::from(decoder.height()));
+
+    let min_interval = Config::default().min_frame_interval;
+    let mut frame_count = 0;
+    let mut total_duration = Duration::ZERO;
+    while let Some(frame) = decoder.next_frame_info().map_err(gif_error)? {
+        frame_count += 1;
+        // GIF delays are in hundredths of a second
+        let delay = Delay::from_numer_denom_ms(u32::from(frame.delay) * 10, 1);
+        total_duration += delay_to_duration(delay, min_interval);
+    }
+
+    Ok(AnimationInfo {
+        frame_count,
+        total_duration,
+        dimensions,
+    })
+}
+
+fn gif_error(err: gif::DecodingError) -> ViuError {
+    match err {
+        gif::DecodingError::Io(err) => ViuError::Io(err),
+        err => ViuError::Image(ImageError::Decoding(DecodingError::new(
+            ImageFormatHint::Exact(ImageFormat::Gif),
+            err,
+        ))),
+    }
+}
+
+// Guess the format of the data and return the animation's dimensions together with an
+// iterator over its frames. Only formats that can hold an animation are accepted.
+pub(crate) fn decode_frames<'a, R: BufRead + Seek + 'a>(
+    reader: R,
+) -> ViuResult<((u32, u32), Frames<'a>)> {
+    let reader = image::io::Reader::new(reader).with_guessed_format()?;
+    let format = reader.format();
+    let reader = reader.into_inner();
+
+    match format {
+        Some(ImageFormat::Gif) => {
+            let decoder = GifDecoder::new(reader)?;
+            Ok((decoder.dimensions(), decoder.into_frames()))
+        }
+        Some(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(reader)?;
+            Ok((decoder.dimensions(), decoder.apng().into_frames()))
+        }
+        Some(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(reader)?;
+            Ok((decoder.dimensions(), decoder.into_frames()))
+        }
+        other => Err(
+            ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+                other.map_or(ImageFormatHint::Unknown, ImageFormatHint::Exact),
+                UnsupportedErrorKind::GenericFeature("animation".to_owned()),
+            ))
+            .into(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::printer::BlockPrinter;
+    use crate::{LoopCount, TrueColorMode};
+    use image::codecs::gif::{GifEncoder, Repeat};
+    use image::{Delay, Frame, Rgba, RgbaImage};
+    use std::io::Cursor;
+    use termcolor::Ansi;
+
+    fn gif_bytes(delays_ms: &[u32]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            for (i, delay) in delays_ms.iter().enumerate() {
+                let buffer = RgbaImage::from_pixel(6, 4, Rgba([40 * i as u8, 0, 0, 255]));
+                let frame = Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(*delay, 1));
+                encoder.encode_frame(frame).unwrap();
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_animation_info_three_frames() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, &gif_bytes(&[100, 200, 300])).unwrap();
+
+        let info = animation_info_from_file(file.path()).unwrap();
+        assert_eq!(
+            info,
+            AnimationInfo {
+                frame_count: 3,
+                total_duration: Duration::from_millis(600),
+                dimensions: (6, 4),
+            }
+        );
+    }
+
+    #[test]
+    fn test_delay_to_duration() {
+        let min = Duration::from_millis(10);
+        assert_eq!(
+            delay_to_duration(Delay::from_numer_denom_ms(70, 1), min),
+            Duration::from_millis(70)
+        );
+        // a third of a second is not a whole number of milliseconds
+        assert_eq!(
+            delay_to_duration(Delay::from_numer_denom_ms(1000, 3), min),
+            Duration::from_nanos(333_333_333)
+        );
+        assert_eq!(
+            delay_to_duration(Delay::from_numer_denom_ms(1, 4), Duration::ZERO),
+            Duration::from_micros(250)
+        );
+        assert_eq!(
+            delay_to_duration(Delay::from_numer_denom_ms(0, 1), min),
+            min
+        );
+    }
+
+    #[test]
+    fn test_play_frames_zero_delay() {
+        let bytes = gif_bytes(&[0, 30]);
+        let (_, frames) = decode_frames(Cursor::new(bytes)).unwrap();
+        let config = Config {
+            min_frame_interval: Duration::from_millis(50),
+            ..Default::default()
+        };
+
+        let mut delays = Vec::new();
+        play_frames(
+            frames,
+            &config,
+            &mut IntrinsicTiming,
+            |_, _| Ok((6, 2)),
+            |delay| delays.push(delay),
+        )
+        .unwrap();
+        assert_eq!(delays, vec![Duration::from_millis(50); 2]);
+    }
+
+    struct ConstantScheduler(Vec<(usize, Duration)>);
+
+    impl FrameScheduler for ConstantScheduler {
+        fn next_delay(&mut self, frame_index: usize, intrinsic: Duration) -> Duration {
+            self.0.push((frame_index, intrinsic));
+            Duration::from_millis(100)
+        }
+    }
+
+    #[test]
+    fn test_play_frames_custom_scheduler() {
+        let bytes = gif_bytes(&[20, 500, 1000]);
+        let (_, frames) = decode_frames(Cursor::new(bytes)).unwrap();
+
+        let mut scheduler = ConstantScheduler(Vec::new());
+        let mut delays = Vec::new();
+        play_frames(
+            frames,
+            &Config::default(),
+            &mut scheduler,
+            |_, _| Ok((6, 2)),
+            |delay| delays.push(delay),
+        )
+        .unwrap();
+
+        assert_eq!(delays, vec![Duration::from_millis(100); 3]);
+        assert_eq!(
+            scheduler.0,
+            vec![
+                (0, Duration::from_millis(20)),
+                (1, Duration::from_millis(500)),
+                (2, Duration::from_millis(1000))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_play_frames_in_order() {
+        let bytes = gif_bytes(&[100, 200, 300]);
+        let (_, frames) = decode_frames(Cursor::new(bytes)).unwrap();
+
+        let config = Config {
+            absolute_offset: false,
+            ..Default::default()
+        };
+        let mut drawn = Vec::new();
+        let mut delays = Vec::new();
+        let dimensions = play_frames(
+            frames,
+            &config,
+            &mut IntrinsicTiming,
+            |img, config| {
+                drawn.push((img.as_rgba8().unwrap().get_pixel(0, 0)[0], config.y));
+                Ok((6, 2))
+            },
+            |delay| delays.push(delay),
+        )
+        .unwrap();
+
+        assert_eq!(dimensions, (6, 2));
+        // the first frame is printed at the offset, the others go back up to the same position
+        assert_eq!(drawn, vec![(0, 0), (40, -2), (80, -2)]);
+        assert_eq!(
+            delays,
+            vec![
+                Duration::from_millis(100),
+                Duration::from_millis(200),
+                Duration::from_millis(300)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_animation_info_min_interval() {
+        // zero delays are played back at the minimum interval, and counted as such
+        let info = animation_info(Cursor::new(gif_bytes(&[0, 100]))).unwrap();
+        assert_eq!(info.frame_count, 2);
+        assert_eq!(info.total_duration, Duration::from_millis(110));
+    }
+
+    #[test]
+    fn test_animation_info_not_animated() {
+        let mut bytes = Vec::new();
+        image::DynamicImage::ImageRgba8(RgbaImage::new(2, 2))
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Bmp)
+            .unwrap();
+
+        assert!(animation_info(Cursor::new(bytes)).is_err());
+    }
+
+    #[test]
+    fn test_play_images_in_order() {
+        let frames: Vec<DynamicImage> = (0..3)
+            .map(|i| DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([i, 0, 0, 255]))))
+            .collect();
+        let delays = [
+            Duration::from_millis(50),
+            Duration::ZERO,
+            Duration::from_millis(200),
+        ];
+        let config = Config {
+            absolute_offset: false,
+            ..Default::default()
+        };
+
+        let mut drawn = Vec::new();
+        let mut waited = Vec::new();
+        play_images(
+            &frames,
+            &delays,
+            &config,
+            |img, config| {
+                drawn.push((img.as_rgba8().unwrap().get_pixel(0, 0)[0], config.y));
+                Ok((4, 2))
+            },
+            |delay| waited.push(delay),
+        )
+        .unwrap();
+
+        assert_eq!(drawn, vec![(0, 0), (1, -2), (2, -2)]);
+        assert_eq!(
+            waited,
+            vec![
+                Duration::from_millis(50),
+                config.min_frame_interval,
+                Duration::from_millis(200)
+            ]
+        );
+
+        let err = play_images(&frames, &delays[..2], &config, |_, _| Ok((4, 2)), |_| {});
+        assert!(matches!(err, Err(ViuError::InvalidConfiguration(_))));
+    }
+
+    #[test]
+    fn test_filmstrip_layout() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            for i in 0..10 {
+                let buffer = RgbaImage::from_pixel(6, 4, Rgba([20 * i, 0, 0, 255]));
+                let frame = Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(100, 1));
+                encoder.encode_frame(frame).unwrap();
+            }
+        }
+
+        // three frames of 6 pixels in 26 columns, each 8 cells wide with gaps of one cell
+        let strip = filmstrip(Cursor::new(bytes), 3, 26).unwrap().to_rgba8();
+        assert_eq!(strip.dimensions(), (6 * 3 + 2, 4));
+
+        for (i, red) in [0, 80, 180].into_iter().enumerate() {
+            let x = i as u32 * 7;
+            for dx in 0..6 {
+                assert_eq!(*strip.get_pixel(x + dx, 2), Rgba([red, 0, 0, 255]));
+            }
+            if i < 2 {
+                assert_eq!(strip.get_pixel(x + 6, 2)[3], 0);
+            }
+        }
+
+        assert!(filmstrip(Cursor::new(gif_bytes(&[100])), 0, 26).is_err());
+    }
+
+    #[test]
+    fn test_transcode_round_trip() {
+        let config = Config {
+            absolute_offset: false,
+            truecolor: TrueColorMode::Force,
+            ..Default::default()
+        };
+        let mut transcoded = Vec::new();
+        let dimensions =
+            transcode_gif(Cursor::new(gif_bytes(&[30, 70])), &mut transcoded, &config).unwrap();
+        assert_eq!(dimensions, (6, 2));
+
+        // the frames are the same as printed directly, the second one moving back up first
+        let mut expected = Vec::new();
+        for (i, y) in [(0u8, 0), (1, -2)] {
+            let frame = RgbaImage::from_pixel(6, 4, Rgba([40 * i, 0, 0, 255]));
+            let frame_config = Config {
+                y,
+                ..config.clone()
+            };
+            let mut out = Ansi::new(Vec::new());
+            BlockPrinter::write(&mut out, &DynamicImage::ImageRgba8(frame), &frame_config).unwrap();
+            expected.push(out.into_inner());
+        }
+        let record = |delay: u32, payload: &[u8]| {
+            let mut record = format!("{} {}\n", delay, payload.len()).into_bytes();
+            record.extend_from_slice(payload);
+            record
+        };
+        let mut stream = b"VIUER-ANSI 1\n".to_vec();
+        stream.extend(record(30, &expected[0]));
+        stream.extend(record(70, &expected[1]));
+        assert_eq!(transcoded, stream);
+
+        let mut out = Vec::new();
+        let mut delays = Vec::new();
+        play_transcoded_to(Cursor::new(transcoded), &mut out, &config, |d| {
+            delays.push(d)
+        })
+        .unwrap();
+        assert_eq!(out, expected.concat());
+        assert_eq!(
+            delays,
+            [Duration::from_millis(30), Duration::from_millis(70)]
+        );
+    }
+
+    #[test]
+    fn test_play_transcoded_invalid() {
+        let config = Config::default();
+        let play = |stream: &[u8]| play_transcoded_to(stream, &mut Vec::new(), &config, |_| {});
+        assert!(play(b"GIF89a").is_err());
+        assert!(play(b"VIUER-ANSI 1\n30\n").is_err());
+        // the payload ends early
+        assert!(play(b"VIUER-ANSI 1\n30 10\nabc").is_err());
+        // the length is not trusted, even if it would fit in memory
+        assert!(play(b"VIUER-ANSI 1\n30 18446744073709551615\nabc").is_err());
+        assert!(play(b"VIUER-ANSI 1\n30 1000000000\nabc").is_err());
+        assert!(play(b"VIUER-ANSI 1\n").is_ok());
+    }
+
+    // A GIF where every frame after the first only covers part of the canvas.
+    fn patched_gif_bytes() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            let background = RgbaImage::from_pixel(6, 4, Rgba([200, 0, 0, 255]));
+            encoder.encode_frame(Frame::new(background)).unwrap();
+            for i in 1..4u32 {
+                let patch = RgbaImage::from_pixel(2, 2, Rgba([0, 60 * i as u8, 0, 255]));
+                let frame = Frame::from_parts(
+                    patch,
+                    2 * (i - 1),
+                    i % 2 * 2,
+                    Delay::from_numer_denom_ms(10, 1),
+                );
+                encoder.encode_frame(frame).unwrap();
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_render_frame_at() {
+        let config = Config {
+            absolute_offset: false,
+            truecolor: TrueColorMode::Force,
+            ..Default::default()
+        };
+        let (_, frames) = decode_frames(Cursor::new(patched_gif_bytes())).unwrap();
+        let mut played = Vec::new();
+        play_frames(
+            frames,
+            &config,
+            &mut IntrinsicTiming,
+            |img, _| {
+                played.push(render_ansi(img, &config)?);
+                Ok((6, 2))
+            },
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(played.len(), 4);
+
+        let seeked = render_frame_at(Cursor::new(patched_gif_bytes()), 2, &config).unwrap();
+        assert_eq!(seeked, played[2]);
+        assert!(render_frame_at(Cursor::new(patched_gif_bytes()), 4, &config).is_err());
+
+        // forwards, repeated and backwards seeks
+        let mut seeker = FrameSeeker::new(Cursor::new(patched_gif_bytes())).unwrap();
+        for index in [2, 3, 3, 0, 2, 1] {
+            assert_eq!(seeker.render(index, &config).unwrap(), played[index]);
+        }
+        assert!(seeker.frame(4).is_err());
+        assert_eq!(seeker.render(3, &config).unwrap(), played[3]);
+    }
+
+    // A GIF with a red frame, followed by one that is transparent except for a green patch and
+    // keeps the previous frame below it.
+    fn kept_gif_bytes() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = gif::Encoder::new(&mut bytes, 6, 4, &[]).unwrap();
+            let mut red = RgbaImage::from_pixel(6, 4, Rgba([255, 0, 0, 255])).into_raw();
+            let mut patched = RgbaImage::from_fn(6, 4, |x, y| match (x, y) {
+                (2..=3, 1..=2) => Rgba([0, 255, 0, 255]),
+                _ => Rgba([0, 0, 0, 0]),
+            })
+            .into_raw();
+            for rgba in [&mut red, &mut patched] {
+                let mut frame = gif::Frame::from_rgba_speed(6, 4, rgba, 10);
+                frame.dispose = gif::DisposalMethod::Keep;
+                encoder.write_frame(&frame).unwrap();
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_transparent_frame_over_canvas() {
+        let (_, frames) = decode_frames(Cursor::new(kept_gif_bytes())).unwrap();
+        let frames: Vec<RgbaImage> = frames.map(|f| f.unwrap().into_buffer()).collect();
+        assert_eq!(frames.len(), 2);
+
+        // outside of the patch, the previous frame shows through
+        for (x, y, pixel) in frames[1].enumerate_pixels() {
+            match (x, y) {
+                (2..=3, 1..=2) => assert_eq!(*pixel, Rgba([0, 255, 0, 255])),
+                _ => assert_eq!(pixel, frames[0].get_pixel(x, y)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_redraw_clears_transparent_cells() {
+        let (_, frames) = decode_frames(Cursor::new(gif_bytes(&[10, 10]))).unwrap();
+        let config = Config {
+            transparent: true,
+            ..Default::default()
+        };
+        let mut drawn = Vec::new();
+        play_frames(
+            frames,
+            &config,
+            &mut IntrinsicTiming,
+            |_, config| {
+                drawn.push((config.y, config.transparent_as_space));
+                Ok((6, 2))
+            },
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(drawn, [(0, false), (0, true)]);
+    }
+
+    #[test]
+    fn test_play_limits() {
+        // an endlessly looping animation
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            encoder.set_repeat(Repeat::Infinite).unwrap();
+            for (i, delay) in [100, 200, 300].into_iter().enumerate() {
+                let buffer = RgbaImage::from_pixel(6, 4, Rgba([40 * i as u8, 0, 0, 255]));
+                let frame = Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(delay, 1));
+                encoder.encode_frame(frame).unwrap();
+            }
+        }
+        let play = |config: &Config| {
+            let mut drawn = Vec::new();
+            stream_frames(
+                Cursor::new(&bytes),
+                config,
+                &mut IntrinsicTiming,
+                |img, config| {
+                    drawn.push((img.as_rgba8().unwrap().get_pixel(0, 0)[0], config.y));
+                    Ok((6, 2))
+                },
+                |_| {},
+            )
+            .unwrap();
+            drawn
+        };
+
+        // played once by default
+        let colors = |drawn: Vec<(u8, i16)>| drawn.into_iter().map(|(color, _)| color).collect();
+        let once: Vec<u8> = colors(play(&Config::default()));
+        assert_eq!(once, vec![0, 40, 80]);
+
+        let config = Config {
+            loop_count: LoopCount::Infinite,
+            max_frames: Some(5),
+            absolute_offset: false,
+            ..Default::default()
+        };
+        // every loop is drawn at the position of the first frame
+        assert_eq!(
+            play(&config),
+            vec![(0, 0), (40, -2), (80, -2), (0, -2), (40, -2)]
+        );
+
+        // the frame starting at 900ms is the last one before the limit
+        let config = Config {
+            loop_count: LoopCount::Infinite,
+            max_duration: Some(Duration::from_millis(901)),
+            ..Default::default()
+        };
+        assert_eq!(play(&config).len(), 6);
+
+        let config = Config {
+            loop_count: LoopCount::Finite(2),
+            ..Default::default()
+        };
+        let twice: Vec<u8> = colors(play(&config));
+        assert_eq!(twice, vec![0, 40, 80, 0, 40, 80]);
+    }
+
+    #[test]
+    fn test_play_images_loops() {
+        let frames: Vec<DynamicImage> = (0..2)
+            .map(|i| DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([i, 0, 0, 255]))))
+            .collect();
+        let delays = [Duration::from_millis(50); 2];
+        let config = Config {
+            loop_count: LoopCount::Infinite,
+            max_frames: Some(3),
+            ..Default::default()
+        };
+        let mut drawn = Vec::new();
+        play_images(
+            &frames,
+            &delays,
+            &config,
+            |img, _| {
+                drawn.push(img.as_rgba8().unwrap().get_pixel(0, 0)[0]);
+                Ok((4, 2))
+            },
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(drawn, vec![0, 1, 0]);
+
+        // without frames, there is nothing to loop
+        let dimensions = play_images(&[], &[], &config, |_, _| Ok((4, 2)), |_| {}).unwrap();
+        assert_eq!(dimensions, (0, 0));
+    }
+
+    #[test]
+    fn test_poster_frame_draws_one_frame() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            for color in [
+                Rgba([0, 0, 0, 0]),
+                Rgba([0, 200, 0, 255]),
+                Rgba([0, 0, 200, 255]),
+            ] {
+                let buffer = RgbaImage::from_pixel(6, 4, color);
+                let frame = Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(50, 1));
+                encoder.encode_frame(frame).unwrap();
+            }
+        }
+
+        let mut drawn = Vec::new();
+        let dimensions = draw_poster(Cursor::new(&bytes), &Config::default(), |img, _| {
+            drawn.push(img.to_rgba8().get_pixel(0, 0).0);
+            Ok((6, 2))
+        })
+        .unwrap();
+        assert_eq!(dimensions, (6, 2));
+        assert_eq!(drawn, vec![[0, 200, 0, 255]]);
+
+        let config = Config {
+            poster_index: Some(2),
+            ..Default::default()
+        };
+        let mut drawn = Vec::new();
+        draw_poster(Cursor::new(&bytes), &config, |img, _| {
+            drawn.push(img.to_rgba8().get_pixel(0, 0).0);
+            Ok((6, 2))
+        })
+        .unwrap();
+        assert_eq!(drawn, vec![[0, 0, 200, 255]]);
+
+        let config = Config {
+            poster_index: Some(3),
+            ..Default::default()
+        };
+        assert!(draw_poster(Cursor::new(&bytes), &config, |_, _| Ok((0, 0))).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::error::ViuResult;
+use crate::printer::BlockPrinter;
+use crate::writer::Writer;
+use crate::Config;
+use image::DynamicImage;
+use std::io::Write;
+
+/// Collects the output of several images, each printed with its own config, and writes it to
+/// the terminal at once, so that they appear together without flickering.
+///
+/// Images are printed with half blocks like [crate::to_ansi], the graphics protocols are not
+/// used. Give every image an offset, since they are drawn one after another.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{Canvas, Config};
+///
+/// let logo = image::open("logo.png").expect("Could not open image.");
+/// let chart = image::open("chart.png").expect("Could not open image.");
+///
+/// let mut canvas = Canvas::new();
+/// canvas
+///     .draw(&logo, &Config { x: 0, y: 0, width: Some(20), ..Default::default() })
+///     .expect("Invalid configuration.");
+/// canvas
+///     .draw(&chart, &Config { x: 24, y: 0, width: Some(40), ..Default::default() })
+///     .expect("Invalid configuration.");
+/// canvas.flush().expect("Writing to the terminal failed.");
+/// ```
+pub struct Canvas {
+    buffer: termcolor::Ansi<Vec<u8>>,
+}
+
+impl Default for Canvas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Canvas {
+    /// Create an empty canvas.
+    pub fn new() -> Self {
+        Self {
+            buffer: termcolor::Ansi::new(Vec::new()),
+        }
+    }
+
+    /// Render the image into the canvas. Returns its dimensions in terminal cells.
+    pub fn draw(&mut self, img: &DynamicImage, config: &Config) -> ViuResult<(u32, u32)> {
+        BlockPrinter::write(&mut self.buffer, img, config)
+    }
+
+    /// Whether nothing was drawn since the last flush.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.get_ref().is_empty()
+    }
+
+    /// Write everything drawn so far to stdout in a single write, and clear the canvas.
+    pub fn flush(&mut self) -> ViuResult {
+        self.flush_to(&mut Writer::stdout())
+    }
+
+    /// Same as [Canvas::flush], but write to the given writer.
+    pub fn flush_to(&mut self, out: &mut impl Write) -> ViuResult {
+        let buffer = std::mem::take(self.buffer.get_mut());
+        out.write_all(&buffer)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TrueColorMode;
+
+    // Counts the writes it receives.
+    #[derive(Default)]
+    struct CountingWriter {
+        bytes: Vec<u8>,
+        writes: usize,
+    }
+
+    impl Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.writes += 1;
+            self.bytes.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_canvas_single_flush() {
+        let red = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            2,
+            2,
+            image::Rgba([255, 0, 0, 255]),
+        ));
+        let blue = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            2,
+            2,
+            image::Rgba([0, 0, 255, 255]),
+        ));
+        let config = |x, y| Config {
+            x,
+            y,
+            truecolor: TrueColorMode::Force,
+            ..Default::default()
+        };
+
+        let mut canvas = Canvas::new();
+        assert!(canvas.is_empty());
+        assert_eq!(canvas.draw(&red, &config(0, 0)).unwrap(), (2, 1));
+        assert_eq!(canvas.draw(&blue, &config(10, 5)).unwrap(), (2, 1));
+        assert!(!canvas.is_empty());
+
+        let mut out = CountingWriter::default();
+        canvas.flush_to(&mut out).unwrap();
+        assert_eq!(out.writes, 1);
+        let output = String::from_utf8(out.bytes).unwrap();
+        let red_at = output.find("\x1b[1;1H").unwrap();
+        let blue_at = output.find("\x1b[6;1H\x1b[10C").unwrap();
+        assert!(output[red_at..blue_at].contains("38;2;255;0;0"));
+        assert!(output[blue_at..].contains("38;2;0;0;255"));
+
+        // the canvas is cleared
+        assert!(canvas.is_empty());
+        let mut out = CountingWriter::default();
+        canvas.flush_to(&mut out).unwrap();
+        assert!(out.bytes.is_empty());
+    }
+}
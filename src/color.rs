@@ -0,0 +1,208 @@
+//! Color conversions used for terminals without truecolor, so that applications can match the
+//! colors viuer prints.
+//!
+//! ## Example
+//! ```
+//! use viuer::color::{rgb_to_ansi16, rgb_to_ansi256};
+//!
+//! assert_eq!(rgb_to_ansi256((255, 0, 0)), 196);
+//! assert_eq!(rgb_to_ansi16((255, 0, 0)), 9);
+//! ```
+
+// The xterm default of the 16 basic palette colors.
+pub(crate) const BASIC_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Index of the color of the 256 color palette closest to `rgb`, as printed by the block printer
+/// when truecolor is not used.
+pub fn rgb_to_ansi256(rgb: (u8, u8, u8)) -> u8 {
+    ansi_colours::ansi256_from_rgb(rgb)
+}
+
+/// Index of the color of the 16 basic colors closest to `rgb`, assuming the xterm defaults for
+/// the palette.
+pub fn rgb_to_ansi16(rgb: (u8, u8, u8)) -> u8 {
+    nearest(&BASIC_PALETTE, rgb).unwrap_or(0) as u8
+}
+
+// Rec. 709 weights of the color channels in the luminance, out of 256.
+const LUMA_WEIGHTS_FIXED: (u32, u32, u32) = (54, 183, 19);
+
+/// Luminance of the color with the Rec. 709 weights, as used by the printers, computed in fixed
+/// point. It is within 2 of the exact value, see [weighted_luminance].
+pub fn luminance((r, g, b): (u8, u8, u8)) -> u8 {
+    let (wr, wg, wb) = LUMA_WEIGHTS_FIXED;
+    ((wr * r as u32 + wg * g as u32 + wb * b as u32 + 128) >> 8) as u8
+}
+
+/// Luminance of the color with custom weights of its red, green and blue channels, which should
+/// add up to 1. Slower than [luminance], which uses fixed weights.
+pub fn weighted_luminance((r, g, b): (u8, u8, u8), weights: [f32; 3]) -> u8 {
+    let [wr, wg, wb] = weights;
+    (wr * r as f32 + wg * g as f32 + wb * b as f32)
+        .round()
+        .clamp(0.0, 255.0) as u8
+}
+
+/// Color map turning a value from 0 to 1, like a difference or the luminance of single channel
+/// data, into a color.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Colormap {
+    /// Black, through red and yellow to white.
+    #[default]
+    Heat,
+    /// Black to white.
+    Grayscale,
+    /// Perceptually uniform dark purple, through blue and green to yellow, as in matplotlib.
+    Viridis,
+    /// Perceptually uniform black, through purple and orange to light yellow, as in matplotlib.
+    Magma,
+    /// Dark blue, through cyan and yellow to dark red, as in MATLAB.
+    Jet,
+}
+
+// Evenly spaced stops of the perceptual color maps, interpolated linearly.
+const VIRIDIS: [(u8, u8, u8); 9] = [
+    (68, 1, 84),
+    (71, 44, 122),
+    (59, 81, 139),
+    (44, 113, 142),
+    (33, 144, 141),
+    (39, 173, 129),
+    (92, 200, 99),
+    (170, 220, 50),
+    (253, 231, 37),
+];
+const MAGMA: [(u8, u8, u8); 9] = [
+    (0, 0, 4),
+    (28, 16, 68),
+    (79, 18, 123),
+    (129, 37, 129),
+    (181, 54, 122),
+    (229, 80, 100),
+    (251, 135, 97),
+    (254, 194, 135),
+    (252, 253, 191),
+];
+
+impl Colormap {
+    /// The color of a value from 0 to 1, values outside of the range are clamped.
+    pub fn map(self, t: f32) -> (u8, u8, u8) {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let channel = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        match self {
+            Colormap::Grayscale => (channel(t), channel(t), channel(t)),
+            // red rises over the first third, green over the second and blue over the last
+            Colormap::Heat => (
+                channel(3.0 * t),
+                channel(3.0 * t - 1.0),
+                channel(3.0 * t - 2.0),
+            ),
+            // each channel is a trapezoid, offset from the others by a quarter
+            Colormap::Jet => (
+                channel(1.5 - (4.0 * t - 3.0).abs()),
+                channel(1.5 - (4.0 * t - 2.0).abs()),
+                channel(1.5 - (4.0 * t - 1.0).abs()),
+            ),
+            Colormap::Viridis => interpolate(&VIRIDIS, t),
+            Colormap::Magma => interpolate(&MAGMA, t),
+        }
+    }
+}
+
+// Interpolate linearly between the evenly spaced stops, with `t` from 0 to 1.
+fn interpolate(stops: &[(u8, u8, u8)], t: f32) -> (u8, u8, u8) {
+    let position = t * (stops.len() - 1) as f32;
+    let index = (position as usize).min(stops.len() - 2);
+    let fraction = position - index as f32;
+    let (from, to) = (stops[index], stops[index + 1]);
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * fraction).round() as u8;
+    (mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+}
+
+// Index of the color of the palette closest to `rgb` by euclidean distance, None if the palette
+// is empty.
+pub(crate) fn nearest(palette: &[(u8, u8, u8)], rgb: (u8, u8, u8)) -> Option<usize> {
+    let distance = |(r, g, b): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, rgb.0) + d(g, rgb.1) + d(b, rgb.2)
+    };
+    (0..palette.len()).min_by_key(|&index| distance(palette[index]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_conversions() {
+        let table = [
+            // (rgb, ansi256, ansi16)
+            ((0, 0, 0), 16, 0),
+            ((255, 255, 255), 231, 15),
+            ((255, 0, 0), 196, 9),
+            ((205, 0, 0), 160, 1),
+            ((128, 128, 128), 244, 8),
+            ((0, 0, 238), 21, 4),
+            ((95, 135, 175), 67, 8),
+            ((238, 238, 238), 255, 7),
+        ];
+        for (rgb, ansi256, ansi16) in table {
+            assert_eq!(rgb_to_ansi256(rgb), ansi256, "ansi256 of {:?}", rgb);
+            assert_eq!(rgb_to_ansi16(rgb), ansi16, "ansi16 of {:?}", rgb);
+        }
+    }
+
+    #[test]
+    fn test_colormaps() {
+        assert_eq!(Colormap::Heat.map(0.0), (0, 0, 0));
+        assert_eq!(Colormap::Heat.map(1.0 / 3.0), (255, 0, 0));
+        assert_eq!(Colormap::Heat.map(2.0 / 3.0), (255, 255, 0));
+        assert_eq!(Colormap::Heat.map(5.0), (255, 255, 255));
+
+        assert_eq!(Colormap::Jet.map(0.0), (0, 0, 128));
+        assert_eq!(Colormap::Jet.map(0.5), (128, 255, 128));
+        assert_eq!(Colormap::Jet.map(1.0), (128, 0, 0));
+
+        assert_eq!(Colormap::Viridis.map(0.5), (33, 144, 141));
+        assert_eq!(Colormap::Magma.map(1.0), (252, 253, 191));
+        assert_eq!(Colormap::Grayscale.map(-1.0), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_fixed_point_luminance() {
+        const LUMA_WEIGHTS: [f32; 3] = [0.2126, 0.7152, 0.0722];
+        for r in (0..=255).step_by(15) {
+            for g in (0..=255).step_by(15) {
+                for b in (0..=255).step_by(15) {
+                    let rgb = (r, g, b);
+                    let exact = weighted_luminance(rgb, LUMA_WEIGHTS);
+                    assert!(
+                        luminance(rgb).abs_diff(exact) <= 2,
+                        "luminance of {:?}",
+                        rgb
+                    );
+                }
+            }
+        }
+        assert_eq!(luminance((0, 0, 0)), 0);
+        assert_eq!(luminance((255, 255, 255)), 255);
+        assert_eq!(weighted_luminance((90, 0, 0), [1.0, 0.0, 0.0]), 90);
+    }
+}
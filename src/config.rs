@@ -1,10 +1,340 @@
+use crate::color::{self, Colormap};
+use crate::error::{ViuError, ViuResult};
 use crate::{utils, writer::Writer};
+use image::DynamicImage;
+use std::borrow::Cow;
+use std::time::Duration;
+
+/// What to do with the rows of an image that do not fit below its offset in the terminal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VerticalOverflow {
+    /// Keep printing, scrolling the top of the image out of view.
+    #[default]
+    Scroll,
+    /// Stop printing at the last row of the terminal.
+    Clip,
+    /// Do not print and return [crate::ViuError::InvalidConfiguration].
+    Error,
+}
+
+/// Terminator of the lines that are printed, see `line_ending` of [Config].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// A line feed, which terminals move to the start of the next line with.
+    #[default]
+    Lf,
+    /// A carriage return followed by a line feed, for output read on Windows or sent over
+    /// protocols expecting it.
+    CrLf,
+}
+
+impl LineEnding {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Point of the image that the `x` and `y` offsets refer to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Anchor {
+    /// The top left corner.
+    #[default]
+    TopLeft,
+    /// The middle of the top edge.
+    Top,
+    /// The top right corner.
+    TopRight,
+    /// The middle of the left edge.
+    Left,
+    /// The center of the image.
+    Center,
+    /// The middle of the right edge.
+    Right,
+    /// The bottom left corner.
+    BottomLeft,
+    /// The middle of the bottom edge.
+    Bottom,
+    /// The bottom right corner.
+    BottomRight,
+}
+
+impl Anchor {
+    // Position of the anchor along each axis, in halves of the width and height.
+    pub(crate) fn factors(self) -> (u32, u32) {
+        match self {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Top => (1, 0),
+            Anchor::TopRight => (2, 0),
+            Anchor::Left => (0, 1),
+            Anchor::Center => (1, 1),
+            Anchor::Right => (2, 1),
+            Anchor::BottomLeft => (0, 2),
+            Anchor::Bottom => (1, 2),
+            Anchor::BottomRight => (2, 2),
+        }
+    }
+}
+
+/// Syntax of the SGR sequences setting colors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SgrSyntax {
+    /// Parameters separated by semicolons, like `38;2;R;G;B`, which most terminals accept.
+    #[default]
+    Semicolon,
+    /// Color parameters separated by colons as in ITU T.416, like `38:2::R:G:B`, for terminals
+    /// that only accept this form.
+    Colon,
+}
+
+/// How the `x` and `y` offsets combine with the `align` option of the [Config].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AlignOffsets {
+    /// The offsets shift the aligned image, e.g. `x: 2` moves a centered image two columns to
+    /// the right of the center. A negative `y` moves it up.
+    #[default]
+    Nudge,
+    /// The offsets are ignored and the image stays where it was aligned.
+    Ignore,
+}
+
+/// A short label drawn over the image, like "NEW" on a thumbnail.
+///
+/// The text is drawn on a chip of the background color, padded by a space on either side, and
+/// clipped to the width of the image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Badge {
+    /// The label. Line breaks are drawn as spaces.
+    pub text: String,
+    /// Where the chip is placed within the image. [Badge::new] uses [Anchor::TopRight].
+    pub anchor: Anchor,
+    /// Color of the text.
+    pub fg: (u8, u8, u8),
+    /// Color of the chip.
+    pub bg: (u8, u8, u8),
+}
+
+impl Badge {
+    /// A white on red badge in the top right corner.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            anchor: Anchor::TopRight,
+            fg: (255, 255, 255),
+            bg: (200, 0, 0),
+        }
+    }
+}
+
+/// A rectangle of terminal cells, e.g. a pane of a split layout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    /// Column of the left edge, counting from 0.
+    pub x: u16,
+    /// Row of the top edge, counting from 0.
+    pub y: u16,
+    /// Number of columns.
+    pub width: u16,
+    /// Number of rows.
+    pub height: u16,
+}
+
+/// Backdrop shown behind the transparent parts of images printed with blocks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Background {
+    /// Alternating gray cells, like image editors do.
+    #[default]
+    Checkerboard,
+    /// A single RGB color.
+    Solid((u8, u8, u8)),
+    /// The terminal's own background, same as setting `transparent`.
+    Transparent,
+    /// Sparse dark dots on a light gray.
+    Dots,
+    /// Dark diagonal lines on a light gray.
+    Lines,
+}
+
+/// How the last row of cells is drawn when the image has an odd number of pixel rows, leaving
+/// the bottom half of those cells without a pixel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OddRowMode {
+    /// Draw the pixels in the top half, the terminal's background shows below them.
+    #[default]
+    HalfBlock,
+    /// Stretch the pixels over the whole cell.
+    FullBlock,
+    /// Fill the bottom half like a transparent pixel, so that the image always covers an even
+    /// number of pixel rows.
+    PadTransparent,
+}
+
+/// A way of printing images, as listed in [Config::fallback_chain].
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrinterChoice {
+    /// The Kitty graphics protocol, if the terminal supports it.
+    Kitty,
+    /// The Sixel graphics protocol, if the terminal supports it and the `sixel` feature is
+    /// enabled.
+    Sixel,
+    /// The iTerm inline images protocol, if the terminal supports it.
+    iTerm,
+    /// Half blocks in truecolor, if truecolor is available according to `truecolor`.
+    TrueColorBlock,
+    /// Half blocks in the 256 color palette, always supported.
+    Ansi256Block,
+    /// Characters from `ascii_ramp`, always supported.
+    Ascii,
+}
+
+/// Whether to print in truecolor.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrueColorMode {
+    /// Use truecolor if the `COLORTERM` environment variable announces support for it.
+    #[default]
+    Auto,
+    /// Always use truecolor, e.g. when the output is stored for a consumer known to support it.
+    Force,
+    /// Never use truecolor and stick to the 256 color palette instead.
+    Never,
+}
+
+impl From<bool> for TrueColorMode {
+    fn from(truecolor: bool) -> Self {
+        if truecolor {
+            TrueColorMode::Force
+        } else {
+            TrueColorMode::Never
+        }
+    }
+}
+
+/// How many times an animation is played, see `loop_count` of [Config].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoopCount {
+    /// Play the animation the given number of times.
+    Finite(u32),
+    /// Play the animation over and over, until `max_frames` or `max_duration` stop it.
+    Infinite,
+}
+
+impl Default for LoopCount {
+    fn default() -> Self {
+        LoopCount::Finite(1)
+    }
+}
+
+impl LoopCount {
+    // Whether the pass through the animation with the given number, counted from 1, is played.
+    pub(crate) fn plays(self, pass: u32) -> bool {
+        match self {
+            LoopCount::Finite(count) => pass <= count,
+            LoopCount::Infinite => true,
+        }
+    }
+}
+
+/// Number of colors used for a part of the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// 24-bit RGB colors.
+    Truecolor,
+    /// The 256 color ANSI palette.
+    Ansi256,
+}
+
+/// Sampling filter used when resizing images, see [image::imageops::FilterType].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FilterType {
+    /// Nearest neighbor, keeps hard edges.
+    Nearest,
+    /// Linear filter.
+    #[default]
+    Triangle,
+    /// Cubic filter.
+    CatmullRom,
+    /// Gaussian filter.
+    Gaussian,
+    /// Lanczos with window 3, the sharpest and slowest.
+    Lanczos3,
+    /// Chosen by how much the image is scaled: [FilterType::Nearest] when scaling up, to keep
+    /// small images like pixel art crisp, [FilterType::CatmullRom] when scaling down to no less
+    /// than half the size, and [FilterType::Lanczos3] when scaling down further, to avoid
+    /// aliasing. The axis scaled down the most decides.
+    Auto,
+}
+
+impl FilterType {
+    // The concrete filter for resizing an image of `from` pixels to `to` pixels.
+    pub(crate) fn resolve(self, from: (u32, u32), to: (u32, u32)) -> image::imageops::FilterType {
+        if self != FilterType::Auto {
+            return self.into();
+        }
+        let scale = f64::min(
+            to.0 as f64 / from.0.max(1) as f64,
+            to.1 as f64 / from.1.max(1) as f64,
+        );
+        if scale >= 1.0 {
+            image::imageops::FilterType::Nearest
+        } else if scale >= 0.5 {
+            image::imageops::FilterType::CatmullRom
+        } else {
+            image::imageops::FilterType::Lanczos3
+        }
+    }
+}
+
+impl From<FilterType> for image::imageops::FilterType {
+    fn from(filter: FilterType) -> Self {
+        match filter {
+            FilterType::Nearest => image::imageops::FilterType::Nearest,
+            FilterType::Triangle => image::imageops::FilterType::Triangle,
+            FilterType::CatmullRom => image::imageops::FilterType::CatmullRom,
+            FilterType::Gaussian => image::imageops::FilterType::Gaussian,
+            FilterType::Lanczos3 => image::imageops::FilterType::Lanczos3,
+            // without the sizes, fall back to the default
+            FilterType::Auto => image::imageops::FilterType::Triangle,
+        }
+    }
+}
 
 /// Configuration struct to customize printing behaviour.
+#[derive(Clone, Debug)]
 pub struct Config {
     /// Enable true transparency instead of checkerboard background.
     /// Available only for the block printer. Defaults to false.
     pub transparent: bool,
+    /// Backdrop of transparent pixels when `transparent` is false. Available only for the block
+    /// printer. Defaults to [Background::Checkerboard].
+    pub background: Background,
+    /// Optional image shown through the transparent parts of the image, e.g. to replace a green
+    /// screen. It is resized to the printed size of the image and takes precedence over
+    /// `background` and `transparent` wherever it is opaque. Available only for the block
+    /// printer. Defaults to None.
+    pub background_image: Option<DynamicImage>,
+    /// With `transparent`, print spaces with the default background for transparent cells
+    /// instead of moving the cursor over them, so that the output does not depend on what was
+    /// printed before, e.g. when it is saved to a file. Defaults to false.
+    pub transparent_as_space: bool,
+    /// Drawing of the last row of images with an odd number of pixel rows. Available only for
+    /// the block printer. Defaults to [OddRowMode::HalfBlock].
+    pub odd_row_mode: OddRowMode,
+    /// Draw the image in black and white, dithering its luminance with error diffusion so that
+    /// gradients and fine detail turn into patterns of half blocks. Trades color for detail,
+    /// e.g. for scans or monochrome plots. Available only for the block printer and ignored with
+    /// full blocks. Defaults to false.
+    pub sub_cell_dither: bool,
+    /// Draw every pixel as a full block in a cell of its own, at half the vertical resolution,
+    /// if the terminal is likely to leave gaps between half blocks. See
+    /// [crate::likely_block_font_gaps]. Defaults to false.
+    pub prefer_full_block: bool,
+    /// Width in pixels of the border along which the image fades out, so that its edges blend
+    /// into the background. With `transparent`, where partial transparency cannot be drawn, the
+    /// border is dithered instead. Available only for the block printer. Defaults to 0.
+    pub feather: u32,
     /// Make the x and y offset be relative to the top left terminal corner.
     /// If false, the y offset is relative to the cursor's position.
     /// Defaults to true.
@@ -13,6 +343,75 @@ pub struct Config {
     pub x: u16,
     /// Y offset. Can be negative only when `absolute_offset` is `false`. Defaults to 0.
     pub y: i16,
+    /// Optional pane of the terminal to print in. The image is fitted within the pane instead of
+    /// the terminal, and the offsets are relative to its top left corner, regardless of
+    /// `absolute_offset`. Defaults to None.
+    pub pane: Option<Rect>,
+    /// Margins in cells kept free on the top, right, bottom and left of the terminal, or of
+    /// `pane` if set, for displays that cut off their edges like TVs with overscan. The image is
+    /// fitted within the remaining area and the offsets are relative to its top left corner, as
+    /// with a pane. Cannot be combined with `align`. Defaults to no margins.
+    pub safe_area_inset: (u16, u16, u16, u16),
+    /// Point of the image placed at the offsets. With [Anchor::Center], an image printed at
+    /// x=40 and y=12 is centered on that cell. Defaults to [Anchor::TopLeft].
+    pub anchor: Anchor,
+    /// Start every row of the image at the column the cursor is in when printing starts, with
+    /// `x` counted from there, instead of at the left edge of the terminal. With
+    /// `absolute_offset`, `y` counts from the cursor's row as well. Meant for images following
+    /// text on the same line. Available only for the block printer. Defaults to false.
+    pub keep_cursor_column: bool,
+    /// Start printing at the cursor as it is, for callers that positioned it already: `y`, the
+    /// absolute offset and the vertical placement by `anchor` or `align` are ignored, so the
+    /// cursor is never moved up or down before printing. `x` still moves it to the right.
+    /// Defaults to false.
+    pub use_current_position: bool,
+    /// Keep the output free of cursor movements, so that it can be paged with `less -R` or
+    /// saved to a file: offsets are made of newlines and spaces, a negative `y` is ignored,
+    /// transparent cells are printed as spaces and the block printer is used unless `use_ascii`
+    /// is set. Implies `transparent_as_space` and disables `keep_cursor_column` and
+    /// `restore_cursor`. Defaults to false.
+    pub pager_safe: bool,
+    /// Lay the image out for right-to-left interfaces: it is mirrored together with its
+    /// placement, so `x` counts from the right edge of the terminal and [Anchor::TopLeft]
+    /// anchors the top right corner. An image at x=0 touches the right edge. Ignored with
+    /// `align`. Available only for the block printer. Defaults to false.
+    pub right_to_left: bool,
+    /// Optional alignment within the terminal, e.g. [Anchor::Center] to center the image. It
+    /// decides the position of the image, which is absolute, and `x` and `y` are applied on top
+    /// according to `align_offsets`. The `anchor` is not used then. Cannot be combined with
+    /// `pane`. Defaults to None.
+    pub align: Option<Anchor>,
+    /// How `x` and `y` combine with `align`. Defaults to [AlignOffsets::Nudge].
+    pub align_offsets: AlignOffsets,
+    /// Only reset the colors after printing instead of all SGR attributes, leaving styles
+    /// set by the surrounding application untouched. Available only for the block printer.
+    /// Defaults to false.
+    pub preserve_sgr: bool,
+    /// Set both colors of a cell with a single SGR sequence and only when they differ from the
+    /// previous cell's, which greatly reduces the output size of flat regions. Available only
+    /// for the block printer. Defaults to false.
+    pub compact_sgr: bool,
+    /// Syntax of the color sequences. Available only for the block printer. Defaults to
+    /// [SgrSyntax::Semicolon].
+    pub sgr_syntax: SgrSyntax,
+    /// Optional palette to print the image with: every color is replaced by the closest one of
+    /// the palette, e.g. to match a brand or the colors of an e-ink display. Without truecolor,
+    /// the palette's colors are approximated by the 256 color palette in turn. Available only
+    /// for the block printer. Defaults to None.
+    pub palette: Option<Vec<(u8, u8, u8)>>,
+    /// Optional color map to print single channel data with, e.g. depth maps or measurements:
+    /// the luminance of every pixel is mapped to a color, keeping its transparency. Available
+    /// only for the block printer. Defaults to None.
+    pub colormap: Option<Colormap>,
+    /// Optional warmth between -1 and 1 to shift the white balance by, e.g. for a night light
+    /// mode: positive values raise the red channel and lower the blue one, negative values do
+    /// the opposite and 0 leaves the colors as they are. Applied before `colormap` and
+    /// `palette`. Available only for the block printer. Defaults to None.
+    pub color_temperature: Option<f32>,
+    /// Optional weights of the red, green and blue channels in the luminance of a pixel, which
+    /// the ASCII printer, [crate::luminance_map] and `colormap` go by. They should add up to 1.
+    /// Defaults to None, which uses the Rec. 709 weights in faster fixed point arithmetic.
+    pub luma_weights: Option<[f32; 3]>,
     /// Take a note of cursor position before printing and restore it when finished.
     /// Defaults to false.
     pub restore_cursor: bool,
@@ -20,8 +419,71 @@ pub struct Config {
     pub width: Option<u32>,
     /// Optional image height. Defaults to None.
     pub height: Option<u32>,
-    /// Use truecolor if the terminal supports it. Defaults to true.
-    pub truecolor: bool,
+    /// Optional number of terminal columns the image should take up exactly. Unlike `width`,
+    /// the image may also be scaled up. If `rows` is not set, it follows from the aspect ratio.
+    /// Takes precedence over `width` and `height`. Defaults to None.
+    pub cols: Option<u32>,
+    /// Optional number of terminal rows the image should take up exactly. With the block
+    /// printer, every row holds two pixels, which viuer takes care of. If `cols` is not set, it
+    /// follows from the aspect ratio. Takes precedence over `width` and `height`.
+    /// Defaults to None.
+    pub rows: Option<u32>,
+    /// Optional upper bound for the number of columns, applied after all other sizing options.
+    /// Aspect ratio is preserved when the bound is hit. Defaults to None.
+    pub max_width: Option<u32>,
+    /// Optional upper bound for the number of rows, applied after all other sizing options.
+    /// Aspect ratio is preserved when the bound is hit. Defaults to None.
+    pub max_height: Option<u32>,
+    /// Optional lower bound for the number of columns, scaling small images up to it, e.g. to
+    /// keep icons legible. Applied after the other sizing options but before `max_width` and
+    /// `max_height`. Aspect ratio is preserved. Defaults to None.
+    pub min_width: Option<u32>,
+    /// Optional lower bound for the number of rows, like `min_width`. Defaults to None.
+    pub min_height: Option<u32>,
+    /// Optional number of rows for a banner: the image is scaled to take up exactly this many
+    /// rows and `width` columns, or else `max_width` or the terminal's width, and cropped
+    /// around its center to fill them without distortion. Takes precedence over the other
+    /// sizing options. Defaults to None.
+    pub banner_rows: Option<u32>,
+    /// Filter used when resizing the image. Defaults to [FilterType::Triangle].
+    pub filter: FilterType,
+    /// Blend every pixel with its vertical neighbors before pairing them up into half blocks,
+    /// which reduces aliasing on images with fine horizontal detail at the cost of sharpness.
+    /// Available only for the block printer. Defaults to false.
+    pub vertical_smoothing: bool,
+    /// Leave the last terminal row free when fitting the image in the terminal, so that the
+    /// prompt after it does not scroll the image. Defaults to true.
+    pub reserve_prompt_row: bool,
+    /// Optional physical (width, height) of the image in millimeters. Used by the Kitty and
+    /// iTerm printers when the terminal reports its size in pixels, otherwise `width` and
+    /// `height` are used. Defaults to None.
+    pub physical_size: Option<(f32, f32)>,
+    /// Pixel density of the screen in dots per inch, converting `physical_size` to pixels.
+    /// Defaults to 96, the reference density most desktop environments scale to.
+    pub dpi: f32,
+    /// Handling of images taller than the space left in the terminal. With `absolute_offset`
+    /// that space ends at the bottom of the terminal, otherwise the whole terminal height is
+    /// assumed to be available. Available only for the block and ASCII printers.
+    /// Defaults to [VerticalOverflow::Scroll].
+    pub vertical_overflow: VerticalOverflow,
+    /// Fraction of the image's rows that are printed, from the top. Lower values leave the
+    /// bottom out, which can be used to reveal the image gradually. Must be between 0.0 and
+    /// 1.0. Available only for the block and ASCII printers. Defaults to 1.0.
+    pub reveal_fraction: f32,
+    /// Whether to use truecolor. Defaults to [TrueColorMode::Auto], which uses it if the
+    /// terminal supports it.
+    pub truecolor: TrueColorMode,
+    /// Color depth of the backgrounds drawn by the block printer, which can differ from the
+    /// foreground's e.g. to keep them within a fixed palette. Defaults to None, which follows
+    /// `truecolor`.
+    pub bg_color_depth: Option<ColorDepth>,
+    /// Print with characters from `ascii_ramp` instead of blocks or graphics protocols.
+    /// Defaults to false.
+    pub use_ascii: bool,
+    /// Characters used by the ASCII printer, ordered from darkest to brightest. Wide characters
+    /// are supported and narrower ones are padded to the widest. Defaults to None, which uses
+    /// [crate::DEFAULT_ASCII_RAMP].
+    pub ascii_ramp: Option<String>,
     /// Use Kitty protocol if the terminal supports it. Defaults to true.
     pub use_kitty: bool,
     /// Use iTerm protocol if the terminal supports it. Defaults to true.
@@ -29,29 +491,666 @@ pub struct Config {
     /// Use Sixel protocol if the terminal supports it. Defaults to true.
     #[cfg(feature = "sixel")]
     pub use_sixel: bool,
+    /// Ordered list of the ways of printing to try, the first one the terminal supports is
+    /// used. If none of them is supported, the image is printed with half blocks. `use_kitty`,
+    /// `use_iterm` and `use_sixel` are not consulted then, while `use_ascii` and `pager_safe`
+    /// still take precedence. Defaults to None, which detects Sixel, iTerm and Kitty in this
+    /// order and falls back to half blocks.
+    pub fallback_chain: Option<Vec<PrinterChoice>>,
+    /// Shortest time a frame of an animation stays on screen. Shorter delays, most often zero,
+    /// are raised to it. Defaults to 10 milliseconds.
+    pub min_frame_interval: Duration,
+    /// How many times [crate::stream_animation] and [crate::print_frames] play an animation.
+    /// Defaults to [LoopCount::Finite] of 1.
+    pub loop_count: LoopCount,
+    /// Optional largest number of frames of an animation to print, counted across all loops,
+    /// after which playback stops, e.g. to bound previews of endlessly repeating animations.
+    /// Defaults to None.
+    pub max_frames: Option<usize>,
+    /// Optional longest time an animation plays, measured by the delays of its frames. No frame
+    /// is printed once it has passed. Defaults to None.
+    pub max_duration: Option<Duration>,
+    /// Optional index of the frame shown by [crate::poster_frame]. Defaults to None, which shows
+    /// the first frame that is not fully transparent.
+    pub poster_index: Option<usize>,
+    /// Flush the output after every chunk of data sent with the Kitty protocol, not just at the
+    /// end. Helps with multiplexers or connections that hold back output until it is flushed.
+    /// Defaults to false.
+    pub flush_each_chunk: bool,
+    /// Clear what a graphics protocol drew at the position of the image before drawing it, so
+    /// that images redrawn in place do not pile up. The Kitty printer deletes the images at the
+    /// top left cell, the iTerm printer erases the cells the image covers. Defaults to false.
+    pub clear_before_draw: bool,
+    /// Debugging aid drawing a faint crosshair through the center of the image, to check its
+    /// alignment. Available only for the block printer. Defaults to false.
+    pub overlay_grid: bool,
+    /// Optional interval in cells and color of separator lines for a tiled, mosaic look: after
+    /// every `n` columns and rows of cells, one is drawn in the separator color instead, except
+    /// where the image is transparent. The separators hide the part of the image they fall on,
+    /// so that its size does not change. With [crate::print_grid], `n` counts the pixels of the
+    /// grid instead and the separators are inserted between their blocks, making the grid larger
+    /// without hiding any of it. Available only for the block printer. Defaults to None.
+    pub mosaic_gap: Option<(u32, (u8, u8, u8))>,
+    /// Optional badge drawn over the image after it, see [Badge]. Available only for the block
+    /// printer. Defaults to None.
+    pub badge: Option<Badge>,
+    /// Optional mask with a row of flags for every row of printed cells, e.g. for a spotlight
+    /// revealing part of the image. Cells whose flag is false are left untouched like
+    /// transparent ones. Its dimensions have to match the printed cells, mirrored along with
+    /// the image with `right_to_left`. Available only for the block printer. Defaults to None.
+    pub cell_mask: Option<Vec<Vec<bool>>>,
+    /// Follow the image with an escape sequence describing it, which terminals do not display:
+    /// `ESC ] 1337 ; ViuerMetadata=original=WxH;rendered=CxR;printer=NAME BEL`. The original
+    /// size is in pixels and left out when unknown, the rendered one in terminal cells.
+    /// Meant for tools parsing the output. Defaults to false.
+    pub emit_metadata: bool,
     /// Print to stderr instead of stdout. Defaults to false.
     pub use_stderr: bool,
+    /// Always write colors as ANSI escape sequences, even on Windows consoles where the console
+    /// API would be used otherwise, so that redirected or captured output is portable. Available
+    /// only for the block printer. Defaults to false.
+    pub force_ansi: bool,
+    /// Terminator of every line break that is printed, including the one after the image and
+    /// the ones moving down by a relative `y`. Rows of the block and ASCII printers always end
+    /// with a carriage return before it. Defaults to [LineEnding::Lf].
+    pub line_ending: LineEnding,
     /// Override writer method
     pub override_writer: Option<Writer>,
 }
 
+impl Config {
+    /// Config for small previews that fit in a box of `max_cols` x `max_rows` terminal cells.
+    ///
+    /// Aspect ratio is preserved and images are only ever scaled down, so a small image stays
+    /// small. This differs from setting both `width` and `height`, which stretches the image to
+    /// exactly those dimensions. Truecolor is detected as usual and a smoother filter than the
+    /// default is used, as previews are usually scaled down considerably.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use viuer::{print_from_file, Config};
+    ///
+    /// print_from_file("img.jpg", &Config::thumbnail(20, 10)).expect("Image printing failed.");
+    /// ```
+    pub fn thumbnail(max_cols: u32, max_rows: u32) -> Config {
+        Config {
+            max_width: Some(max_cols),
+            max_height: Some(max_rows),
+            filter: FilterType::CatmullRom,
+            ..Default::default()
+        }
+    }
+
+    /// Check the options that contradict each other or can never be printed, returning
+    /// [ViuError::InvalidConfiguration] describing the first one found. The printing functions
+    /// call it before printing anything, but it can also be used to check a config ahead of time.
+    ///
+    /// ## Example
+    /// ```
+    /// use viuer::Config;
+    ///
+    /// let conf = Config {
+    ///     y: -2,
+    ///     ..Default::default()
+    /// };
+    /// assert!(conf.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> ViuResult {
+        if self.absolute_offset && self.y < 0 && self.align.is_none() {
+            return Err(ViuError::InvalidConfiguration(
+                "absolute_offset is true but y offset is negative".to_owned(),
+            ));
+        }
+
+        let dimensions = [
+            ("width", self.width),
+            ("height", self.height),
+            ("cols", self.cols),
+            ("rows", self.rows),
+            ("max_width", self.max_width),
+            ("max_height", self.max_height),
+            ("min_width", self.min_width),
+            ("min_height", self.min_height),
+            ("banner_rows", self.banner_rows),
+        ];
+        for (name, value) in dimensions {
+            if value == Some(0) {
+                return Err(ViuError::InvalidConfiguration(format!("{} is zero", name)));
+            }
+        }
+
+        if let Some(weights) = self.luma_weights {
+            if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+                return Err(ViuError::InvalidConfiguration(format!(
+                    "luma_weights must not be negative, got {:?}",
+                    weights
+                )));
+            }
+        }
+
+        if let Some(warmth) = self.color_temperature {
+            if !(-1.0..=1.0).contains(&warmth) {
+                return Err(ViuError::InvalidConfiguration(format!(
+                    "color_temperature must be between -1 and 1, got {}",
+                    warmth
+                )));
+            }
+        }
+
+        if self.loop_count == LoopCount::Finite(0) {
+            return Err(ViuError::InvalidConfiguration(
+                "loop_count is zero".to_owned(),
+            ));
+        }
+
+        if self.mosaic_gap.is_some_and(|(n, _)| n == 0) {
+            return Err(ViuError::InvalidConfiguration(
+                "mosaic_gap interval is zero".to_owned(),
+            ));
+        }
+
+        if self.palette.as_ref().is_some_and(Vec::is_empty) {
+            return Err(ViuError::InvalidConfiguration(
+                "palette is empty".to_owned(),
+            ));
+        }
+
+        if self.align.is_some() && self.pane.is_some() {
+            return Err(ViuError::InvalidConfiguration(
+                "align cannot be combined with pane".to_owned(),
+            ));
+        }
+        if self.align.is_some() && self.safe_area_inset != (0, 0, 0, 0) {
+            return Err(ViuError::InvalidConfiguration(
+                "align cannot be combined with safe_area_inset".to_owned(),
+            ));
+        }
+
+        if let Some(pane) = self.pane {
+            if pane.width == 0 || pane.height == 0 {
+                return Err(ViuError::InvalidConfiguration(format!(
+                    "pane is {}x{} cells",
+                    pane.width, pane.height
+                )));
+            }
+        }
+
+        if let Some((w, h)) = self.physical_size {
+            if !(w.is_finite() && h.is_finite() && w > 0.0 && h > 0.0) {
+                return Err(ViuError::InvalidConfiguration(format!(
+                    "physical_size must be positive, got {}x{}",
+                    w, h
+                )));
+            }
+        }
+
+        if !(self.dpi.is_finite() && self.dpi > 0.0) {
+            return Err(ViuError::InvalidConfiguration(format!(
+                "dpi must be positive, got {}",
+                self.dpi
+            )));
+        }
+
+        if !(0.0..=1.0).contains(&self.reveal_fraction) {
+            return Err(ViuError::InvalidConfiguration(format!(
+                "reveal_fraction must be between 0 and 1, got {}",
+                self.reveal_fraction
+            )));
+        }
+
+        Ok(())
+    }
+
+    // The config fitting the image within the pane or the safe area, if any, with the offsets
+    // relative to the top left corner of the terminal.
+    pub(crate) fn in_pane(&self) -> Cow<'_, Config> {
+        let pane = match (self.pane, self.safe_area_inset) {
+            (None, (0, 0, 0, 0)) => return Cow::Borrowed(self),
+            (Some(pane), (0, 0, 0, 0)) => pane,
+            (pane, inset) => {
+                let (width, height) = utils::terminal_size();
+                inset_rect(
+                    pane.unwrap_or(Rect {
+                        x: 0,
+                        y: 0,
+                        width,
+                        height,
+                    }),
+                    inset,
+                )
+            }
+        };
+        let bound =
+            |max: Option<u32>, size: u16| Some(max.map_or(size as u32, |m| m.min(size as u32)));
+        Cow::Owned(Config {
+            pane: None,
+            safe_area_inset: (0, 0, 0, 0),
+            absolute_offset: true,
+            x: pane.x.saturating_add(self.x),
+            y: utils::saturating_i16(pane.y).saturating_add(self.y.max(0)),
+            max_width: bound(self.max_width, pane.width),
+            max_height: bound(self.max_height, pane.height),
+            ..self.clone()
+        })
+    }
+
+    // The config with the offsets moved from the anchor to the top left corner of an image that
+    // takes up (w, h) terminal cells, or to where the alignment places it. An absolute offset
+    // never goes above the top of the terminal.
+    pub(crate) fn anchored(&self, (w, h): (u32, u32)) -> Cow<'_, Config> {
+        if let Some(align) = self.align {
+            return Cow::Owned(self.aligned(align, (w, h)));
+        }
+        if self.right_to_left {
+            return Cow::Owned(self.mirrored((w, h)));
+        }
+        if self.anchor == Anchor::TopLeft {
+            return Cow::Borrowed(self);
+        }
+        let (fx, fy) = self.anchor.factors();
+        let dx = utils::saturating_u16(w.saturating_mul(fx) / 2);
+        let dy = utils::saturating_i16(h.saturating_mul(fy) / 2);
+
+        let mut y = self.y.saturating_sub(dy);
+        if self.absolute_offset {
+            y = y.max(0);
+        }
+        Cow::Owned(Config {
+            x: self.x.saturating_sub(dx),
+            y,
+            anchor: Anchor::TopLeft,
+            ..self.clone()
+        })
+    }
+
+    // The config for output free of cursor movements, see `pager_safe`.
+    pub(crate) fn for_pager(&self) -> Cow<'_, Config> {
+        if !self.pager_safe {
+            return Cow::Borrowed(self);
+        }
+        Cow::Owned(Config {
+            transparent_as_space: true,
+            keep_cursor_column: false,
+            restore_cursor: false,
+            ..self.clone()
+        })
+    }
+
+    // The config placing an image of (w, h) terminal cells for right-to-left layouts. The
+    // placement is worked out in a mirrored terminal, where x counts from the left edge again,
+    // and the left edge found there becomes the right edge of the image.
+    fn mirrored(&self, (w, h): (u32, u32)) -> Config {
+        let (term_w, _) = utils::terminal_size();
+        let placed = Config {
+            right_to_left: false,
+            ..self.clone()
+        }
+        .anchored((w, h))
+        .into_owned();
+        Config {
+            x: term_w
+                .saturating_sub(placed.x)
+                .saturating_sub(utils::saturating_u16(w)),
+            ..placed
+        }
+    }
+
+    // The config with absolute offsets placing an image of (w, h) terminal cells at the alignment
+    // within the terminal, nudged by the offsets if requested.
+    fn aligned(&self, align: Anchor, (w, h): (u32, u32)) -> Config {
+        let (term_w, term_h) = utils::terminal_size();
+        let (fx, fy) = align.factors();
+        let x = (term_w as u32).saturating_sub(w) * fx / 2;
+        let y = (term_h as u32).saturating_sub(h) * fy / 2;
+
+        let (dx, dy) = match self.align_offsets {
+            AlignOffsets::Nudge => (self.x, self.y),
+            AlignOffsets::Ignore => (0, 0),
+        };
+        Config {
+            absolute_offset: true,
+            x: utils::saturating_u16(x).saturating_add(dx),
+            y: utils::saturating_i16(y).saturating_add(dy).max(0),
+            align: None,
+            anchor: Anchor::TopLeft,
+            ..self.clone()
+        }
+    }
+
+    // Luminance of the color, with the custom weights if there are any.
+    pub(crate) fn luminance(&self, rgb: (u8, u8, u8)) -> u8 {
+        match self.luma_weights {
+            Some(weights) => color::weighted_luminance(rgb, weights),
+            None => color::luminance(rgb),
+        }
+    }
+
+    // Whether colors are printed in truecolor, detecting support if not set explicitly.
+    pub(crate) fn use_truecolor(&self) -> bool {
+        match self.truecolor {
+            TrueColorMode::Auto => utils::truecolor_available(),
+            TrueColorMode::Force => true,
+            TrueColorMode::Never => false,
+        }
+    }
+
+    // Whether backgrounds are printed in truecolor, taking `bg_color_depth` into account.
+    pub(crate) fn bg_truecolor(&self) -> bool {
+        match self.bg_color_depth {
+            Some(depth) => depth == ColorDepth::Truecolor,
+            None => self.use_truecolor(),
+        }
+    }
+}
+
 impl std::default::Default for Config {
     fn default() -> Self {
         Self {
             transparent: false,
+            background: Background::Checkerboard,
+            background_image: None,
+            transparent_as_space: false,
+            odd_row_mode: OddRowMode::HalfBlock,
+            sub_cell_dither: false,
+            prefer_full_block: false,
+            feather: 0,
             absolute_offset: true,
             x: 0,
             y: 0,
+            pane: None,
+            safe_area_inset: (0, 0, 0, 0),
+            anchor: Anchor::TopLeft,
+            keep_cursor_column: false,
+            use_current_position: false,
+            pager_safe: false,
+            right_to_left: false,
+            align: None,
+            align_offsets: AlignOffsets::Nudge,
+            preserve_sgr: false,
+            compact_sgr: false,
+            sgr_syntax: SgrSyntax::Semicolon,
+            palette: None,
+            colormap: None,
+            color_temperature: None,
+            luma_weights: None,
             restore_cursor: false,
             width: None,
             height: None,
-            truecolor: utils::truecolor_available(),
+            cols: None,
+            rows: None,
+            max_width: None,
+            max_height: None,
+            min_width: None,
+            min_height: None,
+            banner_rows: None,
+            filter: FilterType::Triangle,
+            vertical_smoothing: false,
+            reserve_prompt_row: true,
+            physical_size: None,
+            dpi: 96.0,
+            vertical_overflow: VerticalOverflow::Scroll,
+            reveal_fraction: 1.0,
+            truecolor: TrueColorMode::Auto,
+            bg_color_depth: None,
+            use_ascii: false,
+            ascii_ramp: None,
             use_kitty: true,
             use_iterm: true,
             #[cfg(feature = "sixel")]
             use_sixel: true,
+            fallback_chain: None,
+            min_frame_interval: Duration::from_millis(10),
+            loop_count: LoopCount::Finite(1),
+            max_frames: None,
+            max_duration: None,
+            poster_index: None,
+            flush_each_chunk: false,
+            clear_before_draw: false,
+            overlay_grid: false,
+            mosaic_gap: None,
+            badge: None,
+            cell_mask: None,
+            emit_metadata: false,
             use_stderr: false,
+            force_ansi: false,
+            line_ending: LineEnding::Lf,
             override_writer: None,
         }
     }
 }
+
+// The rectangle left inside the margins on the top, right, bottom and left, at least one cell
+// large.
+fn inset_rect(rect: Rect, (top, right, bottom, left): (u16, u16, u16, u16)) -> Rect {
+    let shrink = |size: u16, a: u16, b: u16| size.saturating_sub(a).saturating_sub(b).max(1);
+    Rect {
+        x: rect.x.saturating_add(left),
+        y: rect.y.saturating_add(top),
+        width: shrink(rect.width, left, right),
+        height: shrink(rect.height, top, bottom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_invalid(config: Config) {
+        assert!(matches!(
+            config.validate(),
+            Err(ViuError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_default() {
+        assert!(Config::default().validate().is_ok());
+        assert!(Config::thumbnail(20, 10).validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_negative_absolute_y() {
+        assert_invalid(Config {
+            y: -1,
+            ..Default::default()
+        });
+        let config = Config {
+            absolute_offset: false,
+            y: -1,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        // aligned images are nudged up from where they are aligned
+        let config = Config {
+            align: Some(Anchor::Center),
+            y: -1,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        assert_invalid(Config {
+            pane: Some(Rect {
+                x: 0,
+                y: 0,
+                width: 10,
+                height: 10,
+            }),
+            ..config
+        });
+    }
+
+    #[test]
+    fn test_validate_zero_dimensions() {
+        assert_invalid(Config {
+            width: Some(0),
+            ..Default::default()
+        });
+        assert_invalid(Config {
+            height: Some(0),
+            ..Default::default()
+        });
+        assert_invalid(Config {
+            cols: Some(0),
+            ..Default::default()
+        });
+        assert_invalid(Config {
+            rows: Some(0),
+            ..Default::default()
+        });
+        assert_invalid(Config::thumbnail(0, 10));
+        assert_invalid(Config::thumbnail(10, 0));
+    }
+
+    #[test]
+    fn test_validate_physical_size() {
+        assert_invalid(Config {
+            physical_size: Some((0.0, 10.0)),
+            ..Default::default()
+        });
+        assert_invalid(Config {
+            physical_size: Some((10.0, f32::NAN)),
+            ..Default::default()
+        });
+        let config = Config {
+            physical_size: Some((25.4, 10.0)),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        assert_invalid(Config {
+            dpi: 0.0,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_validate_reveal_fraction() {
+        for fraction in [-0.1, 1.5, f32::NAN] {
+            assert_invalid(Config {
+                reveal_fraction: fraction,
+                ..Default::default()
+            });
+        }
+        let config = Config {
+            reveal_fraction: 0.0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_loop_count() {
+        assert_invalid(Config {
+            loop_count: LoopCount::Finite(0),
+            ..Default::default()
+        });
+        let config = Config {
+            loop_count: LoopCount::Infinite,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_anchored_offsets() {
+        let mut config = Config {
+            x: 40,
+            y: 12,
+            anchor: Anchor::Center,
+            ..Default::default()
+        };
+        let anchored = config.anchored((10, 10));
+        assert_eq!((anchored.x, anchored.y), (35, 7));
+        assert_eq!(anchored.anchor, Anchor::TopLeft);
+
+        config.anchor = Anchor::BottomRight;
+        let anchored = config.anchored((10, 10));
+        assert_eq!((anchored.x, anchored.y), (30, 2));
+
+        // an absolute offset stays within the terminal, a relative one can move up
+        config.anchor = Anchor::Bottom;
+        let anchored = config.anchored((100, 20));
+        assert_eq!((anchored.x, anchored.y), (0, 0));
+        config.absolute_offset = false;
+        assert_eq!(config.anchored((100, 20)).y, -8);
+
+        config.anchor = Anchor::TopLeft;
+        assert!(matches!(config.anchored((10, 10)), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_in_pane() {
+        let config = Config {
+            pane: Some(Rect {
+                x: 5,
+                y: 5,
+                width: 20,
+                height: 10,
+            }),
+            absolute_offset: false,
+            x: 1,
+            max_height: Some(4),
+            ..Default::default()
+        };
+        let in_pane = config.in_pane();
+        assert!(in_pane.absolute_offset);
+        assert_eq!((in_pane.x, in_pane.y), (6, 5));
+        assert_eq!((in_pane.max_width, in_pane.max_height), (Some(20), Some(4)));
+        assert_eq!(in_pane.pane, None);
+
+        assert_invalid(Config {
+            pane: Some(Rect::default()),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_safe_area_inset() {
+        let config = Config {
+            safe_area_inset: (1, 2, 3, 4),
+            absolute_offset: false,
+            x: 1,
+            ..Default::default()
+        };
+        let inset = config.in_pane();
+        assert!(inset.absolute_offset);
+        assert_eq!((inset.x, inset.y), (5, 1));
+        assert_eq!((inset.max_width, inset.max_height), (Some(74), Some(20)));
+        assert_eq!(inset.safe_area_inset, (0, 0, 0, 0));
+
+        // within a pane, the margins are kept inside of it
+        let config = Config {
+            pane: Some(Rect {
+                x: 10,
+                y: 2,
+                width: 20,
+                height: 10,
+            }),
+            ..config
+        };
+        let inset = config.in_pane();
+        assert_eq!((inset.x, inset.y), (15, 3));
+        assert_eq!((inset.max_width, inset.max_height), (Some(14), Some(6)));
+
+        assert_invalid(Config {
+            safe_area_inset: (1, 1, 1, 1),
+            align: Some(Anchor::Center),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_auto_filter() {
+        use image::imageops::FilterType as Resolved;
+
+        let auto = FilterType::Auto;
+        assert_eq!(auto.resolve((16, 16), (64, 64)), Resolved::Nearest);
+        assert_eq!(auto.resolve((16, 16), (16, 16)), Resolved::Nearest);
+        assert_eq!(auto.resolve((100, 100), (60, 60)), Resolved::CatmullRom);
+        assert_eq!(auto.resolve((4000, 3000), (80, 48)), Resolved::Lanczos3);
+        // the axis scaled down the most decides
+        assert_eq!(auto.resolve((100, 10), (40, 40)), Resolved::Lanczos3);
+
+        // explicit filters are kept
+        let nearest = FilterType::Nearest;
+        assert_eq!(nearest.resolve((4000, 3000), (80, 48)), Resolved::Nearest);
+    }
+}
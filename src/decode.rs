@@ -0,0 +1,219 @@
+use crate::error::{ViuError, ViuResult};
+use base64::{engine::general_purpose, Engine};
+use image::{io::Reader, DynamicImage, ImageFormat};
+use std::io::Cursor;
+use std::path::Path;
+
+// A JPEG XL file starts with either a bare codestream or a container box.
+const JXL_CODESTREAM_SIGNATURE: &[u8] = &[0xff, 0x0a];
+const JXL_CONTAINER_SIGNATURE: &[u8] = &[
+    0x00, 0x00, 0x00, 0x0c, b'J', b'X', b'L', b' ', 0x0d, 0x0a, 0x87, 0x0a,
+];
+
+// Decode the image in the file, guessing its format from the content or else the extension.
+pub(crate) fn decode_file<P: AsRef<Path>>(filename: P) -> ViuResult<DynamicImage> {
+    let bytes = std::fs::read(&filename)?;
+    decode(&bytes, ImageFormat::from_path(filename).ok())
+}
+
+// Decode the image, guessing its format from the content or else the given hint. Formats that
+// need an optional feature are reported as unsupported when it is disabled.
+pub(crate) fn decode(bytes: &[u8], hint: Option<ImageFormat>) -> ViuResult<DynamicImage> {
+    if is_jxl(bytes) {
+        return decode_jxl(bytes);
+    }
+
+    let guessed = image::guess_format(bytes).ok();
+    if guessed == Some(ImageFormat::Avif) && cfg!(not(feature = "avif")) {
+        return Err(ViuError::UnsupportedFormat(
+            "AVIF images require the `avif` feature".to_owned(),
+        ));
+    }
+
+    let mut reader = Reader::new(Cursor::new(bytes));
+    if let Some(format) = guessed.or(hint) {
+        reader.set_format(format);
+    }
+    Ok(reader.decode()?)
+}
+
+// Decode the image in a `data:` URI with a base64 payload, like `data:image/png;base64,iVBO...`.
+// The media type is used as a hint for the format.
+pub(crate) fn decode_data_uri(uri: &str) -> ViuResult<DynamicImage> {
+    let invalid = |reason: &str| ViuError::InvalidConfiguration(format!("data URI {}", reason));
+
+    let uri = uri.trim();
+    let rest = match uri.get(..5) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("data:") => &uri[5..],
+        _ => return Err(invalid("does not start with `data:`")),
+    };
+    let (meta, payload) = rest
+        .split_once(',')
+        .ok_or_else(|| invalid("has no `,` before the data"))?;
+    let media_type = match meta.strip_suffix(";base64") {
+        Some(media_type) => media_type,
+        None => return Err(invalid("is not base64 encoded")),
+    };
+
+    // line breaks are common in long URIs
+    let payload: String = payload.split_whitespace().collect();
+    let bytes = general_purpose::STANDARD
+        .decode(payload)
+        .map_err(|e| invalid(&format!("has invalid base64 data: {}", e)))?;
+    let mime = media_type.split(';').next().unwrap_or_default();
+    decode(&bytes, ImageFormat::from_mime_type(mime))
+}
+
+pub(crate) fn is_jxl(bytes: &[u8]) -> bool {
+    bytes.starts_with(JXL_CODESTREAM_SIGNATURE) || bytes.starts_with(JXL_CONTAINER_SIGNATURE)
+}
+
+#[cfg(not(feature = "jxl"))]
+fn decode_jxl(_bytes: &[u8]) -> ViuResult<DynamicImage> {
+    Err(ViuError::UnsupportedFormat(
+        "JPEG XL images require the `jxl` feature".to_owned(),
+    ))
+}
+
+// Decode the first frame of a JPEG XL image with its color and alpha channels.
+#[cfg(feature = "jxl")]
+fn decode_jxl(bytes: &[u8]) -> ViuResult<DynamicImage> {
+    use image::{GrayAlphaImage, GrayImage, RgbImage, RgbaImage};
+
+    let invalid = |e: Box<dyn std::error::Error + Send + Sync>| {
+        ViuError::UnsupportedFormat(format!("JPEG XL: {}", e))
+    };
+    let jxl = jxl_oxide::JxlImage::builder()
+        .read(bytes)
+        .map_err(invalid)?;
+    let render = jxl.render_frame(0).map_err(invalid)?;
+
+    let mut stream = render.stream();
+    let (width, height) = (stream.width(), stream.height());
+    let mut buf = vec![0u8; (width * height * stream.channels()) as usize];
+    stream.write_to_buffer(&mut buf);
+
+    let img = match stream.channels() {
+        1 => GrayImage::from_raw(width, height, buf).map(DynamicImage::ImageLuma8),
+        2 => GrayAlphaImage::from_raw(width, height, buf).map(DynamicImage::ImageLumaA8),
+        3 => RgbImage::from_raw(width, height, buf).map(DynamicImage::ImageRgb8),
+        4 if !jxl.pixel_format().has_black() => {
+            RgbaImage::from_raw(width, height, buf).map(DynamicImage::ImageRgba8)
+        }
+        _ => None,
+    };
+    img.ok_or_else(|| {
+        ViuError::UnsupportedFormat(format!(
+            "JPEG XL images with {:?} pixels",
+            jxl.pixel_format()
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 240x135 JPEG XL image, from the documentation of jxl-oxide.
+    const SMALL_JXL: &[u8] = &[
+        0xff, 0x0a, 0x30, 0x54, 0x10, 0x09, 0x08, 0x06, 0x01, 0x00, 0x78, 0x00, 0x4b, 0x38, 0x41,
+        0x3c, 0xb6, 0x3a, 0x51, 0xfe, 0x00, 0x47, 0x1e, 0xa0, 0x85, 0xb8, 0x27, 0x1a, 0x48, 0x45,
+        0x84, 0x1b, 0x71, 0x4f, 0xa8, 0x3e, 0x8e, 0x30, 0x03, 0x92, 0x84, 0x01,
+    ];
+
+    // The start of an AVIF file, enough to tell its format.
+    #[cfg(not(feature = "avif"))]
+    const AVIF_HEADER: &[u8] = &[
+        0x00, 0x00, 0x00, 0x1c, b'f', b't', b'y', b'p', b'a', b'v', b'i', b'f', 0x00, 0x00, 0x00,
+        0x00,
+    ];
+
+    #[test]
+    fn test_decode_png() {
+        let img = image::RgbaImage::from_pixel(3, 2, image::Rgba([1, 2, 3, 255]));
+        let mut png = Vec::new();
+        DynamicImage::ImageRgba8(img.clone())
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        assert!(!is_jxl(&png));
+        assert_eq!(decode(&png, None).unwrap().to_rgba8(), img);
+    }
+
+    #[cfg(not(feature = "jxl"))]
+    #[test]
+    fn test_decode_jxl_disabled() {
+        assert!(is_jxl(SMALL_JXL));
+        assert!(matches!(
+            decode(SMALL_JXL, None),
+            Err(ViuError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[cfg(feature = "jxl")]
+    #[test]
+    fn test_decode_jxl() {
+        let img = decode(SMALL_JXL, None).unwrap();
+        assert_eq!((img.width(), img.height()), (240, 135));
+
+        let mut buf = termcolor::Ansi::new(vec![]);
+        let config = crate::Config {
+            absolute_offset: false,
+            width: Some(40),
+            ..Default::default()
+        };
+        let printed = crate::printer::BlockPrinter::write(&mut buf, &img, &config).unwrap();
+        assert_eq!(printed, (40, 11));
+    }
+
+    #[cfg(not(feature = "avif"))]
+    #[test]
+    fn test_decode_avif_disabled() {
+        assert!(matches!(
+            decode(AVIF_HEADER, None),
+            Err(ViuError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_data_uri() {
+        let img = image::RgbaImage::from_pixel(2, 2, image::Rgba([9, 8, 7, 255]));
+        let mut png = Vec::new();
+        DynamicImage::ImageRgba8(img.clone())
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        let uri = format!(
+            "data:image/png;base64,{}",
+            general_purpose::STANDARD.encode(&png)
+        );
+
+        let decoded = decode_data_uri(&uri).unwrap();
+        assert_eq!(decoded.to_rgba8(), img);
+        let mut buf = termcolor::Ansi::new(vec![]);
+        let config = crate::Config {
+            absolute_offset: false,
+            ..Default::default()
+        };
+        let printed = crate::printer::BlockPrinter::write(&mut buf, &decoded, &config).unwrap();
+        assert_eq!(printed, (2, 1));
+        let wrapped = format!("DATA:{}\n", uri[5..].replacen("iVBO", "iV\nBO", 1));
+        assert_eq!(decode_data_uri(&wrapped).unwrap().to_rgba8(), img);
+
+        for malformed in [
+            "image/png;base64,iVBO",
+            "data:image/png;base64",
+            "data:image/png,iVBO",
+            "data:image/png;base64,not base64!",
+            "data:",
+        ] {
+            assert!(
+                matches!(
+                    decode_data_uri(malformed),
+                    Err(ViuError::InvalidConfiguration(_))
+                ),
+                "{} was accepted",
+                malformed
+            );
+        }
+    }
+}
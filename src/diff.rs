@@ -0,0 +1,184 @@
+use crate::color::Colormap;
+use crate::error::{ViuError, ViuResult};
+use crate::Config;
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+// Largest relative difference between the aspect ratios of the images, unless stretched.
+const MAX_ASPECT_DIFFERENCE: f64 = 0.1;
+
+/// How [crate::print_diff] brings images of different sizes to a common one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DiffResize {
+    /// Resize the second image to the size of the first, returning
+    /// [ViuError::InvalidConfiguration] if their aspect ratios differ by more than 10%.
+    #[default]
+    Fit,
+    /// Resize the second image to the size of the first, whatever their aspect ratios.
+    Stretch,
+}
+
+/// Options for [crate::print_diff].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiffOptions {
+    /// Factor the differences are multiplied with, so that small ones stand out. Defaults to 1.
+    pub amplify: f32,
+    /// Color map of the differences. Defaults to [Colormap::Heat].
+    pub colormap: Colormap,
+    /// How images of different sizes are compared. Defaults to [DiffResize::Fit].
+    pub resize: DiffResize,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            amplify: 1.0,
+            colormap: Colormap::Heat,
+            resize: DiffResize::Fit,
+        }
+    }
+}
+
+/// Print a heatmap of the differences between two images, e.g. to compare screenshots in tests.
+///
+/// Every pixel shows the mean absolute difference of the color channels of the two images at its
+/// position, amplified and colored according to the options. The heatmap has the size of the
+/// first image and is printed like [crate::print]. Returns its dimensions in terminal cells.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{print_diff, Config, DiffOptions};
+///
+/// let expected = image::open("expected.png").expect("Could not open image.");
+/// let actual = image::open("actual.png").expect("Could not open image.");
+/// let options = DiffOptions {
+///     amplify: 4.0,
+///     ..Default::default()
+/// };
+/// print_diff(&expected, &actual, &options, &Config::default()).expect("Diff printing failed.");
+/// ```
+pub fn print_diff(
+    a: &DynamicImage,
+    b: &DynamicImage,
+    options: &DiffOptions,
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    let heatmap = diff_image(a, b, options)?;
+    crate::print(&heatmap, config)
+}
+
+fn diff_image(
+    a: &DynamicImage,
+    b: &DynamicImage,
+    options: &DiffOptions,
+) -> ViuResult<DynamicImage> {
+    if !options.amplify.is_finite() || options.amplify < 0.0 {
+        return Err(ViuError::InvalidConfiguration(format!(
+            "amplify must not be negative, got {}",
+            options.amplify
+        )));
+    }
+
+    let (width, height) = a.dimensions();
+    let (b_width, b_height) = b.dimensions();
+    if width == 0 || height == 0 || b_width == 0 || b_height == 0 {
+        return Err(ViuError::InvalidConfiguration(
+            "cannot compare empty images".to_owned(),
+        ));
+    }
+    let aspect = |w: u32, h: u32| w as f64 / h as f64;
+    let ratio = aspect(width, height) / aspect(b_width, b_height);
+    if options.resize == DiffResize::Fit && (ratio - 1.0).abs() > MAX_ASPECT_DIFFERENCE {
+        return Err(ViuError::InvalidConfiguration(format!(
+            "cannot compare {}x{} and {}x{} images without stretching",
+            width, height, b_width, b_height
+        )));
+    }
+
+    let a = a.to_rgba8();
+    let b = match (b_width, b_height) == (width, height) {
+        true => b.to_rgba8(),
+        false => b
+            .resize_exact(width, height, image::imageops::FilterType::Triangle)
+            .to_rgba8(),
+    };
+    let heatmap = RgbaImage::from_fn(width, height, |x, y| {
+        let (pa, pb) = (a.get_pixel(x, y), b.get_pixel(x, y));
+        let sum: u32 = (0..3).map(|c| pa[c].abs_diff(pb[c]) as u32).sum();
+        let difference = sum as f32 / (3.0 * 255.0);
+        let (r, g, b) = options.colormap.map(difference * options.amplify);
+        Rgba([r, g, b, 255])
+    });
+    Ok(DynamicImage::ImageRgba8(heatmap))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn luma(pixel: &Rgba<u8>) -> u32 {
+        pixel.0[..3].iter().map(|&c| c as u32).sum()
+    }
+
+    #[test]
+    fn test_diff_region() {
+        let a = RgbaImage::from_pixel(8, 8, Rgba([100, 100, 100, 255]));
+        let mut b = a.clone();
+        for (x, y, pixel) in b.enumerate_pixels_mut() {
+            if x >= 4 && y >= 4 {
+                *pixel = Rgba([160, 100, 40, 255]);
+            }
+        }
+        let (a, b) = (DynamicImage::ImageRgba8(a), DynamicImage::ImageRgba8(b));
+
+        for colormap in [Colormap::Heat, Colormap::Grayscale] {
+            let options = DiffOptions {
+                amplify: 2.0,
+                colormap,
+                ..Default::default()
+            };
+            let heatmap = diff_image(&a, &b, &options).unwrap().to_rgba8();
+            let changed = luma(heatmap.get_pixel(6, 6));
+            assert!(changed > 0);
+            for (x, y, pixel) in heatmap.enumerate_pixels() {
+                match x >= 4 && y >= 4 {
+                    true => assert_eq!(luma(pixel), changed),
+                    false => assert_eq!(luma(pixel), 0),
+                }
+            }
+        }
+
+        // amplifying makes the difference brighter
+        let plain = diff_image(&a, &b, &DiffOptions::default()).unwrap();
+        let options = DiffOptions {
+            amplify: 3.0,
+            ..Default::default()
+        };
+        let amplified = diff_image(&a, &b, &options).unwrap();
+        assert!(
+            luma(amplified.to_rgba8().get_pixel(6, 6)) > luma(plain.to_rgba8().get_pixel(6, 6))
+        );
+    }
+
+    #[test]
+    fn test_diff_sizes() {
+        let a = DynamicImage::ImageRgba8(RgbaImage::new(20, 10));
+        let half = DynamicImage::ImageRgba8(RgbaImage::new(10, 5));
+        let square = DynamicImage::ImageRgba8(RgbaImage::new(10, 10));
+
+        let heatmap = diff_image(&a, &half, &DiffOptions::default()).unwrap();
+        assert_eq!(heatmap.dimensions(), (20, 10));
+        assert!(matches!(
+            diff_image(&a, &square, &DiffOptions::default()),
+            Err(ViuError::InvalidConfiguration(_))
+        ));
+
+        let options = DiffOptions {
+            resize: DiffResize::Stretch,
+            ..Default::default()
+        };
+        assert_eq!(
+            diff_image(&a, &square, &options).unwrap().dimensions(),
+            (20, 10)
+        );
+    }
+}
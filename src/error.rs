@@ -12,10 +12,10 @@ pub enum ViuError {
     InvalidConfiguration(String),
     /// Error while creating temp files
     Tempfile(tempfile::PersistError),
-    /// Errenous response received from Kitty
-    KittyResponse(Vec<console::Key>),
     /// Kitty protocol not supported
     KittyNotSupported,
+    /// The image format cannot be decoded, e.g. because the feature enabling it is disabled
+    UnsupportedFormat(String),
     /// Error while printing with sixel
     #[cfg(feature = "sixel")]
     SixelError(sixel_rs::status::Error),
@@ -54,8 +54,8 @@ impl std::fmt::Display for ViuError {
             ViuError::Io(e) => write!(f, "IO error: {}", e),
             ViuError::InvalidConfiguration(s) => write!(f, "Invalid Configuration: {}", s),
             ViuError::Tempfile(e) => write!(f, "Tempfile error: {}", e),
-            ViuError::KittyResponse(keys) => write!(f, "Kitty response: {:?}", keys),
             ViuError::KittyNotSupported => write!(f, "Kitty graphics protocol not supported"),
+            ViuError::UnsupportedFormat(s) => write!(f, "Unsupported format: {}", s),
             #[cfg(feature = "sixel")]
             ViuError::SixelError(e) => write!(f, "Sixel error: {:?}", e),
         }
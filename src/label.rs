@@ -0,0 +1,156 @@
+use image::{DynamicImage, GenericImage, GenericImageView, Pixel, Rgba};
+
+// Glyphs are 3 pixels wide and 5 tall, followed by a column and a row of spacing.
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+const ADVANCE: u32 = GLYPH_WIDTH + 1;
+const LINE_HEIGHT: u32 = GLYPH_HEIGHT + 1;
+
+// Rows of the glyphs from top to bottom, the highest of the 3 bits being the left pixel.
+// Lowercase letters are drawn as uppercase ones.
+const FONT: [(char, [u8; 5]); 50] = [
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b001, 0b001]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    (' ', [0b000, 0b000, 0b000, 0b000, 0b000]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    (',', [0b000, 0b000, 0b000, 0b010, 0b100]),
+    (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+    ('=', [0b000, 0b111, 0b000, 0b111, 0b000]),
+    ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+    ('%', [0b101, 0b001, 0b010, 0b100, 0b101]),
+    ('(', [0b010, 0b100, 0b100, 0b100, 0b010]),
+    (')', [0b010, 0b001, 0b001, 0b001, 0b010]),
+    ('#', [0b101, 0b111, 0b101, 0b111, 0b101]),
+    ('_', [0b000, 0b000, 0b000, 0b000, 0b111]),
+    ('?', [0b111, 0b001, 0b010, 0b000, 0b010]),
+];
+
+/// Draw a short text onto the image with a tiny built-in bitmap font, e.g. coordinates or the
+/// length of a scale bar, so that the label is part of the pixels and scales with the image.
+///
+/// `(x, y)` is the top left corner of the label in pixels. Glyphs are 3x5 pixels with a pixel of
+/// spacing, and a newline starts another line below. Digits, letters (drawn uppercase), spaces
+/// and `.,:-+=/%()#_?` are supported, other characters are drawn as `?`. The color is blended
+/// over the image according to its alpha, and parts of the label outside of the image are left
+/// out. Since a terminal cell is made of only a few pixels, labels stay readable when the image
+/// is not shrunk much, e.g. after upscaling it first.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{add_label, print, Config};
+///
+/// let mut img = image::open("plot.png").expect("Could not open image.");
+/// add_label(&mut img, "0.5 MM", 2, 2, image::Rgba([255, 255, 255, 255]));
+/// print(&img, &Config::default()).expect("Image printing failed.");
+/// ```
+pub fn add_label(img: &mut DynamicImage, text: &str, x: u32, y: u32, color: Rgba<u8>) {
+    let (width, height) = img.dimensions();
+    for (line, text) in text.lines().enumerate() {
+        let top = y.saturating_add(line as u32 * LINE_HEIGHT);
+        for (index, c) in text.chars().enumerate() {
+            let left = x.saturating_add(index as u32 * ADVANCE);
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                        continue;
+                    }
+                    let (px, py) = (left.saturating_add(col), top.saturating_add(row as u32));
+                    if px < width && py < height {
+                        let mut pixel = img.get_pixel(px, py);
+                        pixel.blend(&color);
+                        img.put_pixel(px, py, pixel);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// The rows of the glyph for the character, `?` if the font lacks it.
+fn glyph(c: char) -> [u8; 5] {
+    let c = c.to_ascii_uppercase();
+    FONT.iter()
+        .find(|(glyph, _)| *glyph == c)
+        .or_else(|| FONT.iter().find(|(glyph, _)| *glyph == '?'))
+        .map_or([0; 5], |(_, rows)| *rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn test_add_label() {
+        let background = Rgba([20, 20, 20, 255]);
+        let original = DynamicImage::ImageRgba8(RgbaImage::from_pixel(16, 12, background));
+        let mut img = original.clone();
+        add_label(&mut img, "42", 2, 3, Rgba([255, 0, 0, 255]));
+
+        // the top row of 4 is 101 and the one of 2 is 111
+        assert_eq!(img.get_pixel(2, 3), Rgba([255, 0, 0, 255]));
+        assert_eq!(img.get_pixel(3, 3), background);
+        assert_eq!(img.get_pixel(4, 3), Rgba([255, 0, 0, 255]));
+        assert_eq!(img.get_pixel(7, 3), Rgba([255, 0, 0, 255]));
+        // nothing is drawn outside of the label
+        let changed = original
+            .pixels()
+            .filter(|&(x, y, pixel)| img.get_pixel(x, y) != pixel)
+            .map(|(x, y, _)| (x, y))
+            .collect::<Vec<_>>();
+        assert!(changed
+            .iter()
+            .all(|&(x, y)| (2..9).contains(&x) && (3..8).contains(&y)));
+        assert_eq!(changed.len(), 9 + 11);
+    }
+
+    #[test]
+    fn test_add_label_blends_and_clips() {
+        let mut img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([0, 0, 200, 255])));
+        add_label(&mut img, "i\n~", 2, 2, Rgba([200, 0, 0, 128]));
+        let blended = img.get_pixel(2, 2);
+        assert!(blended[0] > 90 && blended[0] < 110, "{:?}", blended);
+        assert!(blended[2] > 90 && blended[2] < 110, "{:?}", blended);
+
+        // far outside of the image
+        add_label(&mut img, "88", u32::MAX - 3, 1, Rgba([255, 255, 255, 255]));
+        assert_eq!(glyph('~'), glyph('?'));
+        assert_eq!(glyph('a'), glyph('A'));
+    }
+}
@@ -26,31 +26,75 @@
 //! print_from_file("img.jpg", &conf).expect("Image printing failed.");
 //! ```
 
+use std::borrow::Cow;
+use std::io::Write;
 use std::path::Path;
 
 use crossterm::{
     cursor::{RestorePosition, SavePosition},
     execute,
 };
-use image::DynamicImage;
+use image::{DynamicImage, GenericImageView};
 use printer::{Printer, PrinterType};
 
+mod animation;
+mod canvas;
+pub mod color;
 mod config;
+mod decode;
+mod diff;
 mod error;
+mod label;
+mod line_sink;
 mod printer;
+mod query;
+mod renderer;
+mod report;
+mod stats;
 mod string_writer;
 mod utils;
+mod view;
+#[cfg(feature = "ratatui")]
+mod widget;
 mod writer;
-pub use config::Config;
+pub use animation::{
+    animation_info_from_file, play_transcoded, poster_frame, print_filmstrip, print_frames,
+    render_frame_at, stream_animation, transcode_gif, AnimationInfo, FrameScheduler, FrameSeeker,
+    IntrinsicTiming,
+};
+pub use canvas::Canvas;
+pub use color::Colormap;
+pub use config::{
+    AlignOffsets, Anchor, Background, Badge, ColorDepth, Config, FilterType, LineEnding, LoopCount,
+    OddRowMode, PrinterChoice, Rect, SgrSyntax, TrueColorMode, VerticalOverflow,
+};
+pub use diff::{print_diff, DiffOptions, DiffResize};
 pub use error::{ViuError, ViuResult};
-pub use printer::{get_kitty_support, is_iterm_supported, resize, KittySupport};
+pub use label::add_label;
+use line_sink::LineSink;
+pub use printer::{
+    bar_cells, get_kitty_quirks, get_kitty_support, is_iterm_supported, luminance_map, prepare,
+    print_ansi_art, refresh_terminal_capabilities, render_cells, render_layer, resize, Cell,
+    KittyQuirks, KittySupport, PrinterCapabilities, DEFAULT_ASCII_RAMP,
+};
+pub use query::{query_terminal, set_query_timeout};
+pub use renderer::Renderer;
+pub use report::{capabilities_report, CapabilitiesReport};
+pub use stats::{print_from_file_with_stats, print_with_stats, RenderStats};
 use string_writer::StringWriter;
-pub use utils::terminal_size;
-pub use writer::Writer;
+pub use utils::{
+    is_dark_background, is_headless, likely_block_font_gaps, set_default_terminal_size,
+    terminal_size, terminal_size_pixels,
+};
+pub use view::{CaptionAlign, ImageView};
+pub use writer::{OutputTarget, Writer};
 
 #[cfg(feature = "sixel")]
 pub use printer::is_sixel_supported;
 
+#[cfg(feature = "ratatui")]
+pub use widget::ViuWidget;
+
 /// Default printing method. Uses either iTerm or Kitty graphics protocol, if supported,
 /// and half blocks otherwise.
 ///
@@ -75,12 +119,27 @@ pub use printer::is_sixel_supported;
 /// print(&img, &Config::default()).expect("Image printing failed.");
 /// ```
 pub fn print(img: &DynamicImage, config: &Config) -> ViuResult<(u32, u32)> {
+    config.validate()?;
+    let config = &*config.for_pager();
+    if let Cow::Owned(config) = config.in_pane() {
+        return print(img, &config);
+    }
+    if let Some((img, config)) = printer::banner(img, config) {
+        return print(&img, &config);
+    }
     let mut stdout = &mut Writer::from_config(config);
     if config.restore_cursor {
         execute!(&mut stdout, SavePosition)?;
     }
 
-    let (w, h) = choose_printer(config).print(&mut stdout, img, config)?;
+    let config = &*chain_config(config, &TERMINAL_PROBES);
+    let printer = choose_printer(config);
+    let (w, h) = printer.print(&mut stdout, img, config)?;
+    if config.emit_metadata {
+        let trailer = printer::metadata_trailer(Some(img.dimensions()), (w, h), printer.name());
+        write!(stdout, "{}", trailer)?;
+        stdout.flush()?;
+    }
 
     if config.restore_cursor {
         execute!(&mut stdout, RestorePosition)?;
@@ -89,6 +148,57 @@ pub fn print(img: &DynamicImage, config: &Config) -> ViuResult<(u32, u32)> {
     Ok((w, h))
 }
 
+/// Decode the image in a `data:` URI with a base64 payload, like the ones in web pages or
+/// clipboards, and print it like [print].
+///
+/// Returns [ViuError::InvalidConfiguration] if the URI is malformed or its payload is not base64
+/// encoded.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{print_from_data_uri, Config};
+///
+/// let uri = std::fs::read_to_string("image.uri").expect("Could not read file.");
+/// print_from_data_uri(&uri, &Config::default()).expect("Image printing failed.");
+/// ```
+pub fn print_from_data_uri(uri: &str, config: &Config) -> ViuResult<(u32, u32)> {
+    print(&decode::decode_data_uri(uri)?, config)
+}
+
+/// Print a raw buffer of RGBA pixels, e.g. a frame of a game or a video decoder, like [print].
+///
+/// The buffer holds `width` times `height` pixels of four bytes each, row by row. It is copied
+/// once to build the image. Returns [ViuError::InvalidConfiguration] if its length does not
+/// match the dimensions.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{print_rgba, Config};
+///
+/// let frame = vec![255u8; 64 * 32 * 4];
+/// print_rgba(&frame, 64, 32, &Config::default()).expect("Image printing failed.");
+/// ```
+pub fn print_rgba(data: &[u8], width: u32, height: u32, config: &Config) -> ViuResult<(u32, u32)> {
+    print(&rgba_image(data, width, height)?, config)
+}
+
+fn rgba_image(data: &[u8], width: u32, height: u32) -> ViuResult<DynamicImage> {
+    let expected = width as u64 * height as u64 * 4;
+    if data.len() as u64 != expected {
+        return Err(ViuError::InvalidConfiguration(format!(
+            "{}x{} RGBA pixels take {} bytes, got {}",
+            width,
+            height,
+            expected,
+            data.len()
+        )));
+    }
+    let img = image::RgbaImage::from_raw(width, height, data.to_vec()).ok_or_else(|| {
+        ViuError::InvalidConfiguration(format!("{}x{} pixels are too many", width, height))
+    })?;
+    Ok(DynamicImage::ImageRgba8(img))
+}
+
 /// Default printing method. Uses either iTerm or Kitty graphics protocol, if supported,
 /// and half blocks otherwise.
 ///
@@ -123,6 +233,269 @@ pub fn to_ansi(img: &DynamicImage, config: &Config) -> ViuResult<String> {
     Ok(stdout.read().to_string())
 }
 
+/// Render the image with half blocks like [to_ansi], but as an HTML `<pre>` block, e.g. to show
+/// a terminal render in documentation or a blog post.
+///
+/// Every cell is a `<span>` with an inline style setting its colors, cells that would leave the
+/// terminal untouched are plain spaces. The offsets of the config are ignored.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{render_to_html, Config};
+///
+/// let img = image::open("img.jpg").expect("Could not open image.");
+/// let html = render_to_html(&img, &Config::default()).expect("Invalid configuration.");
+/// std::fs::write("img.html", html).expect("Could not write file.");
+/// ```
+pub fn render_to_html(img: &DynamicImage, config: &Config) -> ViuResult<String> {
+    config.validate()?;
+    let cells = render_cells(img, config)?;
+    Ok(cells_to_html(&cells))
+}
+
+fn cells_to_html(cells: &[Vec<Cell>]) -> String {
+    let css = |(r, g, b): (u8, u8, u8)| format!("#{:02x}{:02x}{:02x}", r, g, b);
+    let mut html = String::from("<pre style=\"line-height:1;font-family:monospace\">");
+    for (index, row) in cells.iter().enumerate() {
+        if index > 0 {
+            html.push('\n');
+        }
+        for cell in row {
+            let mut style = Vec::with_capacity(2);
+            if let Some(fg) = cell.fg {
+                style.push(format!("color:{}", css(fg)));
+            }
+            if let Some(bg) = cell.bg {
+                style.push(format!("background:{}", css(bg)));
+            }
+            if style.is_empty() {
+                html.push(' ');
+            } else {
+                html.push_str(&format!(
+                    "<span style=\"{}\">{}</span>",
+                    style.join(";"),
+                    cell.glyph
+                ));
+            }
+        }
+    }
+    html.push_str("</pre>");
+    html
+}
+
+/// Render the image with half blocks like [to_ansi], but hand the output to `sink` one line at
+/// a time as soon as it is produced, instead of collecting all of it first.
+///
+/// Useful to forward the output to a remote client without waiting for the whole image.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{stream_ansi, Config};
+///
+/// let img = image::DynamicImage::ImageRgba8(image::RgbaImage::new(20, 10));
+/// let mut sent = 0;
+/// stream_ansi(&img, &Config::default(), |line| sent += line.len())
+///     .expect("Image rendering failed.");
+/// ```
+pub fn stream_ansi(
+    img: &DynamicImage,
+    config: &Config,
+    sink: impl FnMut(&[u8]),
+) -> ViuResult<(u32, u32)> {
+    let mut stdout = termcolor::Ansi::new(LineSink::new(sink));
+    printer::BlockPrinter::write(&mut stdout, img, config)
+}
+
+/// Render the image with half blocks like [to_ansi] and save the output to a file, so that it can
+/// be shown again later with [replay] without decoding or resizing the image.
+///
+/// The offsets, size and colors are fixed when saving. Returns the dimensions of the render.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{replay, save_render, Config};
+///
+/// let img = image::open("banner.png").expect("Could not open image.");
+/// save_render(&img, &Config::default(), "banner.ansi").expect("Image rendering failed.");
+/// replay("banner.ansi").expect("Replaying the render failed.");
+/// ```
+pub fn save_render<P: AsRef<Path>>(
+    img: &DynamicImage,
+    config: &Config,
+    path: P,
+) -> ViuResult<(u32, u32)> {
+    let mut stdout = termcolor::Ansi::new(Vec::new());
+    let dimensions = printer::BlockPrinter::write(&mut stdout, img, config)?;
+    std::fs::write(path, stdout.into_inner())?;
+    Ok(dimensions)
+}
+
+/// Print a render saved with [save_render] to stdout.
+pub fn replay<P: AsRef<Path>>(path: P) -> ViuResult {
+    replay_to(&mut Writer::stdout(), path)
+}
+
+fn replay_to<P: AsRef<Path>>(stdout: &mut impl Write, path: P) -> ViuResult {
+    let bytes = std::fs::read(path)?;
+    stdout.write_all(&bytes)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Print a grid of data, like a heatmap, where every pixel of `grid` is drawn as a visually
+/// square block of full blocks. The number of columns and rows per block follows from the size
+/// of a terminal cell in pixels, if the terminal reports it, and is 2x1 otherwise.
+///
+/// The sizing options of the config are ignored and the graphics protocols are not used.
+/// Returns the dimensions of the printed grid in terminal cells.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{print_grid, Config};
+///
+/// let heatmap = image::RgbaImage::from_fn(8, 4, |x, y| {
+///     image::Rgba([x as u8 * 32, 0, y as u8 * 64, 255])
+/// });
+/// print_grid(&image::DynamicImage::ImageRgba8(heatmap), &Config::default())
+///     .expect("Grid printing failed.");
+/// ```
+pub fn print_grid(grid: &DynamicImage, config: &Config) -> ViuResult<(u32, u32)> {
+    let mut stream = printer::standard_stream(config);
+    printer::BlockPrinter::write_grid(&mut stream, grid, config)
+}
+
+/// Print the rows of the image across several regions of the terminal, e.g. to split a tall
+/// image between the top and bottom strips of a dashboard.
+///
+/// The image is sized with the config like [print] does with the block printer. Its rows are
+/// then handed out to the regions in order, each region taking as many rows as it is tall and
+/// clipping them to its width. Rows left over once all regions are filled are not printed.
+/// The offsets of the config are ignored and the graphics protocols are not used. Returns the
+/// width of the widest printed row and the number of printed rows, in terminal cells.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{print_split, Config, Rect};
+///
+/// let img = image::open("tall.png").expect("Could not open image.");
+/// let top = Rect { x: 0, y: 0, width: 40, height: 5 };
+/// let bottom = Rect { x: 0, y: 20, width: 40, height: 5 };
+/// let conf = Config {
+///     width: Some(40),
+///     ..Default::default()
+/// };
+/// print_split(&img, &[top, bottom], &conf).expect("Image printing failed.");
+/// ```
+pub fn print_split(img: &DynamicImage, regions: &[Rect], config: &Config) -> ViuResult<(u32, u32)> {
+    let mut stream = printer::standard_stream(config);
+    printer::BlockPrinter::write_split(&mut stream, img, regions, config)
+}
+
+/// Print a horizontal bar `cols` cells long, filled from the left by `fill` in steps of an eighth
+/// of a cell. See [bar_cells] for the details.
+///
+/// Only the offset and color options of the config are used. Returns the dimensions of the bar
+/// in terminal cells.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{print_bar, Config};
+///
+/// let conf = Config {
+///     absolute_offset: false,
+///     ..Default::default()
+/// };
+/// print_bar(0.42, 20, (0, 200, 0), &conf).expect("Bar printing failed.");
+/// ```
+pub fn print_bar(
+    fill: f32,
+    cols: u32,
+    color: (u8, u8, u8),
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    let mut stream = printer::standard_stream(config);
+    printer::BlockPrinter::write_bar(&mut stream, fill, cols, color, config)
+}
+
+/// Print the image with half blocks at the cursor's position instead of at the start of a line,
+/// so that text printed afterwards continues to the right of the image's last row. Meant for
+/// small images embedded in text, like emojis.
+///
+/// The offsets of the config are ignored and the graphics protocols are not used. The cursor
+/// position is asked of the terminal, waiting at most as long as [set_query_timeout] allows.
+/// Returns the cursor position (column, row) after the image.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{print_inline, Config};
+///
+/// let img = image::open("icon.png").expect("Could not open image.");
+/// let conf = Config {
+///     height: Some(1),
+///     ..Default::default()
+/// };
+/// print!("Status: ");
+/// print_inline(&img, &conf).expect("Image printing failed.");
+/// println!(" all good");
+/// ```
+pub fn print_inline(img: &DynamicImage, config: &Config) -> ViuResult<(u16, u16)> {
+    let cursor = printer::cursor_position()?;
+    let mut stream = printer::standard_stream(config);
+    printer::BlockPrinter::write_inline(&mut stream, img, config, cursor)
+}
+
+/// Print the images one below the other, all with the same width in terminal cells. The width
+/// is the narrowest one any of the images would get on its own with the given config.
+///
+/// The offsets of the config apply to the first image and the others follow directly below it.
+/// Returns the total number of rows taken up by the images.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{print_stack, Config};
+///
+/// let images = ["a.jpg", "b.jpg"].map(|path| image::open(path).expect("Could not open image."));
+/// let rows = print_stack(&images, &Config::default()).expect("Image printing failed.");
+/// println!("printed {} rows", rows);
+/// ```
+pub fn print_stack(images: &[DynamicImage], config: &Config) -> ViuResult<u32> {
+    config.validate()?;
+    stack(images, config, print)
+}
+
+// Draw the images below each other with a common width, returning the number of rows drawn.
+fn stack(
+    images: &[DynamicImage],
+    config: &Config,
+    mut draw: impl FnMut(&DynamicImage, &Config) -> ViuResult<(u32, u32)>,
+) -> ViuResult<u32> {
+    let mut width = None;
+    for img in images {
+        let (w, _) = printer::fit_for_config(img, config)?;
+        width = Some(width.map_or(w, |width: u32| width.min(w)));
+    }
+
+    let mut rows = 0;
+    for (i, img) in images.iter().enumerate() {
+        let mut img_config = Config {
+            width,
+            height: None,
+            cols: None,
+            rows: None,
+            ..config.clone()
+        };
+        // every image starts on the line after the previous one
+        if config.absolute_offset {
+            img_config.y = config.y.saturating_add(utils::saturating_i16(rows));
+        } else if i > 0 {
+            img_config.y = 0;
+        }
+        let (_, h) = draw(img, &img_config)?;
+        rows += h;
+    }
+    Ok(rows)
+}
+
 /// Helper method that reads a file, tries to decode it and prints it.
 ///
 /// ## Example
@@ -138,12 +511,32 @@ pub fn to_ansi(img: &DynamicImage, config: &Config) -> ViuResult<String> {
 /// print_from_file("img.jpg", &conf).expect("Image printing failed.");
 /// ```
 pub fn print_from_file<P: AsRef<Path>>(filename: P, config: &Config) -> ViuResult<(u32, u32)> {
+    config.validate()?;
+    let config = &*config.for_pager();
+    if let Cow::Owned(config) = config.in_pane() {
+        return print_from_file(filename, &config);
+    }
+    if config.banner_rows.is_some() {
+        let img = decode::decode_file(filename)?;
+        return print(&img, config);
+    }
     let mut stdout = Writer::from_config(config);
     if config.restore_cursor {
         execute!(&mut stdout, SavePosition)?;
     }
 
-    let (w, h) = choose_printer(config).print_from_file(&mut stdout, filename, config)?;
+    let config = &*chain_config(config, &TERMINAL_PROBES);
+    let printer = choose_printer(config);
+    let original = match config.emit_metadata {
+        true => image::image_dimensions(&filename).ok(),
+        false => None,
+    };
+    let (w, h) = printer.print_from_file(&mut stdout, filename, config)?;
+    if config.emit_metadata {
+        let trailer = printer::metadata_trailer(original, (w, h), printer.name());
+        write!(stdout, "{}", trailer)?;
+        stdout.flush()?;
+    }
 
     if config.restore_cursor {
         execute!(&mut stdout, RestorePosition)?;
@@ -152,18 +545,328 @@ pub fn print_from_file<P: AsRef<Path>>(filename: P, config: &Config) -> ViuResul
     Ok((w, h))
 }
 
+/// Describe the printer that [print] would use with the given config, so that callers can
+/// adjust the image or the config to what it can show.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{printer_capabilities, Config};
+///
+/// if !printer_capabilities(&Config::default()).transparency {
+///     println!("transparent pixels will be drawn over a background");
+/// }
+/// ```
+pub fn printer_capabilities(config: &Config) -> PrinterCapabilities {
+    choose_printer(config).capabilities()
+}
+
+/// Resize the image to the pixels drawn by the printer that [crate::print] chooses with the
+/// given config, e.g. to process the image at the resolution it is shown in.
+///
+/// The block printer draws two rows of pixels per terminal cell with half blocks, like
+/// [resize], but only one with full blocks, see `prefer_full_block`. The graphics protocols
+/// draw at the terminal's own resolution, so the image is kept as it is when that is unknown.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{resize_for_config, Config};
+///
+/// let img = image::open("img.jpg").expect("Could not open image.");
+/// let resized = resize_for_config(&img, &Config::default()).expect("Invalid configuration.");
+/// println!("{}x{} pixels will be drawn", resized.width(), resized.height());
+/// ```
+pub fn resize_for_config(img: &DynamicImage, config: &Config) -> ViuResult<DynamicImage> {
+    config.validate()?;
+    let full_blocks = printer::use_full_blocks(config);
+    printer::resize_for_printer(img, config, &choose_printer(config), full_blocks)
+}
+
+// Checks of what the terminal supports. They may query the terminal, and so are only made when
+// needed.
+pub(crate) struct Probes {
+    pub(crate) kitty: fn() -> bool,
+    #[cfg(feature = "sixel")]
+    pub(crate) sixel: fn() -> bool,
+    pub(crate) iterm: fn() -> bool,
+}
+
+// The checks of the terminal viuer runs in.
+pub(crate) const TERMINAL_PROBES: Probes = Probes {
+    kitty: || get_kitty_support() != KittySupport::None,
+    #[cfg(feature = "sixel")]
+    sixel: is_sixel_supported,
+    iterm: is_iterm_supported,
+};
+
 // Choose the appropriate printer to use based on user config and availability
 fn choose_printer(config: &Config) -> PrinterType {
+    choose_printer_with(config, &TERMINAL_PROBES)
+}
+
+pub(crate) fn choose_printer_with(config: &Config, probes: &Probes) -> PrinterType {
+    if config.use_ascii {
+        return PrinterType::Ascii;
+    }
+    if config.pager_safe {
+        return PrinterType::Block;
+    }
+
+    if config.fallback_chain.is_some() {
+        return match chain_choice(config, probes) {
+            Some(PrinterChoice::Kitty) => PrinterType::Kitty,
+            #[cfg(feature = "sixel")]
+            Some(PrinterChoice::Sixel) => PrinterType::Sixel,
+            Some(PrinterChoice::iTerm) => PrinterType::iTerm,
+            Some(PrinterChoice::Ascii) => PrinterType::Ascii,
+            _ => PrinterType::Block,
+        };
+    }
+
     #[cfg(feature = "sixel")]
-    if config.use_sixel && is_sixel_supported() {
+    if config.use_sixel && (probes.sixel)() {
         return PrinterType::Sixel;
     }
 
-    if config.use_iterm && is_iterm_supported() {
+    if config.use_iterm && (probes.iterm)() {
         PrinterType::iTerm
-    } else if config.use_kitty && get_kitty_support() != KittySupport::None {
+    } else if config.use_kitty && (probes.kitty)() {
         PrinterType::Kitty
     } else {
         PrinterType::Block
     }
 }
+
+// The first way of printing in the fallback chain that the terminal supports, if any.
+fn chain_choice(config: &Config, probes: &Probes) -> Option<PrinterChoice> {
+    let chain = config.fallback_chain.as_ref()?;
+    chain.iter().copied().find(|choice| match choice {
+        PrinterChoice::Kitty => (probes.kitty)(),
+        #[cfg(feature = "sixel")]
+        PrinterChoice::Sixel => (probes.sixel)(),
+        #[cfg(not(feature = "sixel"))]
+        PrinterChoice::Sixel => false,
+        PrinterChoice::iTerm => (probes.iterm)(),
+        PrinterChoice::TrueColorBlock => config.use_truecolor(),
+        PrinterChoice::Ansi256Block | PrinterChoice::Ascii => true,
+    })
+}
+
+// The config for the way of printing chosen from the fallback chain, which sticks to the 256
+// color palette for [PrinterChoice::Ansi256Block].
+fn chain_config<'a>(config: &'a Config, probes: &Probes) -> Cow<'a, Config> {
+    match chain_choice(config, probes) {
+        Some(PrinterChoice::Ansi256Block) => Cow::Owned(Config {
+            truecolor: TrueColorMode::Never,
+            ..config.clone()
+        }),
+        _ => Cow::Borrowed(config),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TrueColorMode;
+
+    #[test]
+    fn test_stream_ansi_lines() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(5, 6));
+        let config = Config {
+            truecolor: TrueColorMode::Never,
+            absolute_offset: false,
+            ..Default::default()
+        };
+
+        let mut lines: Vec<Vec<u8>> = Vec::new();
+        let dimensions = stream_ansi(&img, &config, |line| lines.push(line.to_vec())).unwrap();
+        assert_eq!(dimensions, (5, 3));
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|line| line.ends_with(b"\n")));
+
+        let mut full = termcolor::Ansi::new(vec![]);
+        printer::BlockPrinter::write(&mut full, &img, &config).unwrap();
+        assert_eq!(lines.concat(), full.into_inner());
+    }
+
+    #[test]
+    fn test_stack_rows() {
+        let images = [
+            DynamicImage::ImageRgba8(image::RgbaImage::new(40, 20)),
+            DynamicImage::ImageRgba8(image::RgbaImage::new(10, 30)),
+        ];
+        let config = Config {
+            width: Some(20),
+            truecolor: TrueColorMode::Never,
+            absolute_offset: false,
+            ..Default::default()
+        };
+
+        let mut buf = termcolor::Ansi::new(vec![]);
+        let mut drawn = Vec::new();
+        let rows = stack(&images, &config, |img, config| {
+            let dimensions = printer::BlockPrinter::write(&mut buf, img, config)?;
+            drawn.push(dimensions);
+            Ok(dimensions)
+        })
+        .unwrap();
+
+        // the second image is at most 10 cells wide, so the first one is scaled down to it
+        assert_eq!(drawn, vec![(10, 2), (10, 15)]);
+        assert_eq!(rows, 17);
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
+        assert_eq!(output.lines().count(), 17);
+    }
+
+    #[test]
+    fn test_save_render_replay() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            6,
+            4,
+            image::Rgba([10, 200, 30, 255]),
+        ));
+        let config = Config {
+            truecolor: TrueColorMode::Force,
+            absolute_offset: false,
+            ..Default::default()
+        };
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        let dimensions = save_render(&img, &config, file.path()).unwrap();
+        let mut replayed = Vec::new();
+        replay_to(&mut replayed, file.path()).unwrap();
+
+        let mut original = termcolor::Ansi::new(vec![]);
+        let expected = printer::BlockPrinter::write(&mut original, &img, &config).unwrap();
+        assert_eq!(dimensions, expected);
+        assert_eq!(replayed, original.into_inner());
+    }
+
+    #[test]
+    fn test_rgba_image() {
+        let data: Vec<u8> = (0..4 * 6 * 4).map(|i| (i * 5) as u8).collect();
+        let img = rgba_image(&data, 4, 6).unwrap();
+
+        let expected =
+            DynamicImage::ImageRgba8(image::RgbaImage::from_raw(4, 6, data.clone()).unwrap());
+        let config = Config {
+            truecolor: TrueColorMode::Force,
+            absolute_offset: false,
+            ..Default::default()
+        };
+        let render = |img: &DynamicImage| {
+            let mut out = termcolor::Ansi::new(Vec::new());
+            printer::BlockPrinter::write(&mut out, img, &config).unwrap();
+            out.into_inner()
+        };
+        assert_eq!(render(&img), render(&expected));
+
+        assert!(matches!(
+            rgba_image(&data, 4, 5),
+            Err(ViuError::InvalidConfiguration(_))
+        ));
+        assert!(rgba_image(&data[1..], 4, 6).is_err());
+    }
+
+    #[test]
+    fn test_render_to_html() {
+        // the left column is transparent, the rest red over blue
+        let img =
+            DynamicImage::ImageRgba8(image::RgbaImage::from_fn(3, 4, |x, y| match (x, y % 2) {
+                (0, _) => image::Rgba([0, 0, 0, 0]),
+                (_, 0) => image::Rgba([0, 0, 255, 255]),
+                _ => image::Rgba([255, 0, 0, 255]),
+            }));
+        let config = Config {
+            transparent: true,
+            ..Default::default()
+        };
+        let html = render_to_html(&img, &config).unwrap();
+
+        assert!(html.starts_with("<pre"));
+        assert!(html.ends_with("</pre>"));
+        assert_eq!(html.matches("<span").count(), 4);
+        assert_eq!(html.matches("</span>").count(), 4);
+        assert!(html.contains("<span style=\"color:#ff0000;background:#0000ff\">\u{2584}</span>"));
+        assert_eq!(html.lines().count(), 2);
+    }
+
+    const NO_PROTOCOLS: Probes = Probes {
+        kitty: || false,
+        #[cfg(feature = "sixel")]
+        sixel: || false,
+        iterm: || false,
+    };
+
+    #[test]
+    fn test_fallback_chain_skips_unsupported() {
+        let config = Config {
+            fallback_chain: Some(vec![PrinterChoice::Kitty, PrinterChoice::Ansi256Block]),
+            truecolor: TrueColorMode::Force,
+            ..Default::default()
+        };
+        let probes = &NO_PROTOCOLS;
+        assert_eq!(
+            chain_choice(&config, probes),
+            Some(PrinterChoice::Ansi256Block)
+        );
+        assert!(matches!(
+            choose_printer_with(&config, probes),
+            PrinterType::Block
+        ));
+        assert_eq!(
+            chain_config(&config, probes).truecolor,
+            TrueColorMode::Never
+        );
+
+        // a supported protocol is taken in the order of the chain
+        let kitty = &Probes {
+            kitty: || true,
+            ..NO_PROTOCOLS
+        };
+        assert_eq!(chain_choice(&config, kitty), Some(PrinterChoice::Kitty));
+        assert!(matches!(
+            choose_printer_with(&config, kitty),
+            PrinterType::Kitty
+        ));
+        assert_eq!(chain_config(&config, kitty).truecolor, TrueColorMode::Force);
+
+        let config = Config {
+            fallback_chain: Some(vec![PrinterChoice::Kitty, PrinterChoice::Ascii]),
+            ..Default::default()
+        };
+        assert!(matches!(
+            choose_printer_with(&config, probes),
+            PrinterType::Ascii
+        ));
+
+        let config = Config {
+            fallback_chain: Some(vec![PrinterChoice::Kitty]),
+            ..Default::default()
+        };
+        assert_eq!(chain_choice(&config, probes), None);
+        assert!(matches!(
+            choose_printer_with(&config, probes),
+            PrinterType::Block
+        ));
+    }
+
+    #[test]
+    fn test_resize_for_printer() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(40, 40));
+        let config = Config {
+            height: Some(10),
+            ..Default::default()
+        };
+
+        let full = printer::resize_for_printer(&img, &config, &PrinterType::Block, true).unwrap();
+        assert_eq!(full.height(), 10);
+
+        let half = printer::resize_for_printer(&img, &config, &PrinterType::Block, false).unwrap();
+        assert_eq!(half.height(), 20);
+        assert_eq!(half.width(), full.width());
+
+        // the protocols draw at the terminal's resolution, which is unknown here
+        let kitty = printer::resize_for_printer(&img, &config, &PrinterType::Kitty, false).unwrap();
+        assert_eq!(kitty.dimensions(), (40, 40));
+    }
+}
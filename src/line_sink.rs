@@ -0,0 +1,59 @@
+use std::io::{self, Write};
+
+/// Output stream handing every completed line to a callback
+pub struct LineSink<F: FnMut(&[u8])> {
+    pending: Vec<u8>,
+    sink: F,
+}
+
+impl<F: FnMut(&[u8])> LineSink<F> {
+    pub fn new(sink: F) -> LineSink<F> {
+        LineSink {
+            pending: vec![],
+            sink,
+        }
+    }
+}
+
+impl<F: FnMut(&[u8])> Write for LineSink<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        // pass on everything up to the last newline, keeping the unfinished line
+        if let Some(end) = self.pending.iter().rposition(|b| *b == b'\n') {
+            let rest = self.pending.split_off(end + 1);
+            (self.sink)(&self.pending);
+            self.pending = rest;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            (self.sink)(&self.pending);
+            self.pending.clear();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_sink_splits_lines() {
+        let mut chunks = Vec::new();
+        let mut sink = LineSink::new(|bytes: &[u8]| chunks.push(bytes.to_vec()));
+        write!(sink, "ab").unwrap();
+        write!(sink, "c\r\nd").unwrap();
+        write!(sink, "e\r\n").unwrap();
+        write!(sink, "f").unwrap();
+        sink.flush().unwrap();
+        sink.flush().unwrap();
+
+        assert_eq!(
+            chunks,
+            vec![b"abc\r\n".to_vec(), b"de\r\n".to_vec(), b"f".to_vec()]
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::error::ViuResult;
+use crate::printer::block::standard_stream;
+use crate::printer::{adjust_vertical_offset, move_right, newline, visible_rows};
+use crate::Config;
+use std::io::Write;
+use unicode_width::UnicodeWidthStr;
+
+// Resets the attributes after every line, so that they do not leak into the offset of the next.
+const RESET: &str = "\x1b[0m";
+// End of the art in `.ans` files, possibly followed by a SAUCE metadata record.
+const EOF_MARKER: char = '\x1a';
+
+/// Print pre-rendered ANSI art, like the contents of a `.ans` file, placed like an image.
+///
+/// The art is treated as a block of cells: its size is the number of lines and the width of the
+/// widest one, ignoring escape sequences. It is moved by the offsets and the `anchor` or `align`
+/// of the config, and rows that do not fit in the terminal are handled according to its
+/// `vertical_overflow` and `reveal_fraction`. The sizing options do not apply. Anything after an
+/// end of file marker (`0x1a`) is ignored, and the content is expected to be UTF-8, with invalid
+/// bytes replaced. Returns the size of the art in terminal cells.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{print_ansi_art, Anchor, Config};
+///
+/// let art = std::fs::read("logo.ans").expect("Could not read file.");
+/// let conf = Config {
+///     align: Some(Anchor::Center),
+///     ..Default::default()
+/// };
+/// print_ansi_art(&art, &conf).expect("Printing failed.");
+/// ```
+pub fn print_ansi_art(bytes: &[u8], config: &Config) -> ViuResult<(u32, u32)> {
+    config.validate()?;
+    let mut stdout = standard_stream(config);
+    write_ansi_art(&mut stdout, &String::from_utf8_lossy(bytes), config)
+}
+
+fn write_ansi_art(stdout: &mut impl Write, art: &str, config: &Config) -> ViuResult<(u32, u32)> {
+    let lines = art_lines(art);
+    let width = lines
+        .iter()
+        .map(|line| visible_width(line))
+        .max()
+        .unwrap_or(0);
+    let config = &*config.anchored((width, lines.len() as u32));
+    let rows = visible_rows(lines.len() as u32, config)?;
+
+    adjust_vertical_offset(stdout, config)?;
+    for (curr_row, line) in lines.iter().take(rows as usize).enumerate() {
+        move_right(stdout, config.x, config)?;
+        write!(stdout, "{}{}", line, RESET)?;
+        if curr_row + 1 < rows as usize {
+            writeln!(stdout, "\r")?;
+        }
+    }
+    newline(stdout, config)?;
+    stdout.flush()?;
+
+    Ok((width, rows))
+}
+
+// The lines of the art up to the end of file marker, without their line endings. A line ending
+// at the end does not start another line.
+fn art_lines(art: &str) -> Vec<&str> {
+    let art = art.split(EOF_MARKER).next().unwrap_or_default();
+    let art = art.strip_suffix('\n').unwrap_or(art);
+    if art.is_empty() {
+        return Vec::new();
+    }
+    art.split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect()
+}
+
+// Number of columns the line takes up, skipping escape sequences.
+fn visible_width(line: &str) -> u32 {
+    let mut visible = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            visible.push(c);
+            continue;
+        }
+        match chars.next() {
+            // control sequences end with a character from @ to ~
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // operating system commands end with BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            // other escape sequences are a single character long
+            _ => {}
+        }
+    }
+    visible.width() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Anchor;
+
+    const SNIPPET: &str = "\x1b[31m/\\_/\\\x1b[0m\r\n\x1b[1;33m( o.o )\n > ^ <\n";
+
+    #[test]
+    fn test_ansi_art_size() {
+        assert_eq!(
+            art_lines(SNIPPET),
+            vec!["\x1b[31m/\\_/\\\x1b[0m", "\x1b[1;33m( o.o )", " > ^ <"]
+        );
+        assert_eq!(art_lines("abc\x1aSAUCE00"), vec!["abc"]);
+        assert!(art_lines("").is_empty());
+        assert_eq!(
+            visible_width("\x1b[38;2;1;2;3mab\x1b]8;;https://x\x1b\\c\x1b]0;t\x07"),
+            3
+        );
+        assert_eq!(visible_width("\x1b[31m漢字"), 4);
+    }
+
+    #[test]
+    fn test_ansi_art_centered() {
+        let config = Config {
+            align: Some(Anchor::Center),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        assert_eq!(write_ansi_art(&mut out, SNIPPET, &config).unwrap(), (7, 3));
+        let result = std::str::from_utf8(&out).unwrap();
+
+        // the terminal is 80x24 in tests, so the art starts at column 36 of row 11
+        assert_eq!(
+            result,
+            "\x1b[11;1H\x1b[36C\x1b[31m/\\_/\\\x1b[0m\x1b[0m\r\n\x1b[36C\x1b[1;33m( o.o )\x1b[0m\r\n\x1b[36C > ^ <\x1b[0m\n"
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use crate::error::{ViuError, ViuResult};
+use crate::printer::{
+    adjust_vertical_offset, fit_for_config, move_right, newline, resize_exact, visible_rows,
+    Printer, PrinterCapabilities,
+};
+use crate::utils::{is_dark_background, saturating_u16};
+use crate::Config;
+use image::DynamicImage;
+use std::io::Write;
+use unicode_width::UnicodeWidthChar;
+
+/// Glyphs used when no custom ramp is configured, ordered from darkest to brightest. On terminals
+/// with a light background, the ramp is used the other way around.
+pub const DEFAULT_ASCII_RAMP: &str = " .:-=+*#%@";
+
+pub struct AsciiPrinter;
+
+impl Printer for AsciiPrinter {
+    fn print(
+        &self,
+        stdout: &mut impl Write,
+        img: &DynamicImage,
+        config: &Config,
+    ) -> ViuResult<(u32, u32)> {
+        // the ramp goes from darkest to brightest on a dark background, the other way on a light one
+        let light_background = is_dark_background() == Some(false);
+        print_ascii(stdout, img, config, light_background)
+    }
+    fn capabilities(&self) -> PrinterCapabilities {
+        PrinterCapabilities {
+            truecolor: false,
+            transparency: true,
+            pixels_per_cell: Some((1, 1)),
+            animation: false,
+        }
+    }
+}
+
+// Print the image with the glyphs of the ramp, reversed on a light background.
+fn print_ascii(
+    stdout: &mut impl Write,
+    img: &DynamicImage,
+    config: &Config,
+    light_background: bool,
+) -> ViuResult<(u32, u32)> {
+    let ramp = Ramp::new(config.ascii_ramp.as_deref().unwrap_or(DEFAULT_ASCII_RAMP))?;
+
+    // every glyph takes up `ramp.width` columns and a whole row, which is two pixels tall
+    let (w, h) = fit_for_config(img, config)?;
+    let columns = std::cmp::max(1, w / ramp.width);
+    let mut img = resize_exact(img, (columns, h), config.filter);
+    let config = &*config.for_pager();
+    let config = &*config.anchored((columns * ramp.width, h));
+
+    // drop the rows that do not fit in the terminal, if requested
+    let h = visible_rows(h, config)?;
+    if h < img.height() {
+        img = img.crop_imm(0, 0, columns, h);
+    }
+    let img_buffer = img.to_rgba8();
+
+    adjust_vertical_offset(stdout, config)?;
+
+    for (curr_row, img_row) in img_buffer.enumerate_rows() {
+        move_right(stdout, config.x, config)?;
+
+        for (_, _, pixel) in img_row {
+            if pixel[3] == 0 && config.transparent && config.transparent_as_space {
+                write!(stdout, "{}", " ".repeat(ramp.width as usize))?;
+            } else if pixel[3] == 0 && config.transparent {
+                move_right(stdout, saturating_u16(ramp.width), config)?;
+            } else {
+                let luma = config.luminance((pixel[0], pixel[1], pixel[2]));
+                let luma = if light_background { 255 - luma } else { luma };
+                write!(stdout, "{}", ramp.glyph(luma))?;
+            }
+        }
+
+        if curr_row + 1 < h {
+            writeln!(stdout, "\r")?;
+        }
+    }
+    newline(stdout, config)?;
+    stdout.flush()?;
+
+    Ok((columns * ramp.width, h))
+}
+
+/// Describe the image as plain text, with a digit from 0 (darkest) to 9 (brightest) for the
+/// luminance of every terminal cell and a space for transparent ones.
+///
+/// The output contains no escape sequences at all, which makes it suitable for logs and screen
+/// readers. Lines are separated by `\n` and the offsets of the config are ignored.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{luminance_map, Config};
+///
+/// let img = image::open("img.jpg").expect("Could not open image.");
+/// let conf = Config {
+///     width: Some(20),
+///     ..Default::default()
+/// };
+/// println!("{}", luminance_map(&img, &conf).expect("Invalid configuration."));
+/// ```
+pub fn luminance_map(img: &DynamicImage, config: &Config) -> ViuResult<String> {
+    config.validate()?;
+    let (w, h) = fit_for_config(img, config)?;
+    let img = resize_exact(img, (w, h), config.filter).to_rgba8();
+
+    let lines: Vec<String> = img
+        .rows()
+        .map(|row| {
+            row.map(|pixel| match pixel[3] {
+                0 => ' ',
+                _ => {
+                    let luma = config.luminance((pixel[0], pixel[1], pixel[2]));
+                    char::from(b'0' + ((luma as u32 * 9 + 127) / 255) as u8)
+                }
+            })
+            .collect()
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+// Glyphs of a ramp, padded so that all of them take up the same number of columns.
+struct Ramp {
+    glyphs: Vec<String>,
+    width: u32,
+}
+
+impl Ramp {
+    fn new(ramp: &str) -> ViuResult<Self> {
+        let mut widths = Vec::new();
+        for c in ramp.chars() {
+            match c.width() {
+                Some(w) if w > 0 => widths.push((c, w)),
+                _ => {
+                    return Err(ViuError::InvalidConfiguration(format!(
+                        "ascii ramp contains the zero-width or control character {:?}",
+                        c
+                    )))
+                }
+            }
+        }
+        let width = match widths.iter().map(|(_, w)| *w).max() {
+            Some(width) => width,
+            None => {
+                return Err(ViuError::InvalidConfiguration(
+                    "ascii ramp is empty".to_owned(),
+                ))
+            }
+        };
+
+        let glyphs = widths
+            .into_iter()
+            .map(|(c, w)| format!("{}{}", c, " ".repeat(width - w)))
+            .collect();
+
+        Ok(Self {
+            glyphs,
+            width: width as u32,
+        })
+    }
+
+    fn glyph(&self, luma: u8) -> &str {
+        let index = (luma as usize * (self.glyphs.len() - 1) + 127) / 255;
+        &self.glyphs[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+    use unicode_width::UnicodeWidthStr;
+
+    fn gradient_image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, _| {
+            let v = (x * 255 / (width - 1)) as u8;
+            Rgba([v, v, v, 255])
+        }))
+    }
+
+    fn print_lines(img: &DynamicImage, config: &Config) -> ((u32, u32), Vec<String>) {
+        let mut vec = Vec::new();
+        let dimensions = AsciiPrinter.print(&mut vec, img, config).unwrap();
+        let output = String::from_utf8(vec).unwrap();
+        let lines = output.lines().map(|l| l.trim_end_matches('\r').to_owned());
+        (dimensions, lines.collect())
+    }
+
+    #[test]
+    fn test_ascii_default_ramp() {
+        let config = Config {
+            absolute_offset: false,
+            width: Some(10),
+            height: Some(2),
+            use_ascii: true,
+            ..Default::default()
+        };
+        let ((w, h), lines) = print_lines(&gradient_image(10, 4), &config);
+
+        assert_eq!((w, h), (10, 2));
+        assert_eq!(lines, vec![" .:-=+*#%@", " .:-=+*#%@"]);
+    }
+
+    #[test]
+    fn test_ascii_light_background() {
+        let config = Config {
+            absolute_offset: false,
+            width: Some(10),
+            height: Some(2),
+            use_ascii: true,
+            ..Default::default()
+        };
+        let mut vec = Vec::new();
+        print_ascii(&mut vec, &gradient_image(10, 4), &config, true).unwrap();
+        let output = String::from_utf8(vec).unwrap();
+        let lines: Vec<_> = output.lines().map(|l| l.trim_end_matches('\r')).collect();
+
+        // dark pixels get the densest glyphs
+        assert_eq!(lines, vec!["@%#*+=-:. ", "@%#*+=-:. "]);
+    }
+
+    #[test]
+    fn test_ascii_wide_ramp() {
+        let config = Config {
+            absolute_offset: false,
+            width: Some(10),
+            height: Some(3),
+            use_ascii: true,
+            ascii_ramp: Some("\u{3000}一二三".to_owned()),
+            ..Default::default()
+        };
+        let ((w, h), lines) = print_lines(&gradient_image(40, 6), &config);
+
+        assert_eq!((w, h), (10, 3));
+        assert_eq!(lines.len(), 3);
+        for line in lines {
+            assert_eq!(line.width() as u32, w);
+            assert_eq!(line.chars().count(), 5);
+        }
+    }
+
+    #[test]
+    fn test_ascii_mixed_width_ramp() {
+        let ramp = Ramp::new(".一").unwrap();
+        assert_eq!(ramp.width, 2);
+        assert_eq!(ramp.glyph(0), ". ");
+        assert_eq!(ramp.glyph(255), "一");
+    }
+
+    #[test]
+    fn test_ascii_invalid_ramp() {
+        assert!(Ramp::new("").is_err());
+        assert!(Ramp::new("a\u{301}b").is_err());
+        assert!(Ramp::new("a\tb").is_err());
+    }
+
+    #[test]
+    fn test_luminance_map() {
+        let mut img = gradient_image(10, 4).to_rgba8();
+        img.put_pixel(9, 3, Rgba([255, 255, 255, 0]));
+        let config = Config {
+            width: Some(10),
+            height: Some(2),
+            ..Default::default()
+        };
+        let map = luminance_map(&DynamicImage::ImageRgba8(img), &config).unwrap();
+
+        let lines: Vec<&str> = map.split('\n').collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.len() == 10));
+        assert!(map
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == ' ' || c == '\n'));
+        assert!(lines[0].starts_with('0'));
+        assert!(lines[0].ends_with('9'));
+    }
+}
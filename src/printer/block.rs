@@ -1,20 +1,371 @@
-use crate::error::ViuResult;
-use crate::printer::{adjust_offset, Printer};
-use crate::Config;
+use crate::error::{ViuError, ViuResult};
+use crate::printer::{
+    adjust_vertical_offset, banner, cell_size_pixels, cursor_position, fit_for_config,
+    metadata_trailer, move_right, move_to, newline, resize_exact, resize_to_cells, square_span,
+    visible_rows, Printer, PrinterCapabilities, MAX_CURSOR,
+};
+use crate::utils::{likely_block_font_gaps, saturating_i16, saturating_u16, terminal_size};
+use crate::{Background, Badge, Colormap, Config, OddRowMode, Rect, RenderStats, SgrSyntax};
 
 use ansi_colours::ansi256_from_rgb;
-use image::{DynamicImage, GenericImageView, Rgba};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use std::borrow::Cow;
 use std::io::Write;
-use termcolor::{BufferedStandardStream, Color, ColorSpec, WriteColor};
+use std::time::Instant;
+use termcolor::{BufferedStandardStream, Color, ColorChoice, ColorSpec, WriteColor};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-use crossterm::cursor::MoveRight;
+use crossterm::cursor::MoveToColumn;
 use crossterm::execute;
 
 const UPPER_HALF_BLOCK: &str = "\u{2580}";
 const LOWER_HALF_BLOCK: &str = "\u{2584}";
+const FULL_BLOCK: &str = "\u{2588}";
+// Tones of dithered images
+const WHITE: (u8, u8, u8) = (255, 255, 255);
+const BLACK: (u8, u8, u8) = (0, 0, 0);
+// Blocks filled from the left by one to seven eighths of a cell
+const LEFT_EIGHTH_BLOCKS: [&str; 7] = [
+    "\u{258f}", "\u{258e}", "\u{258d}", "\u{258c}", "\u{258b}", "\u{258a}", "\u{2589}",
+];
 
-const CHECKERBOARD_BACKGROUND_LIGHT: (u8, u8, u8) = (153, 153, 153);
-const CHECKERBOARD_BACKGROUND_DARK: (u8, u8, u8) = (102, 102, 102);
+// SGR sequences restoring the default colors without touching other attributes
+const DEFAULT_COLORS: &str = "\x1b[39;49m";
+const DEFAULT_BACKGROUND: &str = "\x1b[49m";
+
+const BACKGROUND_LIGHT: (u8, u8, u8) = (153, 153, 153);
+const BACKGROUND_DARK: (u8, u8, u8) = (102, 102, 102);
+
+const CROSSHAIR_COLOR: (u8, u8, u8) = (255, 0, 255);
+
+/// A terminal cell of an image printed with half blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cell {
+    /// Color of the glyph, None if nothing is drawn.
+    pub fg: Option<(u8, u8, u8)>,
+    /// Color of the cell's background, None if the terminal's background shows through.
+    pub bg: Option<(u8, u8, u8)>,
+    /// The character drawn in the cell.
+    pub glyph: &'static str,
+}
+
+impl Cell {
+    /// A cell where nothing is drawn.
+    pub const TRANSPARENT: Cell = Cell {
+        fg: None,
+        bg: None,
+        glyph: " ",
+    };
+
+    // Build the cell showing the given top and bottom pixels, where None is a transparent pixel.
+    // Lower half blocks are used by default.
+    fn from_halves(top: Option<(u8, u8, u8)>, bottom: Option<(u8, u8, u8)>) -> Cell {
+        match (top, bottom) {
+            (None, None) => Cell::TRANSPARENT,
+            (None, Some(bottom)) => Cell {
+                fg: Some(bottom),
+                bg: None,
+                glyph: LOWER_HALF_BLOCK,
+            },
+            (Some(top), None) => Cell {
+                fg: Some(top),
+                bg: None,
+                glyph: UPPER_HALF_BLOCK,
+            },
+            (Some(top), Some(bottom)) => Cell {
+                fg: Some(bottom),
+                bg: Some(top),
+                glyph: LOWER_HALF_BLOCK,
+            },
+        }
+    }
+
+    /// Whether the cell leaves the terminal untouched.
+    pub fn is_transparent(&self) -> bool {
+        self.fg.is_none() && self.bg.is_none()
+    }
+}
+
+/// Build a horizontal bar `cols` cells long, filled from the left by the given fraction, e.g. for
+/// gauges and bar charts. Cells can be partially filled in steps of an eighth, using the left
+/// block glyphs (▏ to ▉).
+///
+/// The filled part is drawn in `color` and the rest is left transparent. The fraction is clamped
+/// to 0..=1.
+///
+/// ## Example
+/// ```
+/// use viuer::bar_cells;
+///
+/// // two and a half cells out of ten
+/// let bar = bar_cells(0.25, 10, (0, 200, 0));
+/// assert_eq!(bar.len(), 10);
+/// assert_eq!(bar[2].glyph, "\u{258C}");
+/// ```
+pub fn bar_cells(fill: f32, cols: u32, color: (u8, u8, u8)) -> Vec<Cell> {
+    let eighths = (fill.clamp(0.0, 1.0) * cols as f32 * 8.0).round() as u32;
+    (0..cols)
+        .map(|col| match eighths.saturating_sub(col * 8).min(8) {
+            0 => Cell::TRANSPARENT,
+            8 => Cell {
+                fg: Some(color),
+                bg: None,
+                glyph: FULL_BLOCK,
+            },
+            partial => Cell {
+                fg: Some(color),
+                bg: None,
+                glyph: LEFT_EIGHTH_BLOCKS[partial as usize - 1],
+            },
+        })
+        .collect()
+}
+
+/// Run the preprocessing of the block printer and return the image it would draw, without
+/// printing anything.
+///
+/// The image is resized to fit the sizing options of the config, every two rows of pixels
+/// making up a row of terminal cells (or a single one when falling back to full blocks), and the
+/// other adjustments of the config are applied. Rows that would not fit in the terminal are not
+/// dropped.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{prepare, Config};
+///
+/// let img = image::open("img.jpg").expect("Could not open image.");
+/// let prepared = prepare(&img, &Config::default()).expect("Invalid configuration.");
+/// println!("{}x{} pixels will be drawn", prepared.width(), prepared.height());
+/// ```
+pub fn prepare(img: &DynamicImage, config: &Config) -> ViuResult<DynamicImage> {
+    let img = prepare_pixels(img, config, use_full_blocks(config))?;
+    Ok(DynamicImage::ImageRgba8(img))
+}
+
+// With full blocks, every pixel takes up a whole cell instead of half of one.
+fn prepare_pixels(img: &DynamicImage, config: &Config, full_blocks: bool) -> ViuResult<RgbaImage> {
+    if let Some((img, config)) = banner(img, config) {
+        return prepare_pixels(&img, &config, full_blocks);
+    }
+    let (w, h) = fit_for_config(img, config)?;
+    let mut img = if full_blocks {
+        resize_exact(img, (w, h), config.filter).to_rgba8()
+    } else {
+        resize_to_cells(img, (w, h), config.filter).to_rgba8()
+    };
+    if config.vertical_smoothing {
+        img = smooth_vertically(&img);
+    }
+    if let Some(background) = &config.background_image {
+        composite_over(&mut img, background, config);
+    }
+    if let Some(warmth) = config.color_temperature {
+        shift_temperature(&mut img, warmth);
+    }
+    if let Some(colormap) = config.colormap {
+        apply_colormap(&mut img, colormap, config);
+    }
+    feather_edges(&mut img, config.feather);
+    if config.overlay_grid {
+        draw_crosshair(&mut img, if full_blocks { 1 } else { 2 });
+    }
+    Ok(img)
+}
+
+// Draw the image over the background image, stretched to the same size.
+fn composite_over(img: &mut RgbaImage, background: &DynamicImage, config: &Config) {
+    let background = resize_exact(background, img.dimensions(), config.filter).to_rgba8();
+    for (pixel, behind) in img.pixels_mut().zip(background.pixels()) {
+        let (alpha, behind_alpha) = (pixel[3] as u32, behind[3] as u32);
+        // the alpha of the result, times 255
+        let total = 255 * alpha + behind_alpha * (255 - alpha);
+        if total == 0 {
+            continue;
+        }
+        for c in 0..3 {
+            let front = pixel[c] as u32 * alpha * 255;
+            let back = behind[c] as u32 * behind_alpha * (255 - alpha);
+            pixel[c] = ((front + back + total / 2) / total) as u8;
+        }
+        pixel[3] = ((total + 127) / 255) as u8;
+    }
+}
+
+// Multiply the red and blue channels by gains of up to 25% in opposite directions, raising red
+// for a positive warmth.
+fn shift_temperature(img: &mut RgbaImage, warmth: f32) {
+    if warmth == 0.0 {
+        return;
+    }
+    let gains = [1.0 + 0.25 * warmth, 1.0, 1.0 - 0.25 * warmth];
+    for pixel in img.pixels_mut() {
+        for (channel, gain) in gains.iter().enumerate() {
+            pixel[channel] = (pixel[channel] as f32 * gain).round().min(255.0) as u8;
+        }
+    }
+}
+
+// Map the luminance of every pixel to a color of the color map, keeping its alpha.
+fn apply_colormap(img: &mut RgbaImage, colormap: Colormap, config: &Config) {
+    for pixel in img.pixels_mut() {
+        let luminance = config.luminance((pixel[0], pixel[1], pixel[2])) as f32 / 255.0;
+        let (r, g, b) = colormap.map(luminance);
+        *pixel = Rgba([r, g, b, pixel[3]]);
+    }
+}
+
+// Full blocks are only used when preferred and half blocks are likely to be drawn with gaps.
+pub(crate) fn use_full_blocks(config: &Config) -> bool {
+    config.prefer_full_block && likely_block_font_gaps()
+}
+
+/// Resolve the cells the block printer would print for the image, without printing anything.
+///
+/// Returns the rows of cells, each with the glyph and colors that would be used. The colors
+/// are the exact RGB values, before any conversion to the 256 color palette. Rows that would
+/// not fit in the terminal are not dropped.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{render_cells, Config};
+///
+/// let img = image::open("img.jpg").expect("Could not open image.");
+/// let cells = render_cells(&img, &Config::default()).expect("Invalid configuration.");
+/// println!("{} rows of {} cells", cells.len(), cells[0].len());
+/// ```
+pub fn render_cells(img: &DynamicImage, config: &Config) -> ViuResult<Vec<Vec<Cell>>> {
+    let full_blocks = use_full_blocks(config);
+    let img = prepare_pixels(img, config, full_blocks)?;
+    Ok(pixels_to_cells(&img, config, full_blocks))
+}
+
+// Turn the prepared pixels into cells, see render_cells.
+fn pixels_to_cells(img: &RgbaImage, config: &Config, full_blocks: bool) -> Vec<Vec<Cell>> {
+    let mut cells = if full_blocks {
+        image_to_full_cells(img, config)
+    } else if config.sub_cell_dither {
+        image_to_dithered_cells(img, config)
+    } else {
+        image_to_cells(img, config)
+    };
+    if let Some(palette) = &config.palette {
+        reduce_to_palette(&mut cells, palette);
+    }
+    if let Some(gap) = config.mosaic_gap {
+        draw_mosaic_gaps(&mut cells, gap);
+    }
+    cells
+}
+
+// Turn every cell after `n` cells in a row or column into a separator of the given color. The
+// separators hide the cells they fall on, so that the image keeps its size. Transparent cells
+// are left untouched and of cells with one transparent half, only the other half is colored.
+fn draw_mosaic_gaps(cells: &mut [Vec<Cell>], (n, color): (u32, (u8, u8, u8))) {
+    let n = n as usize;
+    let is_gap = |index: usize| index % (n + 1) == n;
+    for (row, cells_row) in cells.iter_mut().enumerate() {
+        for (col, cell) in cells_row.iter_mut().enumerate() {
+            if !(is_gap(row) || is_gap(col)) || cell.is_transparent() {
+                continue;
+            }
+            *cell = match cell.bg {
+                None if cell.glyph != FULL_BLOCK => Cell {
+                    fg: Some(color),
+                    ..*cell
+                },
+                _ => mosaic_separator(color),
+            };
+        }
+    }
+}
+
+// Insert a separator of the given color between every `n` tiles of the grid, which are `span`
+// cells wide and tall, see [crate::print_grid]. Separators between two transparent cells are
+// left transparent.
+fn insert_mosaic_gaps(
+    cells: Vec<Vec<Cell>>,
+    (cols, rows): (u32, u32),
+    (n, color): (u32, (u8, u8, u8)),
+) -> Vec<Vec<Cell>> {
+    let separator =
+        |before: &Cell, after: &Cell| match before.is_transparent() && after.is_transparent() {
+            true => Cell::TRANSPARENT,
+            false => mosaic_separator(color),
+        };
+    let cells = cells
+        .into_iter()
+        .map(|row| with_separators(row, (n * cols) as usize, separator))
+        .collect();
+    with_separators(cells, (n * rows) as usize, |above, below| {
+        above
+            .iter()
+            .zip(below)
+            .map(|(a, b)| separator(a, b))
+            .collect()
+    })
+}
+
+// Insert an item built from its neighbors after every `every` items.
+fn with_separators<T>(items: Vec<T>, every: usize, separator: impl Fn(&T, &T) -> T) -> Vec<T> {
+    let mut separated: Vec<T> = Vec::with_capacity(items.len() + items.len() / every);
+    for (index, item) in items.into_iter().enumerate() {
+        if let Some(before) = separated.last().filter(|_| index % every == 0) {
+            separated.push(separator(before, &item));
+        }
+        separated.push(item);
+    }
+    separated
+}
+
+fn mosaic_separator(color: (u8, u8, u8)) -> Cell {
+    Cell {
+        fg: Some(color),
+        bg: None,
+        glyph: FULL_BLOCK,
+    }
+}
+
+// Replace the colors of the cells, including the backgrounds blended in, by the closest ones of
+// the palette.
+fn reduce_to_palette(cells: &mut [Vec<Cell>], palette: &[(u8, u8, u8)]) {
+    let closest = |rgb| crate::color::nearest(palette, rgb).map_or(rgb, |index| palette[index]);
+    for cell in cells.iter_mut().flatten() {
+        cell.fg = cell.fg.map(closest);
+        cell.bg = cell.bg.map(closest);
+    }
+}
+
+/// Resolve the cells like [render_cells], but as a layer to composite onto another grid of cells
+/// cell by cell: cells that would leave the terminal untouched are None.
+///
+/// Pixels are only transparent if `transparent` is set in the config, otherwise the background
+/// is drawn behind them. A cell where just one half is transparent is returned with a `bg` of
+/// None.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{render_layer, Config};
+///
+/// let watermark = image::open("logo.png").expect("Could not open image.");
+/// let conf = Config {
+///     transparent: true,
+///     ..Default::default()
+/// };
+/// let layer = render_layer(&watermark, &conf).expect("Invalid configuration.");
+/// let drawn = layer.iter().flatten().filter(|cell| cell.is_some()).count();
+/// println!("{} cells are drawn", drawn);
+/// ```
+pub fn render_layer(img: &DynamicImage, config: &Config) -> ViuResult<Vec<Vec<Option<Cell>>>> {
+    let cells = render_cells(img, config)?;
+    Ok(cells
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|cell| (!cell.is_transparent()).then_some(cell))
+                .collect()
+        })
+        .collect())
+}
 
 pub struct BlockPrinter;
 impl BlockPrinter {
@@ -23,9 +374,139 @@ impl BlockPrinter {
         img: &DynamicImage,
         config: &Config,
     ) -> ViuResult<(u32, u32)> {
-        print_to_writecolor(stdout, img, config)
+        Self::write_measured(stdout, img, config, None)
+    }
+
+    // Print like write, measuring the time spent preparing the pixels and rendering them.
+    pub(crate) fn write_timed(
+        stdout: &mut impl WriteColor,
+        img: &DynamicImage,
+        config: &Config,
+        stats: &mut RenderStats,
+    ) -> ViuResult<(u32, u32)> {
+        Self::write_measured(stdout, img, config, Some(stats))
+    }
+
+    fn write_measured(
+        stdout: &mut impl WriteColor,
+        img: &DynamicImage,
+        config: &Config,
+        stats: Option<&mut RenderStats>,
+    ) -> ViuResult<(u32, u32)> {
+        config.validate()?;
+        let pane_config = config.in_pane();
+        let full_blocks = use_full_blocks(&pane_config);
+
+        let start = Instant::now();
+        let pixels = prepare_pixels(img, &pane_config, full_blocks)?;
+        let prepared = Instant::now();
+        let cells = pixels_to_cells(&pixels, &pane_config, full_blocks);
+        let dimensions = place_cells(stdout, cells, &pane_config)?;
+        if config.emit_metadata {
+            let trailer = metadata_trailer(Some(img.dimensions()), dimensions, "block");
+            write!(stdout, "{}", trailer)?;
+            stdout.flush()?;
+        }
+        if let Some(stats) = stats {
+            stats.resize = prepared - start;
+            stats.render = prepared.elapsed();
+        }
+        Ok(dimensions)
+    }
+
+    // Print every pixel of the grid as a visually square block of full blocks, see
+    // [crate::print_grid].
+    pub fn write_grid(
+        stdout: &mut impl WriteColor,
+        grid: &DynamicImage,
+        config: &Config,
+    ) -> ViuResult<(u32, u32)> {
+        config.validate()?;
+        let config = &*config.in_pane();
+        let span = square_span(cell_size_pixels());
+        let mut cells = grid_to_cells(&grid.to_rgba8(), span, config);
+        if let Some(gap) = config.mosaic_gap {
+            cells = insert_mosaic_gaps(cells, span, gap);
+        }
+        place_cells(stdout, cells, config)
+    }
+
+    // Print a bar of eighth blocks, see [crate::print_bar].
+    pub fn write_bar(
+        stdout: &mut impl WriteColor,
+        fill: f32,
+        cols: u32,
+        color: (u8, u8, u8),
+        config: &Config,
+    ) -> ViuResult<(u32, u32)> {
+        config.validate()?;
+        place_cells(
+            stdout,
+            vec![bar_cells(fill, cols, color)],
+            &config.in_pane(),
+        )
+    }
+
+    // Print the rows of the image across the regions, see [crate::print_split].
+    pub fn write_split(
+        stdout: &mut impl WriteColor,
+        img: &DynamicImage,
+        regions: &[Rect],
+        config: &Config,
+    ) -> ViuResult<(u32, u32)> {
+        config.validate()?;
+        let cells = render_cells(img, config)?;
+        let mut printed = (0, 0);
+        for (region, cells) in split_cells(cells, regions) {
+            let region_config = Config {
+                absolute_offset: true,
+                x: region.x,
+                y: saturating_i16(region.y),
+                pane: None,
+                anchor: crate::Anchor::TopLeft,
+                align: None,
+                keep_cursor_column: false,
+                reveal_fraction: 1.0,
+                vertical_overflow: crate::VerticalOverflow::Clip,
+                ..config.clone()
+            };
+            let (cols, rows) = place_cells(stdout, cells, &region_config)?;
+            printed = (printed.0.max(cols), printed.1 + rows);
+        }
+        Ok(printed)
+    }
+
+    // Print the image at the given cursor position so that text can flow after it, see
+    // [crate::print_inline]. The offsets of the config are ignored.
+    pub fn write_inline(
+        stdout: &mut impl WriteColor,
+        img: &DynamicImage,
+        config: &Config,
+        cursor: (u16, u16),
+    ) -> ViuResult<(u16, u16)> {
+        config.validate()?;
+        let cells = render_cells(img, config)?;
+        write_cells_inline(stdout, &cells, config, cursor)
+    }
+}
+
+// The buffered stdout or stderr stream the block printer writes colors to.
+pub(crate) fn standard_stream(config: &Config) -> BufferedStandardStream {
+    let choice = color_choice(config);
+    match config.use_stderr {
+        true => BufferedStandardStream::stderr(choice),
+        false => BufferedStandardStream::stdout(choice),
     }
 }
+
+// termcolor only uses the synchronous console API on Windows consoles, unless forced not to.
+fn color_choice(config: &Config) -> ColorChoice {
+    match config.force_ansi {
+        true => ColorChoice::AlwaysAnsi,
+        false => ColorChoice::Always,
+    }
+}
+
 impl Printer for BlockPrinter {
     fn print(
         &self,
@@ -34,304 +515,2220 @@ impl Printer for BlockPrinter {
         img: &DynamicImage,
         config: &Config,
     ) -> ViuResult<(u32, u32)> {
-        let mut stream = match config.use_stderr {
-            true => BufferedStandardStream::stderr(termcolor::ColorChoice::Always),
-            false => BufferedStandardStream::stdout(termcolor::ColorChoice::Always),
+        let mut stream = standard_stream(config);
+        print_to_writecolor(&mut stream, img, config)
+    }
+    fn capabilities(&self) -> PrinterCapabilities {
+        PrinterCapabilities {
+            truecolor: true,
+            transparency: true,
+            pixels_per_cell: Some((1, 2)),
+            animation: false,
+        }
+    }
+}
+
+fn print_to_writecolor(
+    stdout: &mut impl WriteColor,
+    img: &DynamicImage,
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    // resize the image so that it fits in the constraints, if any
+    let cells = render_cells(img, config)?;
+    place_cells(stdout, cells, config)
+}
+
+// Print the cells at the offset of the config, returning the dimensions that were printed.
+fn place_cells(
+    stdout: &mut impl WriteColor,
+    mut cells: Vec<Vec<Cell>>,
+    config: &Config,
+) -> ViuResult<(u32, u32)> {
+    let config = &*config.for_pager();
+    let width = cells.first().map_or(0, Vec::len) as u32;
+    if let Some(mask) = &config.cell_mask {
+        apply_mask(&mut cells, mask)?;
+    }
+    if config.right_to_left {
+        cells.iter_mut().for_each(|row| row.reverse());
+    }
+    let config = &*config.anchored((width, cells.len() as u32));
+
+    // drop the rows that do not fit in the terminal, if requested
+    let rows = visible_rows(cells.len() as u32, config)?;
+    cells.truncate(rows as usize);
+
+    let chip = config
+        .badge
+        .as_ref()
+        .and_then(|badge| place_badge(badge, (width, rows)));
+
+    let cursor = match config.keep_cursor_column {
+        true => Some(cursor_position()?),
+        false => None,
+    };
+    let config = &*match cursor {
+        Some((_, row)) => from_cursor_row(config, row),
+        None => Cow::Borrowed(config),
+    };
+    let start_column = cursor.map(|(column, _)| column);
+
+    // adjust with x=0 and handle horizontal offset entirely below
+    adjust_vertical_offset(stdout, config)?;
+    write_cells(stdout, &cells, config, chip.as_ref(), start_column)?;
+
+    Ok((width, rows))
+}
+
+// The config with an absolute offset counted from the cursor's row, instead of the top of the
+// terminal.
+fn from_cursor_row(config: &Config, row: u16) -> Cow<'_, Config> {
+    if !config.absolute_offset || config.use_current_position {
+        return Cow::Borrowed(config);
+    }
+    Cow::Owned(Config {
+        y: config.y.saturating_add(saturating_i16(row)),
+        ..config.clone()
+    })
+}
+
+// Make the cells that are masked out transparent. The mask must be as large as the cells.
+fn apply_mask(cells: &mut [Vec<Cell>], mask: &[Vec<bool>]) -> ViuResult {
+    let fits = mask.len() == cells.len()
+        && mask
+            .iter()
+            .zip(cells.iter())
+            .all(|(flags, row)| flags.len() == row.len());
+    if !fits {
+        let width = cells.first().map_or(0, Vec::len);
+        return Err(ViuError::InvalidConfiguration(format!(
+            "cell_mask does not match the {}x{} printed cells",
+            width,
+            cells.len()
+        )));
+    }
+    for (row, flags) in cells.iter_mut().zip(mask) {
+        for (cell, allowed) in row.iter_mut().zip(flags) {
+            if !allowed {
+                *cell = Cell::TRANSPARENT;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Hand out the rows of cells to the regions from top to bottom, each taking as many as it is
+// tall, clipped to its width. Rows left over when the regions are full are dropped.
+fn split_cells(cells: Vec<Vec<Cell>>, regions: &[Rect]) -> Vec<(Rect, Vec<Vec<Cell>>)> {
+    let mut rows = cells.into_iter();
+    regions
+        .iter()
+        .map(|region| {
+            let chunk: Vec<Vec<Cell>> = rows
+                .by_ref()
+                .take(region.height as usize)
+                .map(|mut row| {
+                    row.truncate(region.width as usize);
+                    row
+                })
+                .collect();
+            (*region, chunk)
+        })
+        .take_while(|(_, chunk)| !chunk.is_empty())
+        .collect()
+}
+
+// Blend every pixel with the ones above and below it, using weights 1-2-1. Colors are weighted
+// by their alpha, so that transparent pixels do not darken their neighbors.
+fn smooth_vertically(img: &RgbaImage) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    RgbaImage::from_fn(width, height, |x, y| {
+        let neighbors = [
+            (y.saturating_sub(1), 1),
+            (y, 2),
+            ((y + 1).min(height - 1), 1),
+        ];
+        let mut sums = [0u32; 3];
+        let mut alpha = 0;
+        for (row, weight) in neighbors {
+            let pixel = img.get_pixel(x, row);
+            let w = weight * pixel[3] as u32;
+            for (sum, channel) in sums.iter_mut().zip(pixel.0) {
+                *sum += w * channel as u32;
+            }
+            alpha += w;
+        }
+        if alpha == 0 {
+            return image::Rgba([0, 0, 0, 0]);
+        }
+        let [r, g, b] = sums.map(|sum| (sum / alpha) as u8);
+        image::Rgba([r, g, b, (alpha / 4) as u8])
+    })
+}
+
+// Tint the center column and the center row of cells, each `cell_height` pixels tall, to help
+// with checking the alignment of the image. Transparent pixels are given a translucent tint.
+fn draw_crosshair(img: &mut RgbaImage, cell_height: u32) {
+    let (width, height) = img.dimensions();
+    let center_col = width / 2;
+    let center_row = height.div_ceil(cell_height) / 2 * cell_height;
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        if x != center_col && !(center_row..center_row + cell_height).contains(&y) {
+            continue;
+        }
+        *pixel = match pixel[3] {
+            0 => image::Rgba([CROSSHAIR_COLOR.0, CROSSHAIR_COLOR.1, CROSSHAIR_COLOR.2, 128]),
+            alpha => {
+                let (r, g, b) = blend((pixel[0], pixel[1], pixel[2]), CROSSHAIR_COLOR, 128);
+                image::Rgba([r, g, b, alpha])
+            }
+        };
+    }
+}
+
+// Fade out the pixels within `feather` pixels of the border. The alpha decreases linearly
+// towards the edge, without ever reaching zero.
+fn feather_edges(img: &mut RgbaImage, feather: u32) {
+    if feather == 0 {
+        return;
+    }
+    let (width, height) = img.dimensions();
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let distance = x.min(width - 1 - x).min(y).min(height - 1 - y);
+        if distance < feather {
+            pixel[3] = (pixel[3] as u32 * (distance + 1) / (feather + 1)) as u8;
+        }
+    }
+}
+
+// Draw every pixel as a full block, in a cell of its own.
+fn image_to_full_cells(img: &RgbaImage, config: &Config) -> Vec<Vec<Cell>> {
+    (0..img.height())
+        .map(|row| {
+            (0..img.width())
+                .map(|col| match get_pixel_color(img, col, row, config) {
+                    Some(color) => Cell {
+                        fg: Some(color),
+                        bg: None,
+                        glyph: FULL_BLOCK,
+                    },
+                    None => Cell::TRANSPARENT,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Draw every pixel of the grid as `cols` x `rows` full blocks.
+fn grid_to_cells(grid: &RgbaImage, (cols, rows): (u32, u32), config: &Config) -> Vec<Vec<Cell>> {
+    let scaled = RgbaImage::from_fn(grid.width() * cols, grid.height() * rows, |x, y| {
+        *grid.get_pixel(x / cols, y / rows)
+    });
+    image_to_full_cells(&scaled, config)
+}
+
+// Pair up every two rows of pixels into a row of cells. On odd heights, the last row is drawn
+// according to the odd row mode.
+fn image_to_cells(img: &RgbaImage, config: &Config) -> Vec<Vec<Cell>> {
+    let (width, height) = img.dimensions();
+
+    (0..height)
+        .step_by(2)
+        .map(|row| {
+            (0..width)
+                .map(|col| {
+                    let top = get_pixel_color(img, col, row, config);
+                    if row + 1 < height {
+                        let bottom = get_pixel_color(img, col, row + 1, config);
+                        return Cell::from_halves(top, bottom);
+                    }
+                    match (config.odd_row_mode, top) {
+                        (OddRowMode::HalfBlock, _) | (_, None) => Cell::from_halves(top, None),
+                        (OddRowMode::FullBlock, Some(top)) => Cell {
+                            fg: Some(top),
+                            bg: None,
+                            glyph: FULL_BLOCK,
+                        },
+                        (OddRowMode::PadTransparent, Some(top)) => {
+                            Cell::from_halves(Some(top), get_background_color(row + 1, col, config))
+                        }
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Draw the image in white and black after Floyd-Steinberg dithering of its luminance. Cells are
+// drawn with white in the foreground and black in the background. Transparent pixels are kept.
+fn image_to_dithered_cells(img: &RgbaImage, config: &Config) -> Vec<Vec<Cell>> {
+    let (width, height) = img.dimensions();
+    let colors: Vec<Option<(u8, u8, u8)>> = img
+        .enumerate_pixels()
+        .map(|(col, row, _)| get_pixel_color(img, col, row, config))
+        .collect();
+    let light = dither_luminance(&colors, width as usize, config);
+
+    let (width, height) = (width as usize, height as usize);
+    let pixel = |row: usize, col: usize| {
+        let index = row * width + col;
+        colors[index].map(|_| light[index])
+    };
+    (0..height)
+        .step_by(2)
+        .map(|row| {
+            (0..width)
+                .map(|col| {
+                    let top = pixel(row, col);
+                    let bottom = (row + 1 < height).then(|| pixel(row + 1, col)).flatten();
+                    match (top, bottom) {
+                        (Some(top), Some(bottom)) => Cell {
+                            fg: Some(WHITE),
+                            bg: Some(BLACK),
+                            glyph: dithered_glyph(top, bottom),
+                        },
+                        (top, bottom) => {
+                            let tone = |light| if light { WHITE } else { BLACK };
+                            Cell::from_halves(top.map(tone), bottom.map(tone))
+                        }
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Whether every pixel is light after Floyd-Steinberg dithering of the luminance, given the
+// pixels row by row. The error of transparent pixels is not spread.
+fn dither_luminance(colors: &[Option<(u8, u8, u8)>], width: usize, config: &Config) -> Vec<bool> {
+    let mut levels: Vec<f32> = colors
+        .iter()
+        .map(|color| color.map_or(0.0, |rgb| config.luminance(rgb) as f32))
+        .collect();
+    let mut light = vec![false; colors.len()];
+    for index in 0..levels.len() {
+        if colors[index].is_none() {
+            continue;
+        }
+        light[index] = levels[index] >= 128.0;
+        let error = levels[index] - if light[index] { 255.0 } else { 0.0 };
+        let (row, col) = (index / width, index % width);
+        let neighbors = [(0, 1, 7.0), (1, -1, 3.0), (1, 0, 5.0), (1, 1, 1.0)];
+        for (dy, dx, weight) in neighbors {
+            let col = col as isize + dx;
+            if col < 0 || col >= width as isize {
+                continue;
+            }
+            if let Some(level) = levels.get_mut((row + dy) * width + col as usize) {
+                *level += error * weight / 16.0;
+            }
+        }
+    }
+    light
+}
+
+// The glyph drawing the light halves of a cell in the foreground and the dark ones in the
+// background.
+fn dithered_glyph(top_light: bool, bottom_light: bool) -> &'static str {
+    match (top_light, bottom_light) {
+        (true, true) => FULL_BLOCK,
+        (true, false) => UPPER_HALF_BLOCK,
+        (false, true) => LOWER_HALF_BLOCK,
+        (false, false) => " ",
+    }
+}
+
+// A badge placed within the image: its text, already padded and clipped, starts at the given
+// row and column of cells.
+struct Chip<'a> {
+    badge: &'a Badge,
+    text: String,
+    row: usize,
+    col: usize,
+}
+
+// Place the badge within an image of (cols, rows) cells. Returns None if no cell of it fits.
+fn place_badge(badge: &Badge, (cols, rows): (u32, u32)) -> Option<Chip<'_>> {
+    if cols == 0 || rows == 0 {
+        return None;
+    }
+    let label = format!(" {} ", badge.text.replace(['\r', '\n'], " "));
+    let mut text = String::new();
+    let mut width = 0;
+    for c in label.chars() {
+        let w = c.width().unwrap_or(0) as u32;
+        if width + w > cols {
+            break;
+        }
+        text.push(c);
+        width += w;
+    }
+
+    let (fx, fy) = badge.anchor.factors();
+    Some(Chip {
+        badge,
+        text,
+        row: ((rows - 1) * fy / 2) as usize,
+        col: ((cols - width) * fx / 2) as usize,
+    })
+}
+
+fn write_cells(
+    stdout: &mut impl WriteColor,
+    cells: &[Vec<Cell>],
+    config: &Config,
+    chip: Option<&Chip>,
+    start_column: Option<u16>,
+) -> ViuResult {
+    let solid = solid_cell(cells);
+    for (curr_row, row) in cells.iter().enumerate() {
+        // move right if x offset is specified, counting from the start column if there is one
+        if let Some(column) = start_column {
+            let column = column.saturating_add(config.x).min(MAX_CURSOR);
+            execute!(stdout, MoveToColumn(column))?;
+        } else {
+            move_right(stdout, config.x, config)?;
+        }
+
+        match chip {
+            Some(chip) if chip.row == curr_row => write_row_with_chip(stdout, row, chip, config)?,
+            _ => match solid {
+                Some(cell) => write_solid_row(stdout, cell, row.len(), config)?,
+                None => write_row(stdout, row, config)?,
+            },
+        }
+
+        if curr_row + 1 < cells.len() {
+            reset_colors(stdout, config.preserve_sgr)?;
+            writeln!(stdout, "\r")?;
+        }
+    }
+
+    reset_colors(stdout, config.preserve_sgr)?;
+    newline(stdout, config)?;
+    stdout.flush()?;
+
+    Ok(())
+}
+
+// The cell all cells are equal to, if the image is a single opaque color like a placeholder.
+fn solid_cell(cells: &[Vec<Cell>]) -> Option<&Cell> {
+    let first = cells.first()?.first()?;
+    let uniform = cells.iter().flatten().all(|cell| cell == first);
+    (uniform && !first.is_transparent()).then_some(first)
+}
+
+// Write a row of equal cells, setting their colors just once.
+fn write_solid_row(
+    stdout: &mut impl WriteColor,
+    cell: &Cell,
+    len: usize,
+    config: &Config,
+) -> ViuResult {
+    if config.compact_sgr {
+        write!(stdout, "{}{}", combined_sgr(cell, config), cell.glyph)?;
+    } else {
+        write_cell(stdout, cell, config)?;
+    }
+    write!(stdout, "{}", cell.glyph.repeat(len.saturating_sub(1)))?;
+    Ok(())
+}
+
+fn write_row(stdout: &mut impl WriteColor, row: &[Cell], config: &Config) -> ViuResult {
+    // colors set by the last sequence in this row, repeating them is skipped when compacting
+    let mut current_colors = None;
+    for cell in row {
+        if config.compact_sgr && !cell.is_transparent() {
+            if current_colors != Some((cell.fg, cell.bg)) {
+                write!(stdout, "{}", combined_sgr(cell, config))?;
+                current_colors = Some((cell.fg, cell.bg));
+            }
+            write!(stdout, "{}", cell.glyph)?;
+        } else {
+            write_cell(stdout, cell, config)?;
+            if cell.is_transparent() && config.transparent_as_space {
+                // the colors were reset for the space
+                current_colors = None;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Write the row with the chip drawn over the cells it covers.
+fn write_row_with_chip(
+    stdout: &mut impl WriteColor,
+    row: &[Cell],
+    chip: &Chip,
+    config: &Config,
+) -> ViuResult {
+    let end = (chip.col + chip.text.width()).min(row.len());
+    write_row(stdout, &row[..chip.col], config)?;
+
+    if config.sgr_syntax == SgrSyntax::Colon {
+        let colors = Cell {
+            fg: Some(chip.badge.fg),
+            bg: Some(chip.badge.bg),
+            glyph: "",
+        };
+        write!(stdout, "{}", combined_sgr(&colors, config))?;
+    } else {
+        let mut color = ColorSpec::new();
+        color
+            .set_fg(Some(to_color(chip.badge.fg, config.use_truecolor())))
+            .set_bg(Some(to_color(chip.badge.bg, config.bg_truecolor())))
+            .set_reset(!config.preserve_sgr);
+        stdout.set_color(&color)?;
+    }
+    write!(stdout, "{}", chip.text)?;
+    reset_colors(stdout, config.preserve_sgr)?;
+
+    write_row(stdout, &row[end..], config)
+}
+
+// Draw the cells with their top left corner at the cursor, given as (column, row), instead of
+// at the start of a line. Space for the rows below is made by scrolling first, so the image
+// may end up higher than the cursor was. Returns the position right after the last row, where
+// text can continue.
+fn write_cells_inline(
+    stdout: &mut impl WriteColor,
+    cells: &[Vec<Cell>],
+    config: &Config,
+    (col, row): (u16, u16),
+) -> ViuResult<(u16, u16)> {
+    let (_, term_h) = terminal_size();
+    let rows = saturating_u16(cells.len());
+    for _ in 1..rows {
+        newline(stdout, config)?;
+    }
+    let top = std::cmp::min(row, term_h.saturating_sub(rows));
+
+    for (curr_row, cells_row) in cells.iter().enumerate() {
+        let y = top.saturating_add(saturating_u16(curr_row));
+        execute!(stdout, move_to(col, y))?;
+        write_row(stdout, cells_row, config)?;
+        reset_colors(stdout, config.preserve_sgr)?;
+    }
+
+    let width = saturating_u16(cells.first().map_or(0, |r| r.len()));
+    let end = (
+        col.saturating_add(width).min(MAX_CURSOR),
+        top.saturating_add(rows.saturating_sub(1)).min(MAX_CURSOR),
+    );
+    execute!(stdout, move_to(end.0, end.1))?;
+    stdout.flush()?;
+
+    Ok(end)
+}
+
+// Reset all SGR attributes or, when they should be preserved, only the colors.
+fn reset_colors(stdout: &mut impl WriteColor, preserve_sgr: bool) -> ViuResult {
+    if preserve_sgr {
+        write!(stdout, "{}", DEFAULT_COLORS)?;
+    } else {
+        stdout.reset()?;
+    }
+    Ok(())
+}
+
+fn write_cell(stdout: &mut impl WriteColor, cell: &Cell, config: &Config) -> ViuResult {
+    if cell.is_transparent() {
+        if config.transparent_as_space {
+            reset_colors(stdout, config.preserve_sgr)?;
+            write!(stdout, " ")?;
+        } else {
+            move_right(stdout, 1, config)?;
+        }
+        return Ok(());
+    }
+
+    // termcolor only produces semicolons
+    if config.sgr_syntax == SgrSyntax::Colon {
+        write!(stdout, "{}{}", combined_sgr(cell, config), cell.glyph)?;
+        return Ok(());
+    }
+
+    let mut out_color = ColorSpec::new();
+    out_color
+        .set_fg(cell.fg.map(|rgb| to_color(rgb, config.use_truecolor())))
+        .set_bg(cell.bg.map(|rgb| to_color(rgb, config.bg_truecolor())));
+
+    if config.preserve_sgr {
+        // every character sets its foreground, but the background has to be cleared explicitly
+        // since the attributes are not reset in between
+        if cell.bg.is_none() {
+            write!(stdout, "{}", DEFAULT_BACKGROUND)?;
+        }
+        out_color.set_reset(false);
+    }
+    stdout.set_color(&out_color)?;
+    write!(stdout, "{}", cell.glyph)?;
+
+    Ok(())
+}
+
+// A single SGR sequence setting both colors of the cell, including the reset of previous
+// attributes unless they should be preserved.
+fn combined_sgr(cell: &Cell, config: &Config) -> String {
+    let mut params = Vec::with_capacity(3);
+    if !config.preserve_sgr {
+        params.push("0".to_owned());
+    }
+    if let Some(fg) = cell.fg {
+        params.push(sgr_color_params(
+            38,
+            fg,
+            config.use_truecolor(),
+            config.sgr_syntax,
+        ));
+    }
+    match cell.bg {
+        Some(bg) => params.push(sgr_color_params(
+            48,
+            bg,
+            config.bg_truecolor(),
+            config.sgr_syntax,
+        )),
+        None if config.preserve_sgr => params.push("49".to_owned()),
+        None => {}
+    }
+    format!("\x1b[{}m", params.join(";"))
+}
+
+// SGR parameters for a foreground (38) or background (48) color.
+fn sgr_color_params(target: u8, rgb: (u8, u8, u8), truecolor: bool, syntax: SgrSyntax) -> String {
+    match (truecolor, syntax) {
+        (true, SgrSyntax::Semicolon) => format!("{};2;{};{};{}", target, rgb.0, rgb.1, rgb.2),
+        // the empty parameter is the color space, which is left out
+        (true, SgrSyntax::Colon) => format!("{}:2::{}:{}:{}", target, rgb.0, rgb.1, rgb.2),
+        (false, SgrSyntax::Semicolon) => format!("{};5;{}", target, ansi256_from_rgb(rgb)),
+        (false, SgrSyntax::Colon) => format!("{}:5:{}", target, ansi256_from_rgb(rgb)),
+    }
+}
+
+// Color of the pixel, where transparent pixels are either None or, when true transparency is
+// disabled, replaced by the configured background. Partially transparent pixels are drawn as
+// opaque, unless the edges are feathered: then they are blended with the background, or
+// dithered when the terminal's background shows through, since its color is unknown.
+fn get_pixel_color(img: &RgbaImage, col: u32, row: u32, config: &Config) -> Option<(u8, u8, u8)> {
+    let pixel = img.get_pixel(col, row);
+    let rgb = (pixel[0], pixel[1], pixel[2]);
+    match (pixel[3], get_background_color(row, col, config)) {
+        (255, _) => Some(rgb),
+        (0, background) => background,
+        _ if config.feather == 0 => Some(rgb),
+        (alpha, Some(background)) => Some(blend(rgb, background, alpha)),
+        (alpha, None) => alpha_dither(alpha, row, col).then_some(rgb),
+    }
+}
+
+// Whether a partially transparent pixel is drawn, with ordered dithering so that the share of
+// pixels drawn in an area follows their alpha.
+fn alpha_dither(alpha: u8, row: u32, col: u32) -> bool {
+    const BAYER: [[u32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+    let threshold = BAYER[(row % 4) as usize][(col % 4) as usize];
+    // alpha / 255 against (threshold + 0.5) / 16
+    alpha as u32 * 16 > (2 * threshold + 1) * 128
+}
+
+fn blend(fg: (u8, u8, u8), bg: (u8, u8, u8), alpha: u8) -> (u8, u8, u8) {
+    let mix =
+        |f: u8, b: u8| ((f as u32 * alpha as u32 + b as u32 * (255 - alpha as u32)) / 255) as u8;
+    (mix(fg.0, bg.0), mix(fg.1, bg.1), mix(fg.2, bg.2))
+}
+
+// Color of the backdrop at the given pixel, None when the terminal's background shows through.
+fn get_background_color(row: u32, col: u32, config: &Config) -> Option<(u8, u8, u8)> {
+    if config.transparent {
+        return None;
+    }
+    let dark = match config.background {
+        Background::Transparent => return None,
+        Background::Solid(rgb) => return Some(rgb),
+        //imitate the transparent chess board pattern
+        Background::Checkerboard => row % 2 == col % 2,
+        Background::Dots => row % 4 == 0 && col % 4 == 0,
+        Background::Lines => (row + col) % 4 == 0,
+    };
+    if dark {
+        Some(BACKGROUND_DARK)
+    } else {
+        Some(BACKGROUND_LIGHT)
+    }
+}
+
+fn to_color(rgb: (u8, u8, u8), truecolor: bool) -> Color {
+    if truecolor {
+        Color::Rgb(rgb.0, rgb.1, rgb.2)
+    } else {
+        Color::Ansi256(ansi256_from_rgb(rgb))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TrueColorMode;
+    use crate::VerticalOverflow;
+    use termcolor::Ansi;
+
+    // Note: truecolor is not supported in CI. Hence, it should be disabled when writing the tests
+
+    #[test]
+    fn test_block_printer_e2e() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(5, 4));
+        let mut buf = Ansi::new(vec![]);
+
+        let config = Config {
+            truecolor: TrueColorMode::Never,
+            ..Default::default()
+        };
+
+        let (w, h) = print_to_writecolor(&mut buf, &img, &config).unwrap();
+        assert_eq!((w, h), (5, 2));
+
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[1;1H\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\x1b[38;5;241m\x1b[48;5;247m▄\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\x1b[38;5;241m\x1b[48;5;247m▄\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\r\n\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\x1b[38;5;241m\x1b[48;5;247m▄\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\x1b[38;5;241m\x1b[48;5;247m▄\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_block_printer_e2e_transparent() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(5, 4));
+        let mut buf = Ansi::new(vec![]);
+
+        let config = Config {
+            transparent: true,
+            ..Default::default()
+        };
+
+        let (w, h) = print_to_writecolor(&mut buf, &img, &config).unwrap();
+        assert_eq!((w, h), (5, 2));
+
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[1;1H\x1b[1C\x1b[1C\x1b[1C\x1b[1C\x1b[1C\x1b[0m\r\n\x1b[1C\x1b[1C\x1b[1C\x1b[1C\x1b[1C\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_block_printer_e2e_odd_height() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(4, 3));
+        let mut buf = Ansi::new(vec![]);
+
+        let config = Config {
+            truecolor: TrueColorMode::Never,
+            absolute_offset: false,
+            ..Default::default()
+        };
+        let (w, h) = print_to_writecolor(&mut buf, &img, &config).unwrap();
+        assert_eq!((w, h), (4, 2));
+
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\x1b[38;5;241m\x1b[48;5;247m▄\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\x1b[38;5;241m\x1b[48;5;247m▄\x1b[0m\r\n\x1b[0m\x1b[38;5;241m▀\x1b[0m\x1b[38;5;247m▀\x1b[0m\x1b[38;5;241m▀\x1b[0m\x1b[38;5;247m▀\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_block_printer_preserve_sgr() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(2, 3));
+        let mut buf = Ansi::new(vec![]);
+
+        let config = Config {
+            truecolor: TrueColorMode::Never,
+            absolute_offset: false,
+            preserve_sgr: true,
+            ..Default::default()
+        };
+        print_to_writecolor(&mut buf, &img, &config).unwrap();
+
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
+        assert!(!output.contains("\x1b[0m"));
+        assert_eq!(
+            output,
+            "\x1b[38;5;247m\x1b[48;5;241m▄\x1b[38;5;241m\x1b[48;5;247m▄\x1b[39;49m\r\n\x1b[49m\x1b[38;5;241m▀\x1b[49m\x1b[38;5;247m▀\x1b[39;49m\n"
+        );
+    }
+
+    fn count_printed_rows(img: &DynamicImage, config: &Config) -> ViuResult<usize> {
+        let mut buf = Ansi::new(vec![]);
+        let (_, h) = print_to_writecolor(&mut buf, img, config)?;
+        let lines = std::str::from_utf8(buf.get_ref()).unwrap().lines().count();
+        assert_eq!(lines, h as usize);
+        Ok(lines)
+    }
+
+    #[test]
+    fn test_block_printer_vertical_overflow() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(10, 100));
+        let mut config = Config {
+            truecolor: TrueColorMode::Never,
+            height: Some(50),
+            ..Default::default()
+        };
+        assert_eq!(count_printed_rows(&img, &config).unwrap(), 50);
+
+        config.vertical_overflow = VerticalOverflow::Clip;
+        assert_eq!(count_printed_rows(&img, &config).unwrap(), 24);
+
+        config.vertical_overflow = VerticalOverflow::Error;
+        let err = count_printed_rows(&img, &config).unwrap_err();
+        assert!(matches!(err, crate::ViuError::InvalidConfiguration(_)));
+    }
+
+    #[test]
+    fn test_block_printer_zero_width() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(20, 10));
+        let config = Config {
+            width: Some(0),
+            height: Some(0),
+            ..Default::default()
+        };
+        let mut buf = Ansi::new(vec![]);
+        let err = print_to_writecolor(&mut buf, &img, &config).unwrap_err();
+        assert!(matches!(err, crate::ViuError::InvalidConfiguration(_)));
+        assert!(buf.get_ref().is_empty());
+    }
+
+    #[test]
+    fn test_block_printer_exact_rows() {
+        let config = Config {
+            truecolor: TrueColorMode::Never,
+            absolute_offset: false,
+            rows: Some(10),
+            ..Default::default()
+        };
+        for (w, h) in [(100, 100), (300, 50), (50, 300), (7, 3), (1000, 999)] {
+            let img = DynamicImage::ImageRgba8(image::RgbaImage::new(w, h));
+            assert_eq!(count_printed_rows(&img, &config).unwrap(), 10);
+        }
+    }
+
+    #[test]
+    fn test_block_printer_matches_previous_output() {
+        // Output recorded before the block printer was rewritten to build a grid of cells
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(3, 5, |x, y| {
+            if (x + 2 * y) % 3 == 0 {
+                image::Rgba([0, 0, 0, 0])
+            } else {
+                image::Rgba([(40 * x + 10) as u8, (30 * y) as u8, 200, 255])
+            }
+        }));
+        let mut config = Config {
+            truecolor: TrueColorMode::Never,
+            absolute_offset: false,
+            x: 2,
+            ..Default::default()
+        };
+
+        let mut buf = Ansi::new(vec![]);
+        print_to_writecolor(&mut buf, &img, &config).unwrap();
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[2C\x1b[0m\x1b[38;5;20m\x1b[48;5;241m▄\x1b[0m\x1b[38;5;241m\x1b[48;5;56m▄\x1b[0m\x1b[38;5;56m\x1b[48;5;56m▄\x1b[0m\r\n\x1b[2C\x1b[0m\x1b[38;5;247m\x1b[48;5;26m▄\x1b[0m\x1b[38;5;62m\x1b[48;5;62m▄\x1b[0m\x1b[38;5;62m\x1b[48;5;241m▄\x1b[0m\r\n\x1b[2C\x1b[0m\x1b[38;5;32m▀\x1b[0m\x1b[38;5;247m▀\x1b[0m\x1b[38;5;68m▀\x1b[0m\n"
+        );
+
+        config.truecolor = TrueColorMode::Force;
+        config.transparent = true;
+        let mut buf = Ansi::new(vec![]);
+        print_to_writecolor(&mut buf, &img, &config).unwrap();
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[2C\x1b[0m\x1b[38;2;10;30;200m▄\x1b[0m\x1b[38;2;50;0;200m▀\x1b[0m\x1b[38;2;90;30;200m\x1b[48;2;90;0;200m▄\x1b[0m\r\n\x1b[2C\x1b[0m\x1b[38;2;10;60;200m▀\x1b[0m\x1b[38;2;50;90;200m\x1b[48;2;50;60;200m▄\x1b[0m\x1b[38;2;90;90;200m▄\x1b[0m\r\n\x1b[2C\x1b[0m\x1b[38;2;10;120;200m▀\x1b[1C\x1b[0m\x1b[38;2;90;120;200m▀\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_block_printer_compact_sgr() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            6,
+            4,
+            image::Rgba([255, 0, 0, 255]),
+        ));
+        let config = Config {
+            truecolor: TrueColorMode::Never,
+            absolute_offset: false,
+            compact_sgr: true,
+            ..Default::default()
+        };
+        let mut buf = Ansi::new(vec![]);
+        print_to_writecolor(&mut buf, &img, &config).unwrap();
+
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
+        for line in output.lines() {
+            assert_eq!(line.matches("38;5;").count(), 1);
+        }
+        assert_eq!(
+            output,
+            "\x1b[0;38;5;196;48;5;196m▄▄▄▄▄▄\x1b[0m\r\n\x1b[0;38;5;196;48;5;196m▄▄▄▄▄▄\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_block_printer_compact_sgr_runs() {
+        let mut img = image::RgbaImage::from_pixel(4, 1, image::Rgba([0, 0, 255, 255]));
+        img.put_pixel(1, 0, image::Rgba([0, 0, 0, 0]));
+        img.put_pixel(3, 0, image::Rgba([0, 255, 0, 255]));
+        let config = Config {
+            truecolor: TrueColorMode::Force,
+            transparent: true,
+            absolute_offset: false,
+            compact_sgr: true,
+            preserve_sgr: true,
+            ..Default::default()
+        };
+        let mut buf = Ansi::new(vec![]);
+        print_to_writecolor(&mut buf, &DynamicImage::ImageRgba8(img), &config).unwrap();
+
+        // transparent cells do not interrupt a run of the same color
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[38;2;0;0;255;49m▀\x1b[1C▀\x1b[38;2;0;255;0;49m▀\x1b[39;49m\n"
+        );
+    }
+
+    fn write_cell_output(cell: &Cell) -> String {
+        let mut buf = Ansi::new(vec![]);
+        let config = Config {
+            truecolor: TrueColorMode::Force,
+            ..Default::default()
+        };
+        write_cell(&mut buf, cell, &config).unwrap();
+        String::from_utf8(buf.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn test_write_cell_only_bottom() {
+        let cell = Cell::from_halves(None, Some((10, 20, 30)));
+        assert_eq!(write_cell_output(&cell), "\x1b[0m\x1b[38;2;10;20;30m▄");
+    }
+
+    #[test]
+    fn test_write_cell_only_top() {
+        let cell = Cell::from_halves(Some((50, 60, 70)), None);
+        assert_eq!(write_cell_output(&cell), "\x1b[0m\x1b[38;2;50;60;70m▀");
+    }
+
+    #[test]
+    fn test_write_cell_top_and_bottom() {
+        let cell = Cell::from_halves(Some((15, 25, 35)), Some((10, 20, 30)));
+        assert_eq!(
+            write_cell_output(&cell),
+            "\x1b[0m\x1b[38;2;10;20;30m\x1b[48;2;15;25;35m▄"
+        );
+    }
+
+    #[test]
+    fn test_write_cell_no_color() {
+        // expect to print nothing, just move cursor to the right
+        assert_eq!(write_cell_output(&Cell::TRANSPARENT), "\x1b[1C");
+    }
+
+    #[test]
+    fn test_write_cell_bg_color_depth() {
+        let cell = Cell::from_halves(Some((255, 0, 0)), Some((10, 20, 30)));
+        let mut config = Config {
+            truecolor: TrueColorMode::Force,
+            bg_color_depth: Some(crate::ColorDepth::Ansi256),
+            ..Default::default()
+        };
+
+        let mut buf = Ansi::new(vec![]);
+        write_cell(&mut buf, &cell, &config).unwrap();
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[0m\x1b[38;2;10;20;30m\x1b[48;5;196m▄"
+        );
+
+        config.compact_sgr = true;
+        assert_eq!(
+            combined_sgr(&cell, &config),
+            "\x1b[0;38;2;10;20;30;48;5;196m"
+        );
+    }
+
+    #[test]
+    fn test_image_to_cells_last_row() {
+        let mut img = image::RgbaImage::new(2, 3);
+        img.put_pixel(0, 2, image::Rgba([10, 20, 30, 255]));
+        img.put_pixel(1, 1, image::Rgba([40, 50, 60, 255]));
+        let config = Config {
+            transparent: true,
+            ..Default::default()
+        };
+
+        let cells = image_to_cells(&img, &config);
+        assert_eq!(cells.len(), 2);
+        assert_eq!(cells[0][0], Cell::TRANSPARENT);
+        assert_eq!(cells[0][1], Cell::from_halves(None, Some((40, 50, 60))));
+        // the last row only has a top half
+        assert_eq!(cells[1][0], Cell::from_halves(Some((10, 20, 30)), None));
+        assert_eq!(cells[1][0].glyph, UPPER_HALF_BLOCK);
+        assert_eq!(cells[1][1], Cell::TRANSPARENT);
+    }
+
+    #[test]
+    fn test_feather_edges() {
+        let mut img = image::RgbaImage::from_pixel(8, 8, image::Rgba([200, 100, 50, 255]));
+        feather_edges(&mut img, 2);
+
+        for i in 0..8 {
+            for (x, y) in [(i, 0), (i, 7), (0, i), (7, i)] {
+                let alpha = img.get_pixel(x, y)[3];
+                assert!(
+                    alpha > 0 && alpha < 255,
+                    "({}, {}) has alpha {}",
+                    x,
+                    y,
+                    alpha
+                );
+            }
+        }
+        assert_eq!(img.get_pixel(0, 0)[3], 85);
+        assert_eq!(img.get_pixel(1, 4)[3], 170);
+        assert_eq!(img.get_pixel(2, 2)[3], 255);
+        assert_eq!(img.get_pixel(4, 5)[3], 255);
+
+        // the border is blended with the checkerboard
+        let config = Config {
+            feather: 2,
+            ..Default::default()
+        };
+        let blended = get_pixel_color(&img, 0, 0, &config).unwrap();
+        assert_ne!(blended, (200, 100, 50));
+
+        // with true transparency about as many pixels are drawn as the alpha says
+        let config = Config {
+            feather: 2,
+            transparent: true,
+            ..Default::default()
+        };
+        let drawn = |alpha: u8| {
+            let img = image::RgbaImage::from_pixel(4, 4, image::Rgba([200, 100, 50, alpha]));
+            (0..16)
+                .filter_map(|i| get_pixel_color(&img, i % 4, i / 4, &config))
+                .inspect(|&rgb| assert_eq!(rgb, (200, 100, 50)))
+                .count()
+        };
+        assert_eq!(drawn(85), 5);
+        assert_eq!(drawn(170), 11);
+        assert_eq!(drawn(255), 16);
+    }
+
+    #[test]
+    fn test_partial_alpha_without_feather() {
+        let img = image::RgbaImage::from_pixel(2, 2, image::Rgba([200, 100, 50, 128]));
+        let config = Config::default();
+        assert_eq!(get_pixel_color(&img, 0, 0, &config), Some((200, 100, 50)));
+        let config = Config {
+            transparent: true,
+            ..Default::default()
+        };
+        assert_eq!(get_pixel_color(&img, 1, 0, &config), Some((200, 100, 50)));
+    }
+
+    #[test]
+    fn test_background_variants() {
+        let img = image::RgbaImage::new(8, 8);
+        let mut config = Config {
+            background: Background::Solid((1, 2, 3)),
+            ..Default::default()
+        };
+        for (x, y, _) in img.enumerate_pixels() {
+            assert_eq!(get_pixel_color(&img, x, y, &config), Some((1, 2, 3)));
+        }
+
+        // dots repeat every fourth pixel, unlike the checkerboard alternating on every one
+        config.background = Background::Dots;
+        assert_eq!(get_pixel_color(&img, 0, 0, &config), Some(BACKGROUND_DARK));
+        assert_eq!(get_pixel_color(&img, 1, 1, &config), Some(BACKGROUND_LIGHT));
+        assert_eq!(get_pixel_color(&img, 2, 2, &config), Some(BACKGROUND_LIGHT));
+        assert_eq!(get_pixel_color(&img, 4, 4, &config), Some(BACKGROUND_DARK));
+        assert_eq!(get_pixel_color(&img, 4, 1, &config), Some(BACKGROUND_LIGHT));
+
+        config.background = Background::Lines;
+        assert_eq!(get_pixel_color(&img, 3, 1, &config), Some(BACKGROUND_DARK));
+        assert_eq!(get_pixel_color(&img, 2, 1, &config), Some(BACKGROUND_LIGHT));
+
+        config.background = Background::Transparent;
+        assert_eq!(get_pixel_color(&img, 0, 0, &config), None);
+
+        // opaque pixels are unaffected
+        let img = image::RgbaImage::from_pixel(2, 2, image::Rgba([9, 8, 7, 255]));
+        config.background = Background::Solid((1, 2, 3));
+        assert_eq!(get_pixel_color(&img, 1, 1, &config), Some((9, 8, 7)));
+    }
+
+    #[test]
+    fn test_prepare_matches_print() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(40, 25));
+        let config = Config {
+            width: Some(12),
+            truecolor: TrueColorMode::Never,
+            absolute_offset: false,
+            ..Default::default()
+        };
+
+        let prepared = prepare(&img, &config).unwrap();
+        let mut buf = Ansi::new(vec![]);
+        let (w, h) = print_to_writecolor(&mut buf, &img, &config).unwrap();
+        assert_eq!(prepared.width(), w);
+        assert_eq!(prepared.height(), 2 * h - 1);
+        assert_eq!(
+            image_to_cells(&prepared.to_rgba8(), &config).len() as u32,
+            h
+        );
+    }
+
+    #[test]
+    fn test_write_inline_end_cursor() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(6, 4));
+        let config = Config {
+            height: Some(2),
+            truecolor: TrueColorMode::Never,
+            ..Default::default()
+        };
+
+        let mut buf = Ansi::new(vec![]);
+        let end = BlockPrinter::write_inline(&mut buf, &img, &config, (10, 5)).unwrap();
+        assert_eq!(end, (16, 6));
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
+        assert!(output.starts_with("\n\x1b[6;11H"));
+        assert!(output.contains("\x1b[7;11H"));
+        assert!(output.ends_with("\x1b[7;17H"));
+
+        // on the last row, the terminal scrolls to make space
+        let mut buf = Ansi::new(vec![]);
+        let end = BlockPrinter::write_inline(&mut buf, &img, &config, (10, 23)).unwrap();
+        assert_eq!(end, (16, 23));
+    }
+
+    #[test]
+    fn test_truecolor_modes() {
+        let cell = Cell::from_halves(None, Some((10, 20, 30)));
+        let mut config = Config {
+            truecolor: TrueColorMode::Force,
+            ..Default::default()
+        };
+        let mut buf = Ansi::new(vec![]);
+        write_cell(&mut buf, &cell, &config).unwrap();
+        assert!(std::str::from_utf8(buf.get_ref())
+            .unwrap()
+            .contains("38;2;10;20;30"));
+
+        config.truecolor = TrueColorMode::Never;
+        let mut buf = Ansi::new(vec![]);
+        write_cell(&mut buf, &cell, &config).unwrap();
+        assert!(std::str::from_utf8(buf.get_ref())
+            .unwrap()
+            .contains("38;5;"));
+
+        assert_eq!(TrueColorMode::from(true), TrueColorMode::Force);
+        assert_eq!(TrueColorMode::from(false), TrueColorMode::Never);
+    }
+
+    #[test]
+    fn test_odd_row_modes() {
+        let img = image::RgbaImage::from_pixel(1, 3, image::Rgba([10, 20, 30, 255]));
+        let mut config = Config::default();
+        let last_row = |config: &Config| image_to_cells(&img, config)[1][0];
+
+        assert_eq!(
+            last_row(&config),
+            Cell {
+                fg: Some((10, 20, 30)),
+                bg: None,
+                glyph: UPPER_HALF_BLOCK
+            }
+        );
+
+        config.odd_row_mode = OddRowMode::FullBlock;
+        assert_eq!(
+            last_row(&config),
+            Cell {
+                fg: Some((10, 20, 30)),
+                bg: None,
+                glyph: FULL_BLOCK
+            }
+        );
+
+        // padded with the background, or left to the terminal with true transparency
+        config.odd_row_mode = OddRowMode::PadTransparent;
+        assert_eq!(
+            last_row(&config),
+            Cell {
+                fg: Some(BACKGROUND_LIGHT),
+                bg: Some((10, 20, 30)),
+                glyph: LOWER_HALF_BLOCK
+            }
+        );
+        config.transparent = true;
+        assert_eq!(last_row(&config).glyph, UPPER_HALF_BLOCK);
+    }
+
+    #[test]
+    fn test_render_cells() {
+        let mut img = image::RgbaImage::from_pixel(30, 20, image::Rgba([0, 0, 0, 255]));
+        img.put_pixel(0, 0, image::Rgba([200, 10, 10, 255]));
+        img.put_pixel(0, 1, image::Rgba([200, 10, 10, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+        let config = Config {
+            width: Some(30),
+            truecolor: TrueColorMode::Never,
+            absolute_offset: false,
+            filter: crate::FilterType::Nearest,
+            ..Default::default()
+        };
+
+        let cells = render_cells(&img, &config).unwrap();
+        let mut buf = Ansi::new(vec![]);
+        let (w, h) = print_to_writecolor(&mut buf, &img, &config).unwrap();
+        assert_eq!(cells.len() as u32, h);
+        assert!(cells.iter().all(|row| row.len() as u32 == w));
+
+        assert_eq!(cells[0][0].fg, Some((200, 10, 10)));
+        assert_eq!(cells[0][0].bg, Some((200, 10, 10)));
+        assert_eq!(cells[0][1].fg, Some((0, 0, 0)));
+    }
+
+    #[test]
+    fn test_block_printer_reveal_fraction() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(10, 20));
+        let mut config = Config {
+            truecolor: TrueColorMode::Never,
+            absolute_offset: false,
+            height: Some(10),
+            reveal_fraction: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(count_printed_rows(&img, &config).unwrap(), 5);
+
+        config.reveal_fraction = 1.0;
+        assert_eq!(count_printed_rows(&img, &config).unwrap(), 10);
+    }
+
+    #[test]
+    fn test_block_printer_transparent_as_space() {
+        let mut img = image::RgbaImage::new(3, 2);
+        img.put_pixel(1, 0, image::Rgba([255, 0, 0, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+        let mut config = Config {
+            transparent: true,
+            transparent_as_space: true,
+            truecolor: TrueColorMode::Never,
+            absolute_offset: false,
+            ..Default::default()
+        };
+
+        let mut buf = Ansi::new(vec![]);
+        print_to_writecolor(&mut buf, &img, &config).unwrap();
+        assert_eq!(
+            std::str::from_utf8(buf.get_ref()).unwrap(),
+            "\x1b[0m \x1b[0m\x1b[38;5;196m▀\x1b[0m \x1b[0m\n"
+        );
+
+        config.compact_sgr = true;
+        let mut buf = Ansi::new(vec![]);
+        print_to_writecolor(&mut buf, &img, &config).unwrap();
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
+        assert!(!output.contains("\x1b[1C"));
+        assert_eq!(output.matches(' ').count(), 2);
+    }
+
+    #[test]
+    fn test_vertical_smoothing() {
+        // one pixel tall black and white stripes
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(4, 8, |_, y| {
+            let v = if y % 2 == 0 { 255 } else { 0 };
+            image::Rgba([v, v, v, 255])
+        }));
+        let mut config = Config {
+            height: Some(4),
+            filter: crate::FilterType::Nearest,
+            ..Default::default()
+        };
+        let contrast = |config: &Config| {
+            let cell = render_cells(&img, config).unwrap()[1][1];
+            let (top, bottom) = (cell.bg.unwrap(), cell.fg.unwrap());
+            (top.0 as i32 - bottom.0 as i32).abs()
+        };
+
+        assert_eq!(contrast(&config), 255);
+        config.vertical_smoothing = true;
+        assert!(contrast(&config) < 128);
+
+        // solid areas are left as they are
+        let solid = image::RgbaImage::from_pixel(2, 3, image::Rgba([10, 20, 30, 255]));
+        assert_eq!(smooth_vertically(&solid), solid);
+        let single_row = image::RgbaImage::from_pixel(2, 1, image::Rgba([10, 20, 30, 255]));
+        assert_eq!(smooth_vertically(&single_row), single_row);
+    }
+
+    #[test]
+    fn test_overlay_grid_crosshair() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            9,
+            10,
+            image::Rgba([0, 0, 0, 255]),
+        ));
+        let mut config = Config {
+            height: Some(5),
+            ..Default::default()
+        };
+        let black = Cell::from_halves(Some((0, 0, 0)), Some((0, 0, 0)));
+        let cells = render_cells(&img, &config).unwrap();
+        assert!(cells.iter().flatten().all(|cell| *cell == black));
+
+        config.overlay_grid = true;
+        let cells = render_cells(&img, &config).unwrap();
+        let tinted = Cell::from_halves(Some((127, 0, 127)), Some((127, 0, 127)));
+        assert_eq!(cells[2][4], tinted);
+        assert_eq!(cells[0][4], tinted);
+        assert_eq!(cells[2][0], tinted);
+        assert_eq!(cells[0][0], black);
+        assert_eq!(cells[4][8], black);
+    }
+
+    #[test]
+    fn test_full_block_fallback() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            4,
+            4,
+            image::Rgba([10, 20, 30, 255]),
+        ));
+        let config = Config {
+            height: Some(2),
+            prefer_full_block: true,
+            ..Default::default()
+        };
+        // render_cells, in a terminal with the given TERM
+        let render_in = |term: &str, config: &Config| {
+            let full_blocks =
+                config.prefer_full_block && crate::utils::block_font_gaps(Some(term), None);
+            let img = prepare_pixels(&img, config, full_blocks).unwrap();
+            pixels_to_cells(&img, config, full_blocks)
+        };
+
+        let cells = render_in("linux", &config);
+        assert_eq!(cells.len(), 2);
+        assert!(cells.iter().flatten().all(|cell| cell.glyph == FULL_BLOCK));
+        assert_eq!(cells[1][3].fg, Some((10, 20, 30)));
+
+        // half blocks are kept where they are drawn properly
+        let cells = render_in("xterm-kitty", &config);
+        assert!(cells
+            .iter()
+            .flatten()
+            .all(|cell| cell.glyph == LOWER_HALF_BLOCK));
+
+        let config = Config {
+            prefer_full_block: false,
+            ..config
+        };
+        let cells = render_in("linux", &config);
+        assert!(cells
+            .iter()
+            .flatten()
+            .all(|cell| cell.glyph == LOWER_HALF_BLOCK));
+        assert!(!use_full_blocks(&config));
+    }
+
+    #[test]
+    fn test_block_printer_center_anchor() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(10, 20));
+        let config = Config {
+            x: 40,
+            y: 12,
+            anchor: crate::Anchor::Center,
+            truecolor: TrueColorMode::Never,
+            ..Default::default()
+        };
+        let mut buf = Ansi::new(vec![]);
+        assert_eq!(
+            print_to_writecolor(&mut buf, &img, &config).unwrap(),
+            (10, 10)
+        );
+
+        // the top left corner is at (35, 7), escape sequences count from 1
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
+        assert!(output.starts_with("\x1b[8;1H\x1b[35C"));
+    }
+
+    #[test]
+    fn test_block_printer_capabilities() {
+        let capabilities = BlockPrinter.capabilities();
+        assert!(capabilities.transparency);
+        assert!(capabilities.truecolor);
+        assert_eq!(capabilities.pixels_per_cell, Some((1, 2)));
+        assert!(!capabilities.animation);
+    }
+
+    #[test]
+    fn test_banner_crops_center() {
+        // a tall image, red in the middle and blue elsewhere
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(10, 100, |_, y| {
+            if (45..55).contains(&y) {
+                image::Rgba([255, 0, 0, 255])
+            } else {
+                image::Rgba([0, 0, 255, 255])
+            }
+        }));
+        let config = Config {
+            banner_rows: Some(3),
+            absolute_offset: false,
+            truecolor: TrueColorMode::Force,
+            ..Default::default()
+        };
+        let mut buf = Ansi::new(vec![]);
+        assert_eq!(
+            print_to_writecolor(&mut buf, &img, &config).unwrap(),
+            (80, 3)
+        );
+
+        let cells = render_cells(&img, &config).unwrap();
+        assert_eq!(cells.len(), 3);
+        for row in &cells {
+            assert_eq!(row.len(), 80);
+            assert!(row
+                .iter()
+                .all(|cell| cell.fg == Some((255, 0, 0)) && cell.bg == Some((255, 0, 0))));
+        }
+    }
+
+    #[test]
+    fn test_grid_square_cells() {
+        let grid = RgbaImage::from_fn(3, 2, |x, y| image::Rgba([x as u8, y as u8, 0, 255]));
+        let config = Config::default();
+
+        // cells twice as wide as tall need two rows of one column per data cell
+        let span = square_span(Some((2.0, 1.0)));
+        assert_eq!(span, (1, 2));
+        let cells = grid_to_cells(&grid, span, &config);
+        assert_eq!(cells.len(), 4);
+        for (row, cells_row) in cells.iter().enumerate() {
+            assert_eq!(cells_row.len(), 3);
+            for (col, cell) in cells_row.iter().enumerate() {
+                assert_eq!(cell.glyph, FULL_BLOCK);
+                assert_eq!(cell.fg, Some((col as u8, (row / 2) as u8, 0)));
+            }
+        }
+
+        // usual cells, twice as tall as wide
+        assert_eq!(square_span(Some((8.0, 16.0))), (2, 1));
+        assert_eq!(square_span(None), (2, 1));
+        let cells = grid_to_cells(&grid, (2, 1), &config);
+        assert_eq!((cells[0].len(), cells.len()), (6, 2));
+    }
+
+    #[test]
+    fn test_block_printer_pane() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(100, 100));
+        let config = Config {
+            pane: Some(crate::Rect {
+                x: 5,
+                y: 5,
+                width: 20,
+                height: 10,
+            }),
+            truecolor: TrueColorMode::Never,
+            ..Default::default()
+        };
+        let mut buf = Ansi::new(vec![]);
+        let (w, h) = BlockPrinter::write(&mut buf, &img, &config).unwrap();
+        assert!(w <= 20 && h <= 10);
+        assert_eq!(w, 20);
+
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
+        assert!(output.starts_with("\x1b[6;1H\x1b[5C"));
+        assert_eq!(output.matches("\x1b[5C").count(), h as usize);
+    }
+
+    #[test]
+    fn test_block_printer_safe_area_inset() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(200, 100));
+        let config = Config {
+            safe_area_inset: (2, 3, 2, 7),
+            truecolor: TrueColorMode::Never,
+            ..Default::default()
+        };
+        let mut buf = Ansi::new(vec![]);
+        let (w, h) = BlockPrinter::write(&mut buf, &img, &config).unwrap();
+        assert!(w <= 80 - 10 && h <= 24 - 4);
+        assert_eq!(w, 70);
+
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
+        assert!(output.starts_with("\x1b[3;1H\x1b[7C"));
+        assert_eq!(output.matches("\x1b[7C").count(), h as usize);
+    }
+
+    #[test]
+    fn test_bar_cells_eighths() {
+        let color = (10, 20, 30);
+        let glyphs = [
+            " ", "\u{258f}", "\u{258e}", "\u{258d}", "\u{258c}", "\u{258b}", "\u{258a}",
+            "\u{2589}", "\u{2588}",
+        ];
+        for (eighths, glyph) in glyphs.iter().enumerate() {
+            let cells = bar_cells(eighths as f32 / 8.0, 1, color);
+            assert_eq!(cells.len(), 1);
+            assert_eq!(cells[0].glyph, *glyph);
+            let fg = if eighths == 0 { None } else { Some(color) };
+            assert_eq!(cells[0].fg, fg);
+            assert_eq!(cells[0].bg, None);
+        }
+
+        // a bar of 3 cells filled halfway, the fraction being clamped
+        let cells = bar_cells(0.5, 3, color);
+        let glyphs: Vec<&str> = cells.iter().map(|cell| cell.glyph).collect();
+        assert_eq!(glyphs, vec!["\u{2588}", "\u{258c}", " "]);
+        assert!(bar_cells(2.0, 3, color)
+            .iter()
+            .all(|c| c.glyph == FULL_BLOCK));
+        assert!(bar_cells(-1.0, 3, color).iter().all(Cell::is_transparent));
+    }
+
+    #[test]
+    fn test_block_printer_metadata() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(10, 8));
+        let mut config = Config {
+            absolute_offset: false,
+            truecolor: TrueColorMode::Never,
+            ..Default::default()
+        };
+        let mut buf = Ansi::new(vec![]);
+        BlockPrinter::write(&mut buf, &img, &config).unwrap();
+        assert!(!std::str::from_utf8(buf.get_ref())
+            .unwrap()
+            .contains("\x1b]1337"));
+
+        config.emit_metadata = true;
+        let mut buf = Ansi::new(vec![]);
+        BlockPrinter::write(&mut buf, &img, &config).unwrap();
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
+        assert!(output
+            .ends_with("\x1b]1337;ViuerMetadata=original=10x8;rendered=10x4;printer=block\x07"));
+    }
+
+    #[test]
+    fn test_render_layer_transparent_cells() {
+        // the left half is transparent
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 2, |x, _| {
+            image::Rgba([200, 100, 0, if x < 2 { 0 } else { 255 }])
+        }));
+        let config = Config {
+            transparent: true,
+            width: Some(4),
+            height: Some(1),
+            ..Default::default()
+        };
+        let layer = render_layer(&img, &config).unwrap();
+        assert_eq!(layer.len(), 1);
+        assert_eq!(layer[0][..2], [None, None]);
+        for cell in &layer[0][2..] {
+            assert_eq!(cell.unwrap().fg, Some((200, 100, 0)));
+        }
+
+        // with a background, every cell is drawn
+        let config = Config {
+            transparent: false,
+            ..config
+        };
+        let layer = render_layer(&img, &config).unwrap();
+        assert!(layer[0].iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn test_badge() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            6,
+            4,
+            image::Rgba([0, 255, 0, 255]),
+        ));
+        let mut badge = Badge::new("NEW");
+        badge.bg = (0, 0, 255);
+        let config = Config {
+            absolute_offset: false,
+            truecolor: crate::TrueColorMode::Force,
+            compact_sgr: true,
+            badge: Some(badge),
+            ..Default::default()
         };
-        print_to_writecolor(&mut stream, img, config)
+
+        let mut buf = termcolor::Ansi::new(vec![]);
+        assert_eq!(
+            BlockPrinter::write(&mut buf, &img, &config).unwrap(),
+            (6, 2)
+        );
+        let output = String::from_utf8(buf.into_inner()).unwrap();
+        let rows: Vec<&str> = output.split("\r\n").collect();
+
+        // the chip covers the last five cells of the top row, the bottom row is untouched
+        let green = "\x1b[0;38;2;0;255;0;48;2;0;255;0m\u{2584}";
+        let chip = "\x1b[0m\x1b[38;2;255;255;255m\x1b[48;2;0;0;255m NEW \x1b[0m";
+        assert_eq!(rows[0], format!("{}{}\x1b[0m", green, chip));
+        assert_eq!(
+            rows[1],
+            format!("{}{}\x1b[0m\n", green, "\u{2584}".repeat(5))
+        );
     }
-}
 
-fn print_to_writecolor(
-    stdout: &mut impl WriteColor,
-    img: &DynamicImage,
-    config: &Config,
-) -> ViuResult<(u32, u32)> {
-    // adjust with x=0 and handle horizontal offset entirely below
-    adjust_offset(stdout, &Config { x: 0, ..*config })?;
+    #[test]
+    fn test_place_badge() {
+        let mut badge = Badge::new("NEW");
+        badge.anchor = crate::Anchor::BottomLeft;
+        let chip = place_badge(&badge, (10, 4)).unwrap();
+        assert_eq!((chip.row, chip.col, chip.text.as_str()), (3, 0, " NEW "));
 
-    // resize the image so that it fits in the constraints, if any
-    let img = super::resize(img, config.width, config.height);
-    let (width, height) = img.dimensions();
+        // clipped to the width of the image
+        badge.anchor = crate::Anchor::Center;
+        let chip = place_badge(&badge, (3, 3)).unwrap();
+        assert_eq!((chip.row, chip.col, chip.text.as_str()), (1, 0, " NE"));
 
-    let mut row_color_buffer: Vec<ColorSpec> = vec![ColorSpec::new(); width as usize];
-    let img_buffer = img.to_rgba8(); //TODO: Can conversion be avoided?
+        assert!(place_badge(&badge, (0, 3)).is_none());
+    }
 
-    for (curr_row, img_row) in img_buffer.enumerate_rows() {
-        let is_even_row = curr_row % 2 == 0;
-        let is_last_row = curr_row == height - 1;
+    #[test]
+    fn test_align_with_nudge() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(10, 8));
+        let mut config = Config {
+            align: Some(crate::Anchor::Center),
+            x: 2,
+            ..Default::default()
+        };
 
-        // move right if x offset is specified
-        if config.x > 0 && (!is_even_row || is_last_row) {
-            execute!(stdout, MoveRight(config.x))?;
-        }
+        // centered in the 80x24 terminal of the tests at (35, 10), then shifted right
+        let mut buf = termcolor::Ansi::new(vec![]);
+        assert_eq!(
+            BlockPrinter::write(&mut buf, &img, &config).unwrap(),
+            (10, 4)
+        );
+        let output = String::from_utf8(buf.into_inner()).unwrap();
+        assert!(output.starts_with("\x1b[11;1H\x1b[37C"));
 
-        for pixel in img_row {
-            // choose the half block's color
-            let color = if is_pixel_transparent(pixel) {
-                if config.transparent {
-                    None
-                } else {
-                    Some(get_transparency_color(curr_row, pixel.0, config.truecolor))
-                }
-            } else {
-                Some(get_color_from_pixel(pixel, config.truecolor))
-            };
+        config.align_offsets = crate::AlignOffsets::Ignore;
+        let mut buf = termcolor::Ansi::new(vec![]);
+        BlockPrinter::write(&mut buf, &img, &config).unwrap();
+        let output = String::from_utf8(buf.into_inner()).unwrap();
+        assert!(output.starts_with("\x1b[11;1H\x1b[35C"));
 
-            // Even rows modify the background, odd rows the foreground
-            // because lower half blocks are used by default
-            let colorspec = &mut row_color_buffer[pixel.0 as usize];
-            if is_even_row {
-                colorspec.set_bg(color);
-                if is_last_row {
-                    write_colored_character(stdout, colorspec, true)?;
-                }
-            } else {
-                colorspec.set_fg(color);
-                write_colored_character(stdout, colorspec, false)?;
-            }
-        }
+        // nudged up, which absolute offsets do not allow otherwise
+        config.align_offsets = crate::AlignOffsets::Nudge;
+        config.y = -3;
+        let mut buf = termcolor::Ansi::new(vec![]);
+        BlockPrinter::write(&mut buf, &img, &config).unwrap();
+        let output = String::from_utf8(buf.into_inner()).unwrap();
+        assert!(output.starts_with("\x1b[8;1H\x1b[37C"));
+    }
 
-        if !is_even_row && !is_last_row {
-            stdout.reset()?;
-            writeln!(stdout, "\r")?;
-        }
+    #[test]
+    fn test_split_across_regions() {
+        // every row of cells has its own color
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(4, 20, |_, y| {
+            image::Rgba([(y / 2) as u8 * 20, 0, 0, 255])
+        }));
+        let config = Config {
+            width: Some(4),
+            truecolor: crate::TrueColorMode::Force,
+            ..Default::default()
+        };
+        let cells = render_cells(&img, &config).unwrap();
+        assert_eq!(cells.len(), 10);
+
+        let top = Rect {
+            x: 0,
+            y: 0,
+            width: 80,
+            height: 5,
+        };
+        let bottom = Rect {
+            x: 10,
+            y: 15,
+            width: 2,
+            height: 5,
+        };
+        let split = split_cells(cells.clone(), &[top, bottom, top]);
+        assert_eq!(split.len(), 2);
+        assert_eq!(split[0], (top, cells[..5].to_vec()));
+        let clipped: Vec<Vec<Cell>> = cells[5..].iter().map(|row| row[..2].to_vec()).collect();
+        assert_eq!(split[1], (bottom, clipped));
+
+        let mut buf = termcolor::Ansi::new(vec![]);
+        let printed = BlockPrinter::write_split(&mut buf, &img, &[top, bottom], &config).unwrap();
+        assert_eq!(printed, (4, 10));
+        let output = String::from_utf8(buf.into_inner()).unwrap();
+        let second = output.find("\x1b[16;1H\x1b[10C").unwrap();
+        assert!(output.starts_with("\x1b[1;1H"));
+        // the sixth row, the first one of the second region, is red 100
+        assert!(!output[..second].contains("38;2;100;0;0"));
+        assert!(output[second..].starts_with("\x1b[16;1H\x1b[10C\x1b[0m\x1b[38;2;100;0;0m"));
     }
 
-    stdout.reset()?;
-    writeln!(stdout)?;
-    stdout.flush()?;
+    #[test]
+    fn test_colon_sgr_syntax() {
+        let mut config = Config {
+            truecolor: crate::TrueColorMode::Force,
+            sgr_syntax: SgrSyntax::Colon,
+            ..Default::default()
+        };
+        let cell = Cell {
+            fg: Some((1, 2, 3)),
+            bg: Some((4, 5, 6)),
+            glyph: LOWER_HALF_BLOCK,
+        };
+        let mut buf = termcolor::Ansi::new(vec![]);
+        write_cell(&mut buf, &cell, &config).unwrap();
+        assert_eq!(
+            String::from_utf8(buf.into_inner()).unwrap(),
+            "\x1b[0;38:2::1:2:3;48:2::4:5:6m\u{2584}"
+        );
 
-    Ok((width, height / 2 + height % 2))
-}
+        config.truecolor = crate::TrueColorMode::Never;
+        config.preserve_sgr = true;
+        let cell = Cell { bg: None, ..cell };
+        assert_eq!(combined_sgr(&cell, &config), "\x1b[38:5:16;49m");
+    }
 
-fn write_colored_character(
-    stdout: &mut impl WriteColor,
-    c: &ColorSpec,
-    is_last_row: bool,
-) -> ViuResult {
-    let out_color;
-    let out_char;
-    let mut new_color;
-
-    // On the last row use upper blocks and leave the bottom half empty (transparent)
-    if is_last_row {
-        new_color = ColorSpec::new();
-        if let Some(bg) = c.bg() {
-            new_color.set_fg(Some(*bg));
-            out_char = UPPER_HALF_BLOCK;
-        } else {
-            execute!(stdout, MoveRight(1))?;
-            return Ok(());
-        }
-        out_color = &new_color;
-    } else {
-        match (c.fg(), c.bg()) {
-            (None, None) => {
-                // completely transparent
-                execute!(stdout, MoveRight(1))?;
-                return Ok(());
-            }
-            (Some(bottom), None) => {
-                // only top transparent
-                new_color = ColorSpec::new();
-                new_color.set_fg(Some(*bottom));
-                out_color = &new_color;
-                out_char = LOWER_HALF_BLOCK;
-            }
-            (None, Some(top)) => {
-                // only bottom transparent
-                new_color = ColorSpec::new();
-                new_color.set_fg(Some(*top));
-                out_color = &new_color;
-                out_char = UPPER_HALF_BLOCK;
-            }
-            (Some(_top), Some(_bottom)) => {
-                // both parts have a color
-                out_color = c;
-                out_char = LOWER_HALF_BLOCK;
-            }
+    #[test]
+    fn test_write_cells_from_start_column() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(3, 6));
+        let config = Config {
+            x: 2,
+            ..Default::default()
+        };
+        let cells = render_cells(&img, &config).unwrap();
+
+        let mut buf = termcolor::Ansi::new(vec![]);
+        write_cells(&mut buf, &cells, &config, None, Some(10)).unwrap();
+        let output = String::from_utf8(buf.into_inner()).unwrap();
+        let rows: Vec<&str> = output.split("\r\n").collect();
+        assert_eq!(rows.len(), 3);
+        // columns are counted from 1, the start column from 0
+        for row in rows {
+            assert!(row.starts_with("\x1b[13G"), "{:?}", row);
         }
     }
-    stdout.set_color(out_color)?;
-    write!(stdout, "{}", out_char)?;
 
-    Ok(())
-}
+    #[test]
+    fn test_palette() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(8, 8, |x, y| {
+            image::Rgba([
+                x as u8 * 30,
+                y as u8 * 30,
+                128,
+                if x == 0 { 0 } else { 255 },
+            ])
+        }));
+        let palette = vec![(20, 20, 40), (240, 230, 200)];
+        let config = Config {
+            palette: Some(palette.clone()),
+            ..Default::default()
+        };
 
-fn is_pixel_transparent(pixel: (u32, u32, &Rgba<u8>)) -> bool {
-    pixel.2[3] == 0
-}
+        let cells = render_cells(&img, &config).unwrap();
+        let colors: Vec<(u8, u8, u8)> = cells
+            .iter()
+            .flatten()
+            .flat_map(|cell| [cell.fg, cell.bg])
+            .flatten()
+            .collect();
+        assert_eq!(colors.len(), 8 * 4 * 2);
+        assert!(colors.iter().all(|color| palette.contains(color)));
+        assert!(palette.iter().all(|color| colors.contains(color)));
 
-fn get_transparency_color(row: u32, col: u32, truecolor: bool) -> Color {
-    //imitate the transparent chess board pattern
-    let rgb = if row % 2 == col % 2 {
-        CHECKERBOARD_BACKGROUND_DARK
-    } else {
-        CHECKERBOARD_BACKGROUND_LIGHT
-    };
-    if truecolor {
-        Color::Rgb(rgb.0, rgb.1, rgb.2)
-    } else {
-        Color::Ansi256(ansi256_from_rgb(rgb))
+        let config = Config {
+            palette: Some(Vec::new()),
+            ..Default::default()
+        };
+        let mut buf = termcolor::Ansi::new(vec![]);
+        assert!(BlockPrinter::write(&mut buf, &img, &config).is_err());
     }
-}
 
-fn get_color_from_pixel(pixel: (u32, u32, &Rgba<u8>), truecolor: bool) -> Color {
-    let (_x, _y, data) = pixel;
-    let rgb = (data[0], data[1], data[2]);
-    if truecolor {
-        Color::Rgb(rgb.0, rgb.1, rgb.2)
-    } else {
-        Color::Ansi256(ansi256_from_rgb(rgb))
+    #[test]
+    fn test_colormap_ramp() {
+        let ramp = RgbaImage::from_fn(16, 2, |x, _| {
+            let v = (x * 17) as u8;
+            Rgba([v, v, v, 255])
+        });
+        let config = Config {
+            absolute_offset: false,
+            width: Some(16),
+            colormap: Some(Colormap::Viridis),
+            truecolor: TrueColorMode::Force,
+            ..Default::default()
+        };
+        let cells = render_cells(&DynamicImage::ImageRgba8(ramp), &config).unwrap();
+        assert_eq!(cells.len(), 1);
+        assert_eq!(cells[0][0].fg, Some((68, 1, 84)));
+        assert_eq!(cells[0][15].fg, Some((253, 231, 37)));
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use termcolor::{Ansi, Color};
-
-    // Note: truecolor is not supported in CI. Hence, it should be disabled when writing the tests
 
     #[test]
-    fn test_block_printer_e2e() {
-        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(5, 4));
-        let mut buf = Ansi::new(vec![]);
+    fn test_background_image() {
+        // the right half is transparent
+        let img = RgbaImage::from_fn(8, 4, |x, _| match x < 4 {
+            true => Rgba([255, 0, 0, 255]),
+            false => Rgba([0, 0, 0, 0]),
+        });
+        let background = RgbaImage::from_pixel(2, 2, Rgba([0, 200, 0, 255]));
+        let config = Config {
+            absolute_offset: false,
+            width: Some(8),
+            background_image: Some(DynamicImage::ImageRgba8(background)),
+            truecolor: TrueColorMode::Force,
+            filter: crate::FilterType::Nearest,
+            ..Default::default()
+        };
+        let cells = render_cells(&DynamicImage::ImageRgba8(img), &config).unwrap();
+        assert_eq!(cells.len(), 2);
+        for row in &cells {
+            assert_eq!(row[0].fg, Some((255, 0, 0)));
+            assert_eq!(row[7].fg, Some((0, 200, 0)));
+            assert_eq!(row[7].bg, Some((0, 200, 0)));
+        }
+    }
 
+    #[test]
+    fn test_solid_fast_path() {
         let config = Config {
-            truecolor: false,
+            absolute_offset: false,
+            width: Some(50),
+            truecolor: TrueColorMode::Force,
             ..Default::default()
         };
+        let solid = RgbaImage::from_pixel(50, 50, Rgba([10, 120, 200, 255]));
+        // a linear congruential generator, for reproducible noise
+        let mut seed = 7u32;
+        let noisy = RgbaImage::from_fn(50, 50, |_, _| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let [r, g, b, _] = seed.to_le_bytes();
+            Rgba([r, g, b, 255])
+        });
 
-        let (w, h) = print_to_writecolor(&mut buf, &img, &config).unwrap();
-        assert_eq!((w, h), (5, 2));
+        let mut solid_buf = Ansi::new(vec![]);
+        let size = print_to_writecolor(&mut solid_buf, &DynamicImage::ImageRgba8(solid), &config);
+        assert_eq!(size.unwrap(), (50, 25));
+        let mut noisy_buf = Ansi::new(vec![]);
+        print_to_writecolor(&mut noisy_buf, &DynamicImage::ImageRgba8(noisy), &config).unwrap();
 
-        assert_eq!(
-            std::str::from_utf8(buf.get_ref()).unwrap(),
-            "\x1b[1;1H\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\x1b[38;5;241m\x1b[48;5;247m▄\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\x1b[38;5;241m\x1b[48;5;247m▄\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\r\n\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\x1b[38;5;241m\x1b[48;5;247m▄\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\x1b[38;5;241m\x1b[48;5;247m▄\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\n"
-        );
+        let solid = std::str::from_utf8(solid_buf.get_ref()).unwrap();
+        assert!(solid.len() * 10 < noisy_buf.get_ref().len());
+        assert_eq!(solid.matches("\x1b[38;2;10;120;200m").count(), 25);
+        assert_eq!(solid.matches("\x1b[48;2;10;120;200m").count(), 25);
+        assert_eq!(solid.matches(LOWER_HALF_BLOCK).count(), 50 * 25);
     }
 
     #[test]
-    fn test_block_printer_e2e_transparent() {
-        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(5, 4));
-        let mut buf = Ansi::new(vec![]);
-
+    fn test_huge_offsets_saturate() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(6, 4));
         let config = Config {
-            transparent: true,
+            x: u16::MAX - 2,
+            y: 3,
+            truecolor: TrueColorMode::Never,
             ..Default::default()
         };
 
-        let (w, h) = print_to_writecolor(&mut buf, &img, &config).unwrap();
-        assert_eq!((w, h), (5, 2));
-
+        let mut buf = Ansi::new(vec![]);
         assert_eq!(
-            std::str::from_utf8(buf.get_ref()).unwrap(),
-            "\x1b[1;1H\x1b[1C\x1b[1C\x1b[1C\x1b[1C\x1b[1C\x1b[0m\r\n\x1b[1C\x1b[1C\x1b[1C\x1b[1C\x1b[1C\x1b[0m\n"
+            print_to_writecolor(&mut buf, &img, &config).unwrap(),
+            (6, 2)
         );
-    }
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
+        assert!(output.starts_with("\x1b[4;1H\x1b[65533C"));
+        assert_eq!(output.matches("\x1b[65533C").count(), 2);
 
-    #[test]
-    fn test_block_printer_e2e_odd_height() {
-        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(4, 3));
+        // the end of the image lies beyond the largest addressable column
         let mut buf = Ansi::new(vec![]);
+        let end = BlockPrinter::write_inline(&mut buf, &img, &config, (u16::MAX - 2, 5)).unwrap();
+        assert_eq!(end, (u16::MAX - 1, 6));
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
+        assert!(output.ends_with("\x1b[7;65535H"));
 
         let config = Config {
-            truecolor: false,
+            x: u16::MAX,
+            ..config
+        };
+        let mut buf = Ansi::new(vec![]);
+        print_to_writecolor(&mut buf, &img, &config).unwrap();
+        assert!(std::str::from_utf8(buf.get_ref())
+            .unwrap()
+            .starts_with("\x1b[4;1H\x1b[65535C"));
+
+        // anchoring and aligning never wrap around either
+        let config = Config {
+            x: u16::MAX,
+            y: i16::MIN,
             absolute_offset: false,
+            anchor: crate::Anchor::BottomRight,
             ..Default::default()
         };
-        let (w, h) = print_to_writecolor(&mut buf, &img, &config).unwrap();
-        assert_eq!((w, h), (4, 2));
-
-        assert_eq!(
-            std::str::from_utf8(buf.get_ref()).unwrap(),
-            "\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\x1b[38;5;241m\x1b[48;5;247m▄\x1b[0m\x1b[38;5;247m\x1b[48;5;241m▄\x1b[0m\x1b[38;5;241m\x1b[48;5;247m▄\x1b[0m\r\n\x1b[0m\x1b[38;5;241m▀\x1b[0m\x1b[38;5;247m▀\x1b[0m\x1b[38;5;241m▀\x1b[0m\x1b[38;5;247m▀\x1b[0m\n"
-        );
+        let anchored = config.anchored((u32::MAX, u32::MAX));
+        assert_eq!((anchored.x, anchored.y), (0, i16::MIN));
+        let aligned = Config {
+            align: Some(crate::Anchor::BottomRight),
+            ..config
+        };
+        let aligned = aligned.anchored((6, 2));
+        assert_eq!((aligned.x, aligned.y), (u16::MAX, 0));
     }
 
     #[test]
-    fn test_write_colored_char_only_fg() {
+    fn test_use_current_position() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4));
+        let config = Config {
+            y: 5,
+            anchor: crate::Anchor::Center,
+            use_current_position: true,
+            truecolor: TrueColorMode::Never,
+            ..Default::default()
+        };
         let mut buf = Ansi::new(vec![]);
-        let mut c = ColorSpec::new();
+        print_to_writecolor(&mut buf, &img, &config).unwrap();
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
+        // the first row starts right away, without moving the cursor
+        assert!(output.starts_with("\x1b[0m\x1b[38;5;"));
+    }
 
-        c.set_fg(Some(Color::Rgb(10, 20, 30)));
+    #[test]
+    fn test_force_ansi() {
+        let config = Config {
+            force_ansi: true,
+            truecolor: TrueColorMode::Force,
+            ..Default::default()
+        };
+        assert_eq!(color_choice(&config), ColorChoice::AlwaysAnsi);
+        assert_eq!(color_choice(&Config::default()), ColorChoice::Always);
 
-        write_colored_character(&mut buf, &c, false).unwrap();
-        assert_eq!(
-            std::str::from_utf8(buf.get_ref()).unwrap(),
-            "\x1b[0m\x1b[38;2;10;20;30m▄"
-        );
+        // a stream with the color choice writes escape sequences on every platform
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([1, 2, 3, 255])));
+        let mut buf = termcolor::BufferWriter::stdout(color_choice(&config)).buffer();
+        print_to_writecolor(&mut buf, &img, &config).unwrap();
+        let output = std::str::from_utf8(buf.as_slice()).unwrap();
+        assert!(output.contains("\x1b[38;2;1;2;3m"));
+        assert!(output.contains("\x1b[48;2;1;2;3m"));
     }
 
     #[test]
-    fn test_write_colored_char_only_bg() {
-        let mut buf = Ansi::new(vec![]);
-        let mut c = ColorSpec::new();
-
-        c.set_bg(Some(Color::Rgb(50, 60, 70)));
+    fn test_right_to_left() {
+        let colors = [(255, 0, 0), (0, 255, 0), (0, 0, 255), (255, 255, 0)];
+        let img = RgbaImage::from_fn(4, 2, |x, _| {
+            let (r, g, b) = colors[x as usize];
+            Rgba([r, g, b, 255])
+        });
+        let img = DynamicImage::ImageRgba8(img);
+        let config = Config {
+            right_to_left: true,
+            truecolor: TrueColorMode::Force,
+            width: Some(4),
+            ..Default::default()
+        };
 
-        write_colored_character(&mut buf, &c, false).unwrap();
+        let mut buf = Ansi::new(vec![]);
         assert_eq!(
-            std::str::from_utf8(buf.get_ref()).unwrap(),
-            "\x1b[0m\x1b[38;2;50;60;70m▀"
+            print_to_writecolor(&mut buf, &img, &config).unwrap(),
+            (4, 1)
         );
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
+        // the image touches the right edge of the 80 columns wide terminal
+        assert!(output.starts_with("\x1b[1;1H\x1b[76C"));
+        let positions: Vec<usize> = colors
+            .iter()
+            .map(|(r, g, b)| output.find(&format!("38;2;{};{};{}m", r, g, b)).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] > pair[1]));
+
+        // mirrored, the right anchor is the left edge, 10 columns from the right edge
+        let config = Config {
+            x: 10,
+            anchor: crate::Anchor::TopRight,
+            ..config
+        };
+        assert_eq!(config.anchored((4, 1)).x, 70);
+    }
+
+    #[test]
+    fn test_from_cursor_row() {
+        let config = Config {
+            y: 2,
+            ..Default::default()
+        };
+        assert_eq!(from_cursor_row(&config, 10).y, 12);
+
+        let relative = Config {
+            absolute_offset: false,
+            ..config.clone()
+        };
+        assert_eq!(from_cursor_row(&relative, 10).y, 2);
+        let current = Config {
+            use_current_position: true,
+            ..config
+        };
+        assert_eq!(from_cursor_row(&current, 10).y, 2);
     }
 
     #[test]
-    fn test_write_colored_char_fg_and_bg() {
+    fn test_pager_safe() {
+        // transparent on the left, opaque on the right
+        let img = RgbaImage::from_fn(6, 4, |x, _| match x < 3 {
+            true => Rgba([0, 0, 0, 0]),
+            false => Rgba([200, 30, 30, 255]),
+        });
+        let config = Config {
+            x: 3,
+            y: 2,
+            transparent: true,
+            keep_cursor_column: true,
+            pager_safe: true,
+            truecolor: TrueColorMode::Never,
+            ..Default::default()
+        };
         let mut buf = Ansi::new(vec![]);
-        let mut c = ColorSpec::new();
+        print_to_writecolor(&mut buf, &DynamicImage::ImageRgba8(img), &config).unwrap();
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
 
-        c.set_fg(Some(Color::Rgb(10, 20, 30)));
-        c.set_bg(Some(Color::Rgb(15, 25, 35)));
+        // only SGR sequences are left
+        for sequence in output.split("\x1b[").skip(1) {
+            let end = sequence.find(|c: char| ('@'..='~').contains(&c)).unwrap();
+            assert_eq!(&sequence[end..=end], "m", "{:?}", output);
+        }
+        // the offset and the transparent cells are spaces
+        let row = "   \x1b[0m \x1b[0m \x1b[0m \x1b[0m\x1b[38;5;160m";
+        assert!(output.starts_with(&format!("\n\n{}", row)), "{:?}", output);
+        assert_eq!(output.matches(&format!("\r\n{}", row)).count(), 1);
+    }
 
-        write_colored_character(&mut buf, &c, false).unwrap();
+    #[test]
+    fn test_sub_cell_dither() {
+        let config = Config {
+            sub_cell_dither: true,
+            truecolor: TrueColorMode::Force,
+            width: Some(4),
+            ..Default::default()
+        };
+        // the pixel pairs of every column, from top to bottom
+        let pairs = [(255, 0), (0, 255), (255, 255), (0, 0)];
+        let img = RgbaImage::from_fn(4, 2, |x, y| {
+            let (top, bottom) = pairs[x as usize];
+            let v = if y == 0 { top } else { bottom };
+            Rgba([v, v, v, 255])
+        });
+        let cells = render_cells(&DynamicImage::ImageRgba8(img), &config).unwrap();
+        let glyphs: Vec<&str> = cells[0].iter().map(|cell| cell.glyph).collect();
         assert_eq!(
-            std::str::from_utf8(buf.get_ref()).unwrap(),
-            "\x1b[0m\x1b[38;2;10;20;30m\x1b[48;2;15;25;35m▄"
+            glyphs,
+            vec![UPPER_HALF_BLOCK, LOWER_HALF_BLOCK, FULL_BLOCK, " "]
         );
+        for cell in &cells[0] {
+            assert_eq!((cell.fg, cell.bg), (Some((255, 255, 255)), Some((0, 0, 0))));
+        }
+
+        // a flat mid gray turns into a mix of light and dark halves
+        let gray = RgbaImage::from_pixel(8, 8, Rgba([128, 128, 128, 255]));
+        let config = Config {
+            width: Some(8),
+            ..config
+        };
+        let cells = render_cells(&DynamicImage::ImageRgba8(gray), &config).unwrap();
+        let glyphs: Vec<&str> = cells.iter().flatten().map(|cell| cell.glyph).collect();
+        assert!(glyphs.iter().any(|&glyph| glyph != glyphs[0]));
+        assert_eq!(dithered_glyph(true, false), UPPER_HALF_BLOCK);
     }
 
     #[test]
-    fn test_write_colored_char_no_color() {
+    fn test_crlf_line_ending() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(3, 4, Rgba([9, 9, 9, 255])));
+        let config = Config {
+            absolute_offset: false,
+            y: 2,
+            line_ending: crate::LineEnding::CrLf,
+            truecolor: TrueColorMode::Never,
+            ..Default::default()
+        };
         let mut buf = Ansi::new(vec![]);
-        let c = ColorSpec::new();
+        print_to_writecolor(&mut buf, &img, &config).unwrap();
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
 
-        write_colored_character(&mut buf, &c, false).unwrap();
-        // expect to print nothing, just move cursor to the right
-        assert_eq!(std::str::from_utf8(buf.get_ref()).unwrap(), "\x1b[1C");
+        assert!(output.starts_with("\r\n\r\n\x1b[0m"));
+        assert!(output.ends_with("\x1b[0m\r\n"));
+        assert_eq!(output.matches('\n').count(), 4);
+        assert_eq!(output.matches("\r\n").count(), 4);
     }
 
     #[test]
-    fn test_write_colored_char_last_row_bg() {
-        let mut buf = Ansi::new(vec![]);
-        let mut c = ColorSpec::new();
+    fn test_solid_background_fills_transparent_halves() {
+        let config = Config {
+            background: Background::Solid((1, 2, 3)),
+            ..Default::default()
+        };
+        let opaque = Rgba([200, 100, 50, 255]);
+        let clear = Rgba([0, 0, 0, 0]);
+        let cases = [
+            (opaque, opaque, (200, 100, 50), (200, 100, 50)),
+            (opaque, clear, (200, 100, 50), (1, 2, 3)),
+            (clear, opaque, (1, 2, 3), (200, 100, 50)),
+            (clear, clear, (1, 2, 3), (1, 2, 3)),
+        ];
+        for (top, bottom, top_rgb, bottom_rgb) in cases {
+            let img = RgbaImage::from_fn(1, 2, |_, y| if y == 0 { top } else { bottom });
+            let cells = image_to_cells(&img, &config);
+            assert_eq!(
+                cells,
+                vec![vec![Cell {
+                    fg: Some(bottom_rgb),
+                    bg: Some(top_rgb),
+                    glyph: LOWER_HALF_BLOCK,
+                }]]
+            );
+        }
+    }
+
+    #[test]
+    fn test_color_temperature() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([128, 128, 128, 255])));
+        let color = |warmth| {
+            let config = Config {
+                width: Some(2),
+                color_temperature: warmth,
+                ..Default::default()
+            };
+            prepare(&img, &config).unwrap().to_rgba8().get_pixel(0, 0).0
+        };
 
-        c.set_bg(Some(Color::Rgb(10, 20, 30)));
+        assert_eq!(color(None), [128, 128, 128, 255]);
+        assert_eq!(color(Some(0.0)), [128, 128, 128, 255]);
+        let [r, g, b, _] = color(Some(0.5));
+        assert!(r > 128 && b < 128);
+        assert_eq!(g, 128);
+        let [r, _, b, _] = color(Some(-1.0));
+        assert!(r < 128 && b > 128);
 
-        write_colored_character(&mut buf, &c, true).unwrap();
-        assert_eq!(
-            std::str::from_utf8(buf.get_ref()).unwrap(),
-            "\x1b[0m\x1b[38;2;10;20;30m▀"
-        );
+        let config = Config {
+            color_temperature: Some(1.5),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
     }
 
     #[test]
-    fn test_write_colored_char_last_row_no_bg() {
+    fn test_cell_mask() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(3, 6, Rgba([9, 9, 9, 255])));
+        let diagonal = |n: usize| -> Vec<Vec<bool>> {
+            (0..n)
+                .map(|row| (0..n).map(|col| row == col).collect())
+                .collect()
+        };
+        let config = Config {
+            absolute_offset: false,
+            truecolor: TrueColorMode::Force,
+            cell_mask: Some(diagonal(3)),
+            ..Default::default()
+        };
         let mut buf = Ansi::new(vec![]);
-        let mut c = ColorSpec::new();
+        assert_eq!(
+            print_to_writecolor(&mut buf, &img, &config).unwrap(),
+            (3, 3)
+        );
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
 
-        // test with no color
-        write_colored_character(&mut buf, &c, true).unwrap();
-        assert_eq!(std::str::from_utf8(buf.get_ref()).unwrap(), "\x1b[1C");
+        // every row draws its one allowed cell and moves over the others
+        let rows: Vec<&str> = output.split('\n').take(3).collect();
+        for (index, row) in rows.iter().enumerate() {
+            assert_eq!(row.matches(LOWER_HALF_BLOCK).count(), 1, "{:?}", row);
+            assert_eq!(row.matches("\x1b[1C").count(), 2, "{:?}", row);
+            let before = &row[..row.find(LOWER_HALF_BLOCK).unwrap()];
+            assert_eq!(before.matches("\x1b[1C").count(), index, "{:?}", row);
+        }
 
-        c.set_fg(Some(Color::Rgb(10, 20, 30)));
+        let config = Config {
+            cell_mask: Some(diagonal(2)),
+            ..config.clone()
+        };
+        assert!(print_to_writecolor(&mut Ansi::new(vec![]), &img, &config).is_err());
 
-        // test with fg (unusual case)
+        // the mask is mirrored along with the image
+        let img = RgbaImage::from_fn(3, 2, |x, _| match x {
+            0 => Rgba([255, 0, 0, 255]),
+            _ => Rgba([0, 0, 255, 255]),
+        });
+        let config = Config {
+            right_to_left: true,
+            cell_mask: Some(vec![vec![true, false, false]]),
+            ..config
+        };
         let mut buf = Ansi::new(vec![]);
-        write_colored_character(&mut buf, &c, true).unwrap();
-        assert_eq!(std::str::from_utf8(buf.get_ref()).unwrap(), "\x1b[1C");
+        print_to_writecolor(&mut buf, &DynamicImage::ImageRgba8(img), &config).unwrap();
+        let output = std::str::from_utf8(buf.get_ref()).unwrap();
+        assert!(output.contains("\x1b[38;2;255;0;0m"));
+        assert!(!output.contains("\x1b[38;2;0;0;255m"));
+        assert!(output.ends_with(&format!("{}\x1b[0m\n", LOWER_HALF_BLOCK)));
+    }
+
+    #[test]
+    fn test_mosaic_gap() {
+        let mut img = RgbaImage::from_pixel(7, 10, Rgba([50, 60, 70, 255]));
+        img.put_pixel(6, 4, Rgba([0, 0, 0, 0]));
+        img.put_pixel(6, 5, Rgba([0, 0, 0, 0]));
+        img.put_pixel(2, 1, Rgba([0, 0, 0, 0]));
+        let separator = (200, 200, 0);
+        let config = Config {
+            width: Some(7),
+            transparent: true,
+            mosaic_gap: Some((2, separator)),
+            ..Default::default()
+        };
+        let cells = render_cells(&DynamicImage::ImageRgba8(img), &config).unwrap();
+        assert_eq!((cells[0].len(), cells.len()), (7, 5));
+
+        for (row, cells_row) in cells.iter().enumerate() {
+            for (col, cell) in cells_row.iter().enumerate() {
+                let gap = row % 3 == 2 || col % 3 == 2;
+                if (col, row) == (6, 2) {
+                    assert!(cell.is_transparent());
+                } else if (col, row) == (2, 0) {
+                    // the transparent bottom half is kept
+                    assert_eq!(cell.fg, Some(separator));
+                    assert_eq!((cell.bg, cell.glyph), (None, UPPER_HALF_BLOCK));
+                } else if gap {
+                    assert_eq!((cell.fg, cell.glyph), (Some(separator), FULL_BLOCK));
+                } else {
+                    assert_eq!(cell.bg, Some((50, 60, 70)));
+                }
+            }
+        }
+
+        let config = Config {
+            mosaic_gap: Some((0, separator)),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_grid_mosaic_gap() {
+        let mut grid = RgbaImage::from_pixel(3, 2, Rgba([50, 60, 70, 255]));
+        grid.put_pixel(2, 0, Rgba([0, 0, 0, 0]));
+        grid.put_pixel(2, 1, Rgba([0, 0, 0, 0]));
+        let separator = (200, 200, 0);
+        let config = Config {
+            transparent: true,
+            ..Default::default()
+        };
+        let cells = grid_to_cells(&grid, (2, 1), &config);
+        let cells = insert_mosaic_gaps(cells, (2, 1), (1, separator));
+
+        // every pixel is kept as a tile of 2x1 cells, with separators in between
+        assert_eq!((cells[0].len(), cells.len()), (8, 3));
+        for (row, cells_row) in cells.iter().enumerate() {
+            for (col, cell) in cells_row.iter().enumerate() {
+                if col > 5 {
+                    assert!(cell.is_transparent(), "{} {}", col, row);
+                } else if row == 1 || col % 3 == 2 {
+                    assert_eq!(cell, &mosaic_separator(separator), "{} {}", col, row);
+                } else {
+                    assert_eq!(cell.fg, Some((50, 60, 70)), "{} {}", col, row);
+                }
+            }
+        }
     }
 }
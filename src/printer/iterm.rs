@@ -1,5 +1,6 @@
+use crate::decode::{decode, is_jxl};
 use crate::error::ViuResult;
-use crate::printer::{adjust_offset, find_best_fit, Printer};
+use crate::printer::{adjust_offset, newline, protocol_fit, Printer, PrinterCapabilities, Probed};
 use crate::Config;
 use base64::{engine::general_purpose, Engine};
 use image::{DynamicImage, GenericImageView, ImageEncoder};
@@ -13,12 +14,12 @@ use std::{
 pub struct iTermPrinter;
 
 lazy_static! {
-    static ref ITERM_SUPPORT: bool = check_iterm_support();
+    pub(crate) static ref ITERM_SUPPORT: Probed<bool> = Probed::new();
 }
 
 /// Returns the terminal's support for the iTerm graphics protocol.
 pub fn is_iterm_supported() -> bool {
-    *ITERM_SUPPORT
+    ITERM_SUPPORT.get(check_iterm_support)
 }
 
 impl Printer for iTermPrinter {
@@ -48,16 +49,28 @@ impl Printer for iTermPrinter {
         filename: P,
         config: &Config,
     ) -> ViuResult<(u32, u32)> {
-        let file = std::fs::File::open(filename)?;
+        let file = std::fs::File::open(&filename)?;
 
         // load the file content
         let mut buf_reader = BufReader::new(file);
         let mut file_content = Vec::new();
         buf_reader.read_to_end(&mut file_content)?;
 
-        let img = image::load_from_memory(&file_content[..])?;
+        let img = decode(&file_content, image::ImageFormat::from_path(&filename).ok())?;
+        // iTerm cannot display JPEG XL itself, so it gets the decoded image instead
+        if is_jxl(&file_content) {
+            return self.print(stdout, &img, config);
+        }
         print_buffer(stdout, &img, &file_content[..], config)
     }
+    fn capabilities(&self) -> PrinterCapabilities {
+        PrinterCapabilities {
+            truecolor: true,
+            transparency: true,
+            pixels_per_cell: None,
+            animation: true,
+        }
+    }
 }
 
 // This function requires both a DynamicImage, which is used to calculate dimensions,
@@ -68,11 +81,15 @@ fn print_buffer(
     img_content: &[u8],
     config: &Config,
 ) -> ViuResult<(u32, u32)> {
-    adjust_offset(stdout, config)?;
+    let (w, h) = protocol_fit(img, config)?;
+    let config = &*config.anchored((w, h));
 
-    let (w, h) = find_best_fit(img, config.width, config.height);
+    adjust_offset(stdout, config)?;
+    if config.clear_before_draw {
+        erase_cells(stdout, (w, h))?;
+    }
 
-    writeln!(
+    write!(
         stdout,
         "\x1b]1337;File=inline=1;preserveAspectRatio=1;size={};width={};height={}:{}\x07",
         img_content.len(),
@@ -80,24 +97,40 @@ fn print_buffer(
         h,
         general_purpose::STANDARD.encode(img_content)
     )?;
+    newline(stdout, config)?;
     stdout.flush()?;
 
     Ok((w, h))
 }
 
-// Check if the iTerm protocol can be used
-fn check_iterm_support() -> bool {
-    if let Ok(term) = std::env::var("TERM_PROGRAM") {
-        if term.contains("iTerm") || term.contains("WezTerm") || term.contains("mintty") {
-            return true;
+// Erase the cells of an image of (w, h) cells starting at the cursor, leaving the cursor where
+// it is.
+fn erase_cells(stdout: &mut impl Write, (w, h): (u32, u32)) -> ViuResult {
+    write!(stdout, "\x1b7")?;
+    for row in 0..h {
+        if row > 0 {
+            write!(stdout, "\x1b[1B")?;
         }
+        write!(stdout, "\x1b[{}X", w)?;
     }
-    if let Ok(lc_term) = std::env::var("LC_TERMINAL") {
-        if lc_term.contains("iTerm") || lc_term.contains("WezTerm") || lc_term.contains("mintty") {
-            return true;
-        }
-    }
-    false
+    write!(stdout, "\x1b8")?;
+    Ok(())
+}
+
+// Check if the iTerm protocol can be used
+fn check_iterm_support() -> bool {
+    iterm_support(
+        std::env::var("TERM_PROGRAM").ok().as_deref(),
+        std::env::var("LC_TERMINAL").ok().as_deref(),
+    )
+}
+
+// Terminals known to support the iTerm protocol, by TERM_PROGRAM or LC_TERMINAL.
+fn iterm_support(term_program: Option<&str>, lc_terminal: Option<&str>) -> bool {
+    [term_program, lc_terminal]
+        .into_iter()
+        .flatten()
+        .any(|term| term.contains("iTerm") || term.contains("WezTerm") || term.contains("mintty"))
 }
 
 #[cfg(test)]
@@ -120,4 +153,29 @@ mod tests {
         assert_eq!(iTermPrinter.print(&mut vec, &img, &config).unwrap(), (2, 2));
         assert_eq!(std::str::from_utf8(&vec).unwrap(), "\x1b[4;5H\x1b]1337;File=inline=1;preserveAspectRatio=1;size=95;width=2;height=2:iVBORw0KGgoAAAANSUhEUgAAAAIAAAADCAYAAAC56t6BAAAAJklEQVR4AQEbAOT/AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAACBAYIAEMAFdTlTsEAAAAASUVORK5CYII=\x07\n");
     }
+
+    #[test]
+    fn test_clear_before_draw() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(3, 4));
+        let config = Config {
+            x: 4,
+            y: 3,
+            clear_before_draw: true,
+            ..Default::default()
+        };
+        let mut vec = Vec::new();
+
+        assert_eq!(iTermPrinter.print(&mut vec, &img, &config).unwrap(), (3, 2));
+        let result = std::str::from_utf8(&vec).unwrap();
+        assert!(result.starts_with("\x1b[4;5H\x1b7\x1b[3X\x1b[1B\x1b[3X\x1b8\x1b]1337;File="));
+    }
+
+    #[test]
+    fn test_iterm_support() {
+        assert!(iterm_support(Some("iTerm.app"), None));
+        assert!(iterm_support(Some("WezTerm"), None));
+        assert!(iterm_support(None, Some("iTerm2")));
+        assert!(!iterm_support(Some("Apple_Terminal"), None));
+        assert!(!iterm_support(None, None));
+    }
 }
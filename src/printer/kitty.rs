@@ -1,22 +1,36 @@
 use crate::error::{ViuError, ViuResult};
-use crate::printer::{adjust_offset, find_best_fit, Printer};
+use crate::printer::{
+    adjust_offset, cell_size_pixels, newline, protocol_fit, resize_exact, Printer,
+    PrinterCapabilities, Probed,
+};
+use crate::query::{query_terminal, query_timeout};
 use crate::Config;
 use base64::{engine::general_purpose, Engine};
-use console::{Key, Term};
+use image::DynamicImage;
 use lazy_static::lazy_static;
+use std::borrow::Cow;
+use std::io::Error;
 use std::io::Write;
-use std::io::{Error, ErrorKind};
 
 pub struct KittyPrinter;
 
 const TEMP_FILE_PREFIX: &str = ".tty-graphics-protocol.viuer.";
+// Size of the chunks of data sent through escape codes, as recommended by the protocol
+const CHUNK_SIZE: usize = 4096;
+
 lazy_static! {
-    static ref KITTY_SUPPORT: KittySupport = check_kitty_support();
+    pub(crate) static ref KITTY_SUPPORT: Probed<(KittySupport, KittyQuirks)> = Probed::new();
 }
 
 /// Returns the terminal's support for the Kitty graphics protocol.
 pub fn get_kitty_support() -> KittySupport {
-    *KITTY_SUPPORT
+    KITTY_SUPPORT.get(check_kitty_support).0
+}
+
+/// Returns how the terminal's implementation of the Kitty graphics protocol differs from Kitty's
+/// own, which the printer takes into account.
+pub fn get_kitty_quirks() -> KittyQuirks {
+    KITTY_SUPPORT.get(check_kitty_support).1
 }
 
 impl Printer for KittyPrinter {
@@ -30,11 +44,11 @@ impl Printer for KittyPrinter {
             KittySupport::None => Err(ViuError::KittyNotSupported),
             KittySupport::Local => {
                 // print from file
-                print_local(stdout, img, config)
+                print_local(stdout, img, config, cell_size_pixels())
             }
             KittySupport::Remote => {
                 // print through escape codes
-                print_remote(stdout, img, config)
+                print_remote(stdout, img, config, cell_size_pixels())
             }
         }
     }
@@ -42,6 +56,14 @@ impl Printer for KittyPrinter {
     // TODO: guess_format() here in order to treat PNGs specially (f=100).
     // Also, maybe get channel count and use f=24 or f=32 accordingly.
     // fn print_from_file(&self, filename: &str, config: &Config) -> ViuResult<(u32, u32)> {}
+    fn capabilities(&self) -> PrinterCapabilities {
+        PrinterCapabilities {
+            truecolor: true,
+            transparency: true,
+            pixels_per_cell: None,
+            animation: false,
+        }
+    }
 }
 
 #[derive(PartialEq, Eq, Copy, Clone)]
@@ -55,82 +77,130 @@ pub enum KittySupport {
     Remote,
 }
 
-// Check if Kitty protocol can be used
-fn check_kitty_support() -> KittySupport {
-    if let Ok(term) = std::env::var("TERM") {
-        if term.contains("kitty") {
-            if has_local_support().is_ok() {
-                return KittySupport::Local;
-            } else {
-                return KittySupport::Remote;
-            }
-        }
+/// Differences between terminals in their implementation of the Kitty graphics protocol.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct KittyQuirks {
+    /// Images can be shared through temporary files when running locally.
+    pub temp_files: bool,
+}
+
+impl Default for KittyQuirks {
+    /// Kitty's own implementation.
+    fn default() -> Self {
+        Self { temp_files: true }
+    }
+}
+
+// Check if Kitty protocol can be used, and the quirks of the terminal
+fn check_kitty_support() -> (KittySupport, KittyQuirks) {
+    let term = std::env::var("TERM").ok();
+    let term_program = std::env::var("TERM_PROGRAM").ok();
+    let konsole = std::env::var_os("KONSOLE_VERSION").is_some();
+    let quirks = detect_quirks(term_program.as_deref(), konsole);
+    if !implements_protocol(term.as_deref(), term_program.as_deref(), konsole) {
+        return (KittySupport::None, quirks);
+    }
+    if quirks.temp_files && has_local_support().unwrap_or(false) {
+        (KittySupport::Local, quirks)
+    } else {
+        (KittySupport::Remote, quirks)
+    }
+}
+
+// Whether the terminal implements the Kitty graphics protocol: Kitty itself sets a `TERM`
+// containing "kitty", while the others keep a generic one like xterm-256color.
+fn implements_protocol(term: Option<&str>, term_program: Option<&str>, konsole: bool) -> bool {
+    term.is_some_and(|term| term.contains("kitty"))
+        || matches!(term_program, Some("WezTerm") | Some("ghostty"))
+        || konsole
+}
+
+// Terminals other than Kitty that implement the protocol, told apart by `TERM_PROGRAM` or by
+// Konsole setting `KONSOLE_VERSION`.
+fn detect_quirks(term_program: Option<&str>, konsole: bool) -> KittyQuirks {
+    if konsole {
+        // only data sent through escape codes is displayed
+        return KittyQuirks { temp_files: false };
+    }
+    match term_program {
+        // temp files are not deleted after reading them
+        Some("WezTerm") => KittyQuirks { temp_files: false },
+        _ => KittyQuirks::default(),
     }
-    KittySupport::None
 }
 
 // Query the terminal whether it can display an image from a file
-fn has_local_support() -> ViuResult {
+fn has_local_support() -> ViuResult<bool> {
     // create a temp file that will hold a 1x1 image
     let x = image::RgbaImage::new(1, 1);
     let raw_img = x.as_raw();
     let path = store_in_tmp_file(raw_img)?;
 
-    // send the query
-    print!(
-        // t=t tells Kitty it's reading from a temp file and will delete if afterwards
+    // t=t tells Kitty it's reading from a temp file and will delete if afterwards
+    let request = format!(
         "\x1b_Gi=31,s=1,v=1,a=q,t=t;{}\x1b\\",
-        general_purpose::STANDARD.encode(path.to_str().ok_or_else(|| std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Could not convert path to &str"
-        ))?)
+        general_purpose::STANDARD.encode(
+            path.to_str()
+                .ok_or_else(|| std::io::Error::other("Could not convert path to &str"))?
+        )
     );
-    std::io::stderr().flush()?;
-
-    // collect Kitty's response after the query
-    let term = Term::stderr();
-    let mut response = Vec::new();
-
-    // TODO: could use a queue of length 3
-    while let Ok(key) = term.read_key() {
-        // The response will end with Esc('x1b'), followed by Backslash('\').
-        // Also, break if the Unknown key is found, which is returned when we're not in a tty
-        let should_break = key == Key::UnknownEscSeq(vec!['\\']) || key == Key::Unknown;
-        response.push(key);
-        if should_break {
-            break;
-        }
-    }
-
-    // Kitty response should end with these 3 Keys if it was successful
-    let expected = [
-        Key::Char('O'),
-        Key::Char('K'),
-        Key::UnknownEscSeq(vec!['\\']),
-    ];
 
-    if response.len() >= expected.len() && response[response.len() - 3..] == expected {
-        return Ok(());
-    }
+    // Kitty's response ends with OK if it was successful
+    let response = query_terminal(&request, query_timeout());
+    Ok(response.is_some_and(|response| response.ends_with("OK\x1b\\")))
+}
 
-    Err(ViuError::KittyResponse(response))
+// The cells the image spans and the image to transmit. Given the size of a cell in pixels, the
+// image is resampled to the device pixels of the cells it covers, keeping its aspect ratio
+// unless both dimensions are set, so that the terminal does not scale it. Otherwise, cells are
+// assumed to be twice as tall as they are wide and the terminal scales the image itself.
+fn fit_to_cells<'a>(
+    img: &'a DynamicImage,
+    config: &Config,
+    cell_size: Option<(f32, f32)>,
+) -> ViuResult<((u32, u32), Cow<'a, DynamicImage>)> {
+    let (w, h) = protocol_fit(img, config)?;
+    let Some((cell_w, cell_h)) = cell_size else {
+        return Ok(((w, h), Cow::Borrowed(img)));
+    };
+
+    let (box_w, box_h) = (w as f32 * cell_w, h as f32 * cell_h);
+    let exact = (config.width.is_some() && config.height.is_some())
+        || (config.cols.is_some() && config.rows.is_some())
+        || config.physical_size.is_some();
+    let (px_w, px_h) = if exact {
+        (box_w, box_h)
+    } else {
+        let (img_w, img_h) = (img.width() as f32, img.height() as f32);
+        let scale = f32::min(box_w / img_w, box_h / img_h);
+        (img_w * scale, img_h * scale)
+    };
+    let (px_w, px_h) = (px_w.round().max(1.0), px_h.round().max(1.0));
+
+    let cols = ((px_w / cell_w).ceil() as u32).clamp(1, w);
+    let rows = ((px_h / cell_h).ceil() as u32).clamp(1, h);
+    let resized = resize_exact(img, (px_w as u32, px_h as u32), config.filter);
+    Ok(((cols, rows), Cow::Owned(resized)))
 }
 
 // Print with kitty graphics protocol through a temp file
 // TODO: try with kitty's supported compression
 fn print_local(
     stdout: &mut impl Write,
-    img: &image::DynamicImage,
+    img: &DynamicImage,
     config: &Config,
+    cell_size: Option<(f32, f32)>,
 ) -> ViuResult<(u32, u32)> {
+    // get the desired width and height
+    let ((w, h), img) = fit_to_cells(img, config, cell_size)?;
     let rgba = img.to_rgba8();
     let raw_img = rgba.as_raw();
     let path = store_in_tmp_file(raw_img)?;
 
-    adjust_offset(stdout, config)?;
+    let config = &*config.anchored((w, h));
 
-    // get the desired width and height
-    let (w, h) = find_best_fit(img, config.width, config.height);
+    adjust_offset(stdout, config)?;
+    clear_placements(stdout, config)?;
 
     write!(
         stdout,
@@ -139,12 +209,12 @@ fn print_local(
         img.height(),
         w,
         h,
-        general_purpose::STANDARD.encode(path.to_str().ok_or_else(|| ViuError::Io(Error::new(
-            ErrorKind::Other,
-            "Could not convert path to &str"
-        )))?)
+        general_purpose::STANDARD.encode(
+            path.to_str()
+                .ok_or_else(|| ViuError::Io(Error::other("Could not convert path to &str")))?
+        )
     )?;
-    writeln!(stdout)?;
+    newline(stdout, config)?;
     stdout.flush()?;
 
     Ok((w, h))
@@ -154,19 +224,23 @@ fn print_local(
 // TODO: try compression
 fn print_remote(
     stdout: &mut impl Write,
-    img: &image::DynamicImage,
+    img: &DynamicImage,
     config: &Config,
+    cell_size: Option<(f32, f32)>,
 ) -> ViuResult<(u32, u32)> {
+    let ((w, h), img) = fit_to_cells(img, config, cell_size)?;
+
     let rgba = img.to_rgba8();
     let raw = rgba.as_raw();
     let encoded = general_purpose::STANDARD.encode(raw);
     let mut iter = encoded.chars().peekable();
 
-    adjust_offset(stdout, config)?;
+    let config = &*config.anchored((w, h));
 
-    let (w, h) = find_best_fit(img, config.width, config.height);
+    adjust_offset(stdout, config)?;
+    clear_placements(stdout, config)?;
 
-    let first_chunk: String = iter.by_ref().take(4096).collect();
+    let first_chunk: String = iter.by_ref().take(CHUNK_SIZE).collect();
 
     // write the first chunk, which describes the image
     write!(
@@ -178,18 +252,32 @@ fn print_remote(
         h,
         first_chunk
     )?;
+    if config.flush_each_chunk {
+        stdout.flush()?;
+    }
 
-    // write all the chunks, each containing 4096 bytes of data
+    // write all the chunks, each containing up to CHUNK_SIZE bytes of data
     while iter.peek().is_some() {
-        let chunk: String = iter.by_ref().take(4096).collect();
+        let chunk: String = iter.by_ref().take(CHUNK_SIZE).collect();
         let m = if iter.peek().is_some() { 1 } else { 0 };
         write!(stdout, "\x1b_Gm={};{}\x1b\\", m, chunk)?;
+        if config.flush_each_chunk {
+            stdout.flush()?;
+        }
     }
-    writeln!(stdout)?;
+    newline(stdout, config)?;
     stdout.flush()?;
     Ok((w, h))
 }
 
+// Delete the images placed at the cursor along with their data, if requested.
+fn clear_placements(stdout: &mut impl Write, config: &Config) -> ViuResult {
+    if config.clear_before_draw {
+        write!(stdout, "\x1b_Ga=d,d=C\x1b\\")?;
+    }
+    Ok(())
+}
+
 // Create a file in temporary dir and write the byte slice to it.
 fn store_in_tmp_file(buf: &[u8]) -> std::result::Result<std::path::PathBuf, ViuError> {
     let (mut tmpfile, path) = tempfile::Builder::new()
@@ -220,7 +308,10 @@ mod tests {
         };
 
         let mut vec = Vec::new();
-        assert_eq!(print_local(&mut vec, &img, &config).unwrap(), (40, 13));
+        assert_eq!(
+            print_local(&mut vec, &img, &config, None).unwrap(),
+            (40, 13)
+        );
         let result = std::str::from_utf8(&vec).unwrap();
 
         assert!(result.starts_with("\x1b[4;5H\x1b_Gf=32,s=40,v=25,c=40,r=13,a=T,t=t;"));
@@ -239,7 +330,7 @@ mod tests {
         };
 
         let mut vec = Vec::new();
-        assert_eq!(print_remote(&mut vec, &img, &config).unwrap(), (1, 1));
+        assert_eq!(print_remote(&mut vec, &img, &config, None).unwrap(), (1, 1));
         let result = std::str::from_utf8(&vec).unwrap();
 
         assert_eq!(
@@ -247,4 +338,127 @@ mod tests {
             "\x1b[6;3H\x1b_Gf=32,a=T,t=d,s=1,v=2,c=1,r=1,m=1;AAAAAAIEBgg=\x1b\\\n"
         );
     }
+
+    // Collects the output and counts how often it was flushed.
+    #[derive(Default)]
+    struct FlushCounter {
+        buf: Vec<u8>,
+        flushes: usize,
+        unflushed: bool,
+    }
+
+    impl Write for FlushCounter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.unflushed = true;
+            self.buf.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.flushes += 1;
+            self.unflushed = false;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_print_remote_flush() {
+        // 6400 bytes are encoded into 3 chunks
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(40, 40));
+        let mut config = Config::default();
+
+        let mut out = FlushCounter::default();
+        print_remote(&mut out, &img, &config, None).unwrap();
+        assert!(!out.unflushed);
+        let flushes = out.flushes;
+
+        config.flush_each_chunk = true;
+        let mut out = FlushCounter::default();
+        print_remote(&mut out, &img, &config, None).unwrap();
+        assert!(!out.unflushed);
+        assert_eq!(out.flushes, flushes + 3);
+    }
+
+    #[test]
+    fn test_detect_quirks() {
+        assert!(!detect_quirks(Some("WezTerm"), false).temp_files);
+        assert!(!detect_quirks(None, true).temp_files);
+        assert_eq!(
+            detect_quirks(Some("ghostty"), false),
+            KittyQuirks::default()
+        );
+        assert_eq!(detect_quirks(None, false), KittyQuirks::default());
+    }
+
+    #[test]
+    fn test_implements_protocol() {
+        assert!(implements_protocol(Some("xterm-kitty"), None, false));
+        assert!(implements_protocol(
+            Some("xterm-256color"),
+            Some("WezTerm"),
+            false
+        ));
+        assert!(implements_protocol(Some("xterm-256color"), None, true));
+        assert!(!implements_protocol(
+            Some("xterm-256color"),
+            Some("iTerm.app"),
+            false
+        ));
+        assert!(!implements_protocol(None, None, false));
+    }
+
+    #[test]
+    fn test_print_remote_cell_size() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(100, 100));
+        let config = Config {
+            absolute_offset: false,
+            width: Some(20),
+            ..Default::default()
+        };
+
+        // the square image spans 10 rows of cells twice as tall as they are wide
+        let mut vec = Vec::new();
+        let cells = print_remote(&mut vec, &img, &config, Some((8.0, 16.0)));
+        assert_eq!(cells.unwrap(), (20, 10));
+        let result = std::str::from_utf8(&vec).unwrap();
+        assert!(result.starts_with("\x1b_Gf=32,a=T,t=d,s=160,v=160,c=20,r=10,m=1;"));
+
+        // but only 8 rows of taller cells, sent at their device pixels
+        let mut vec = Vec::new();
+        let cells = print_remote(&mut vec, &img, &config, Some((8.0, 20.0)));
+        assert_eq!(cells.unwrap(), (20, 8));
+        let result = std::str::from_utf8(&vec).unwrap();
+        assert!(result.starts_with("\x1b_Gf=32,a=T,t=d,s=160,v=160,c=20,r=8,m=1;"));
+
+        // without the cell size, the terminal scales the image
+        let mut vec = Vec::new();
+        print_remote(&mut vec, &img, &config, None).unwrap();
+        let result = std::str::from_utf8(&vec).unwrap();
+        assert!(result.starts_with("\x1b_Gf=32,a=T,t=d,s=100,v=100,c=20,r=10,m=1;"));
+    }
+
+    #[test]
+    fn test_clear_before_draw() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(1, 2));
+        let mut config = Config {
+            x: 2,
+            y: 5,
+            clear_before_draw: true,
+            ..Default::default()
+        };
+
+        let mut vec = Vec::new();
+        print_remote(&mut vec, &img, &config, None).unwrap();
+        let result = std::str::from_utf8(&vec).unwrap();
+        assert!(result.starts_with("\x1b[6;3H\x1b_Ga=d,d=C\x1b\\\x1b_Gf=32,a=T,"));
+
+        let mut vec = Vec::new();
+        print_local(&mut vec, &img, &config, None).unwrap();
+        let result = std::str::from_utf8(&vec).unwrap();
+        assert!(result.starts_with("\x1b[6;3H\x1b_Ga=d,d=C\x1b\\\x1b_Gf=32,s=1,"));
+
+        config.clear_before_draw = false;
+        let mut vec = Vec::new();
+        print_remote(&mut vec, &img, &config, None).unwrap();
+        assert!(!std::str::from_utf8(&vec).unwrap().contains("a=d"));
+    }
 }
@@ -1,16 +1,25 @@
-use crate::config::Config;
+use crate::config::{Config, FilterType, VerticalOverflow};
+use crate::decode::decode_file;
 use crate::error::{ViuError, ViuResult};
-use crate::utils::terminal_size;
+use crate::utils::{terminal_size, terminal_size_pixels};
 use crossterm::cursor::{MoveRight, MoveTo, MoveToPreviousLine};
 use crossterm::execute;
 use image::{DynamicImage, GenericImageView};
+use std::sync::Mutex;
 use std::{io::Write, path::Path};
 
+mod ansi_art;
+pub use ansi_art::print_ansi_art;
+
+mod ascii;
+pub use ascii::{luminance_map, AsciiPrinter, DEFAULT_ASCII_RAMP};
+
 mod block;
-pub use block::BlockPrinter;
+pub use block::{bar_cells, prepare, render_cells, render_layer, BlockPrinter, Cell};
+pub(crate) use block::{standard_stream, use_full_blocks};
 
 mod kitty;
-pub use kitty::{get_kitty_support, KittyPrinter, KittySupport};
+pub use kitty::{get_kitty_quirks, get_kitty_support, KittyPrinter, KittyQuirks, KittySupport};
 
 #[cfg(feature = "sixel")]
 mod sixel;
@@ -21,6 +30,52 @@ mod iterm;
 pub use iterm::iTermPrinter;
 pub use iterm::is_iterm_supported;
 
+/// Forget the cached results of the checks for the graphics protocols, so that the terminal is
+/// probed again the next time they are needed.
+///
+/// Useful in long-running processes, e.g. after switching to another terminal or changing the
+/// environment variables the checks rely on.
+pub fn refresh_terminal_capabilities() {
+    kitty::KITTY_SUPPORT.clear();
+    iterm::ITERM_SUPPORT.clear();
+    crate::utils::DARK_BACKGROUND.clear();
+    #[cfg(feature = "sixel")]
+    self::sixel::SIXEL_SUPPORT.clear();
+}
+
+// Result of probing the terminal, kept until it is cleared.
+pub(crate) struct Probed<T: Copy>(Mutex<Option<T>>);
+
+impl<T: Copy> Probed<T> {
+    pub(crate) fn new() -> Self {
+        Self(Mutex::new(None))
+    }
+
+    // The cached result, probing first if there is none.
+    pub(crate) fn get(&self, probe: impl FnOnce() -> T) -> T {
+        let mut result = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        *result.get_or_insert_with(probe)
+    }
+
+    pub(crate) fn clear(&self) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// What a printer is able to show, independent of the image being printed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrinterCapabilities {
+    /// Colors can be printed in 24-bit, if the terminal supports it.
+    pub truecolor: bool,
+    /// Transparent pixels can show the terminal's background.
+    pub transparency: bool,
+    /// Pixels drawn per terminal cell horizontally and vertically, or None if the image is drawn
+    /// at the terminal's own pixel resolution.
+    pub pixels_per_cell: Option<(u32, u32)>,
+    /// Animated files are played by the terminal itself.
+    pub animation: bool,
+}
+
 pub trait Printer {
     // Print the given image in the terminal while respecting the options in the config struct.
     // Return the dimensions of the printed image in **terminal cells**.
@@ -36,15 +91,16 @@ pub trait Printer {
         filename: P,
         config: &Config,
     ) -> ViuResult<(u32, u32)> {
-        let img = image::io::Reader::open(filename)?
-            .with_guessed_format()?
-            .decode()?;
+        let img = decode_file(filename)?;
         self.print(stdout, &img, config)
     }
+    // Describe what the printer can show.
+    fn capabilities(&self) -> PrinterCapabilities;
 }
 
 #[allow(non_camel_case_types)]
 pub enum PrinterType {
+    Ascii,
     Block,
     Kitty,
     iTerm,
@@ -52,6 +108,20 @@ pub enum PrinterType {
     Sixel,
 }
 
+impl PrinterType {
+    // Name of the printer in the metadata trailer.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            PrinterType::Ascii => "ascii",
+            PrinterType::Block => "block",
+            PrinterType::Kitty => "kitty",
+            PrinterType::iTerm => "iterm",
+            #[cfg(feature = "sixel")]
+            PrinterType::Sixel => "sixel",
+        }
+    }
+}
+
 impl Printer for PrinterType {
     fn print(
         &self,
@@ -60,6 +130,7 @@ impl Printer for PrinterType {
         config: &Config,
     ) -> ViuResult<(u32, u32)> {
         match self {
+            PrinterType::Ascii => AsciiPrinter.print(stdout, img, config),
             PrinterType::Block => BlockPrinter.print(stdout, img, config),
             PrinterType::Kitty => KittyPrinter.print(stdout, img, config),
             PrinterType::iTerm => iTermPrinter.print(stdout, img, config),
@@ -75,6 +146,7 @@ impl Printer for PrinterType {
         config: &Config,
     ) -> ViuResult<(u32, u32)> {
         match self {
+            PrinterType::Ascii => AsciiPrinter.print_from_file(stdout, filename, config),
             PrinterType::Block => BlockPrinter.print_from_file(stdout, filename, config),
             PrinterType::Kitty => KittyPrinter.print_from_file(stdout, filename, config),
             PrinterType::iTerm => iTermPrinter.print_from_file(stdout, filename, config),
@@ -82,20 +154,191 @@ impl Printer for PrinterType {
             PrinterType::Sixel => SixelPrinter.print_from_file(stdout, filename, config),
         }
     }
+
+    fn capabilities(&self) -> PrinterCapabilities {
+        match self {
+            PrinterType::Ascii => AsciiPrinter.capabilities(),
+            PrinterType::Block => BlockPrinter.capabilities(),
+            PrinterType::Kitty => KittyPrinter.capabilities(),
+            PrinterType::iTerm => iTermPrinter.capabilities(),
+            #[cfg(feature = "sixel")]
+            PrinterType::Sixel => SixelPrinter.capabilities(),
+        }
+    }
+}
+
+// Escape sequence describing the printed image, see [Config::emit_metadata]. Terminals ignore
+// unknown OSC 1337 keys.
+pub(crate) fn metadata_trailer(
+    original: Option<(u32, u32)>,
+    rendered: (u32, u32),
+    printer: &str,
+) -> String {
+    let original = original.map_or(String::new(), |(w, h)| format!("original={}x{};", w, h));
+    format!(
+        "\x1b]1337;ViuerMetadata={}rendered={}x{};printer={}\x07",
+        original, rendered.0, rendered.1, printer
+    )
 }
 
 /// Resize a [image::DynamicImage] so that it fits within optional width and height bounds.
 /// If none are provided, terminal size is used instead.
+///
+/// The image gets two rows of pixels per terminal cell, as drawn with half blocks. See
+/// [crate::resize_for_config] for the pixels the printer chosen by a config draws.
 pub fn resize(img: &DynamicImage, width: Option<u32>, height: Option<u32>) -> DynamicImage {
-    let (w, h) = find_best_fit(img, width, height);
+    resize_to_cells(img, find_best_fit(img, width, height), FilterType::Triangle)
+}
+
+// Resize the image to the pixels the printer draws with the config: two rows of pixels per cell
+// for half blocks, one for full blocks and ASCII, and the terminal's own resolution for the
+// graphics protocols. The image is kept as it is if that resolution is unknown. `full_blocks`
+// tells whether the block printer draws full blocks, see use_full_blocks.
+pub(crate) fn resize_for_printer(
+    img: &DynamicImage,
+    config: &Config,
+    printer: &PrinterType,
+    full_blocks: bool,
+) -> ViuResult<DynamicImage> {
+    match printer.capabilities().pixels_per_cell {
+        Some((_, 2)) if !full_blocks => {
+            let cells = fit_for_config(img, config)?;
+            Ok(resize_to_cells(img, cells, config.filter))
+        }
+        Some(_) => Ok(resize_exact(
+            img,
+            fit_for_config(img, config)?,
+            config.filter,
+        )),
+        None => {
+            let (w, h) = protocol_fit(img, config)?;
+            match cell_size_pixels() {
+                Some((cell_w, cell_h)) => {
+                    let w = (w as f32 * cell_w) as u32;
+                    let h = (h as f32 * cell_h) as u32;
+                    Ok(resize_exact(img, (w.max(1), h.max(1)), config.filter))
+                }
+                None => Ok(img.clone()),
+            }
+        }
+    }
+}
 
-    // find_best_fit returns values in terminal cells. Hence, we multiply by two
+// Resize the image so that it is printed in the given number of terminal cells with half blocks.
+fn resize_to_cells(img: &DynamicImage, (w, h): (u32, u32), filter: FilterType) -> DynamicImage {
+    // The dimensions are in terminal cells. Hence, we multiply by two
     // because a 5x10 image can fit in 5x5 cells. However, a 5x9 image will also
     // fit in 5x5 and 1 is deducted in such cases.
-    img.resize_exact(
-        w,
-        2 * h - img.height() % 2,
-        image::imageops::FilterType::Triangle,
+    resize_exact(img, (w, 2 * h - img.height() % 2), filter)
+}
+
+// Resize the image to exactly (w, h) pixels, resolving the filter for the scale.
+pub(crate) fn resize_exact(
+    img: &DynamicImage,
+    (w, h): (u32, u32),
+    filter: FilterType,
+) -> DynamicImage {
+    img.resize_exact(w, h, filter.resolve(img.dimensions(), (w, h)))
+}
+
+// Find the best dimensions in terminal cells for printing the image with the given config.
+pub(crate) fn fit_for_config(img: &DynamicImage, config: &Config) -> ViuResult<(u32, u32)> {
+    let (width, height) = size_bounds(img, config);
+    let fit = fit_within(img, width, height, config.reserve_prompt_row);
+    ensure_not_empty(cap_to_max(raise_to_min(fit, config), config))
+}
+
+// Same as fit_for_config, but for the graphics protocol printers.
+fn protocol_fit(img: &DynamicImage, config: &Config) -> ViuResult<(u32, u32)> {
+    let (width, height) = protocol_bounds(img, config);
+    let fit = fit_within(img, width, height, config.reserve_prompt_row);
+    ensure_not_empty(cap_to_max(raise_to_min(fit, config), config))
+}
+
+// The part of the image shown in a banner and the config printing it in exactly the banner's
+// cells, or None if no banner is configured. The image is cropped around its center to the
+// aspect ratio of the banner.
+pub(crate) fn banner(img: &DynamicImage, config: &Config) -> Option<(DynamicImage, Config)> {
+    let rows = config.banner_rows?;
+    let cols = config
+        .width
+        .or(config.max_width)
+        .unwrap_or_else(|| std::cmp::max(1, terminal_size().0 as u32));
+
+    // a cell is twice as tall as it is wide
+    let (img_width, img_height) = img.dimensions();
+    let scale = f64::max(
+        cols as f64 / img_width as f64,
+        2.0 * rows as f64 / img_height as f64,
+    );
+    let crop_width = ((cols as f64 / scale).round() as u32).clamp(1, img_width);
+    let mut crop_height = ((2.0 * rows as f64 / scale).round() as u32).clamp(1, img_height);
+    // an odd number of pixel rows would leave the last row half empty
+    if crop_height % 2 != 0 {
+        if crop_height < img_height {
+            crop_height += 1;
+        } else if crop_height > 1 {
+            crop_height -= 1;
+        }
+    }
+    let cropped = img.crop_imm(
+        (img_width - crop_width) / 2,
+        (img_height - crop_height) / 2,
+        crop_width,
+        crop_height,
+    );
+
+    let config = Config {
+        banner_rows: None,
+        cols: Some(cols),
+        rows: Some(rows),
+        max_width: None,
+        max_height: None,
+        min_width: None,
+        min_height: None,
+        ..config.clone()
+    };
+    Some((cropped, config))
+}
+
+// Nothing can be printed in zero columns or rows, e.g. when a detached terminal reports a width
+// of 0 or such dimensions are configured.
+fn ensure_not_empty((w, h): (u32, u32)) -> ViuResult<(u32, u32)> {
+    if w == 0 || h == 0 {
+        return Err(ViuError::InvalidConfiguration(format!(
+            "image would be printed in {}x{} terminal cells",
+            w, h
+        )));
+    }
+    Ok((w, h))
+}
+
+// Scale the dimensions down so that they do not exceed the maximum width and height, if any,
+// while preserving their aspect ratio.
+fn cap_to_max((w, h): (u32, u32), config: &Config) -> (u32, u32) {
+    let max_w = config.max_width.unwrap_or(w);
+    let max_h = config.max_height.unwrap_or(h);
+    if w <= max_w && h <= max_h {
+        return (w, h);
+    }
+    // a cell holds two pixels vertically
+    fit_dimensions(w, 2 * h, max_w, max_h)
+}
+
+// Scale the dimensions up so that they reach the minimum width and height, if any, while
+// preserving their aspect ratio.
+fn raise_to_min((w, h): (u32, u32), config: &Config) -> (u32, u32) {
+    if w == 0 || h == 0 {
+        return (w, h);
+    }
+    let scale = |min: Option<u32>, size: u32| min.map_or(0.0, |min| min as f64 / size as f64);
+    let scale = f64::max(scale(config.min_width, w), scale(config.min_height, h));
+    if scale <= 1.0 {
+        return (w, h);
+    }
+    (
+        (w as f64 * scale).round() as u32,
+        (h as f64 * scale).round() as u32,
     )
 }
 
@@ -119,6 +362,17 @@ pub fn resize(img: &DynamicImage, width: Option<u32>, height: Option<u32>) -> Dy
 /// assert_eq!(h, 20);
 //TODO: it might make more sense to change signiture from img to (width, height)
 fn find_best_fit(img: &DynamicImage, width: Option<u32>, height: Option<u32>) -> (u32, u32) {
+    fit_within(img, width, height, true)
+}
+
+// Same as find_best_fit, but the row reserved for the prompt when fitting in the terminal can be
+// reclaimed.
+fn fit_within(
+    img: &DynamicImage,
+    width: Option<u32>,
+    height: Option<u32>,
+    reserve_prompt_row: bool,
+) -> (u32, u32) {
     let (img_width, img_height) = img.dimensions();
 
     // Match user's width and height preferences
@@ -130,7 +384,11 @@ fn find_best_fit(img: &DynamicImage, width: Option<u32>, height: Option<u32>) ->
             // One less row because two reasons:
             // - the prompt after executing the command will take a line
             // - gifs flicker
-            let h = if h == term_h as u32 { h - 1 } else { h };
+            let h = if reserve_prompt_row && h == term_h as u32 {
+                h - 1
+            } else {
+                h
+            };
             (w, h)
         }
         // Either width or height is specified, will fit and preserve aspect ratio.
@@ -142,6 +400,88 @@ fn find_best_fit(img: &DynamicImage, width: Option<u32>, height: Option<u32>) ->
     }
 }
 
+// Width and height bounds in terminal cells. Exact rows and columns take precedence over the
+// width and height, which the image is only fitted into.
+fn size_bounds(img: &DynamicImage, config: &Config) -> (Option<u32>, Option<u32>) {
+    let (img_width, img_height) = img.dimensions();
+    match (config.cols, config.rows) {
+        (None, None) => (config.width, config.height),
+        (Some(cols), None) => {
+            // a cell is twice as tall as it is wide
+            let rows = (img_height as f64 * cols as f64 / img_width as f64 / 2.0).round();
+            (Some(cols), Some(std::cmp::max(1, rows as u32)))
+        }
+        (None, Some(rows)) => {
+            let cols = (img_width as f64 * 2.0 * rows as f64 / img_height as f64).round();
+            (Some(std::cmp::max(1, cols as u32)), Some(rows))
+        }
+        (Some(cols), Some(rows)) => (Some(cols), Some(rows)),
+    }
+}
+
+// Width and height bounds in terminal cells for the graphics protocol printers. A physical size
+// is preferred when the terminal reports its dimensions in pixels.
+fn protocol_bounds(img: &DynamicImage, config: &Config) -> (Option<u32>, Option<u32>) {
+    if let (Some(size), Some(cell_size)) = (config.physical_size, cell_size_pixels()) {
+        let (w, h) = physical_to_cells(size, config.dpi, cell_size);
+        return (Some(w), Some(h));
+    }
+    size_bounds(img, config)
+}
+
+// Column and row of the cursor, counted from 0, asked of the terminal itself so that it works
+// whichever stream the output goes to.
+pub(crate) fn cursor_position() -> ViuResult<(u16, u16)> {
+    crate::query::query_terminal("\x1b[6n", crate::query::query_timeout())
+        .as_deref()
+        .and_then(parse_cursor_position)
+        .ok_or_else(|| {
+            ViuError::Io(std::io::Error::other(
+                "the terminal did not report the cursor position",
+            ))
+        })
+}
+
+// The answer to a cursor position report looks like "ESC [ row ; column R", counted from 1.
+fn parse_cursor_position(answer: &str) -> Option<(u16, u16)> {
+    let (row, column) = answer
+        .strip_prefix("\x1b[")?
+        .strip_suffix('R')?
+        .split_once(';')?;
+    let (row, column): (u16, u16) = (row.parse().ok()?, column.parse().ok()?);
+    Some((column.checked_sub(1)?, row.checked_sub(1)?))
+}
+
+// Size of a terminal cell in pixels, if the terminal reports its dimensions in pixels.
+pub(crate) fn cell_size_pixels() -> Option<(f32, f32)> {
+    let (px_w, px_h) = terminal_size_pixels()?;
+    let (term_w, term_h) = terminal_size();
+    if term_w == 0 || term_h == 0 {
+        return None;
+    }
+    Some((px_w as f32 / term_w as f32, px_h as f32 / term_h as f32))
+}
+
+// Number of columns and rows of full blocks that make up a visually square block, given the
+// size of a cell. Cells are assumed to be twice as tall as wide when their size is unknown.
+fn square_span(cell_size: Option<(f32, f32)>) -> (u32, u32) {
+    let (w, h) = cell_size.unwrap_or((1.0, 2.0));
+    let span = |a: f32, b: f32| std::cmp::max(1, (a / b).round() as u32);
+    (span(h, w), span(w, h))
+}
+
+// Convert a size in millimeters to terminal cells, given the pixel density of the screen in dots
+// per inch and the size of a cell in pixels.
+fn physical_to_cells(size_mm: (f32, f32), dpi: f32, cell_size: (f32, f32)) -> (u32, u32) {
+    let pixels_per_mm = dpi / 25.4;
+    let to_cells =
+        |mm: f32, cell: f32| std::cmp::max(1, (mm * pixels_per_mm / cell).round() as u32);
+    (
+        to_cells(size_mm.0, cell_size.0),
+        to_cells(size_mm.1, cell_size.1),
+    )
+}
+
 /// Given width & height of an image, scale the size so that it can fit within given bounds
 /// while preserving aspect ratio. Will only scale down - if dimensions are smaller than the
 /// bounds, they will be returned unmodified.
@@ -178,13 +518,86 @@ fn fit_dimensions(width: u32, height: u32, bound_width: u32, bound_height: u32)
     }
 }
 
+// Number of terminal rows that can be printed, given that the image takes up `rows` of them.
+// Applies the reveal fraction and the vertical overflow policy from the config.
+fn visible_rows(rows: u32, config: &Config) -> ViuResult<u32> {
+    let rows = (rows as f32 * config.reveal_fraction.clamp(0.0, 1.0)) as u32;
+    let (_, term_h) = terminal_size();
+    let available = if config.absolute_offset {
+        (term_h as i32 - config.y as i32).max(0) as u32
+    } else {
+        term_h as u32
+    };
+
+    if rows <= available {
+        return Ok(rows);
+    }
+    match config.vertical_overflow {
+        VerticalOverflow::Scroll => Ok(rows),
+        VerticalOverflow::Clip => Ok(available),
+        VerticalOverflow::Error => Err(ViuError::InvalidConfiguration(format!(
+            "image is {} rows tall, but only {} rows are available",
+            rows, available
+        ))),
+    }
+}
+
+// Largest 0-based column or row the cursor can be moved to, as the sequences count from 1.
+pub(crate) const MAX_CURSOR: u16 = u16::MAX - 1;
+
+// Move the cursor to a 0-based position, clamped to the addressable ones.
+pub(crate) fn move_to(col: u16, row: u16) -> MoveTo {
+    MoveTo(col.min(MAX_CURSOR), row.min(MAX_CURSOR))
+}
+
+// Start a new line with the line ending of the config.
+pub(crate) fn newline(stdout: &mut impl Write, config: &Config) -> ViuResult {
+    write!(stdout, "{}", config.line_ending.as_str())?;
+    Ok(())
+}
+
+// Move the cursor `n` columns to the right, or write spaces when the output has to be free of
+// cursor movements.
+pub(crate) fn move_right(stdout: &mut impl Write, n: u16, config: &Config) -> ViuResult {
+    // Some terminals interpret 0 as 1, see MoveRight documentation
+    if n == 0 {
+        return Ok(());
+    }
+    if config.pager_safe {
+        write!(stdout, "{:1$}", "", n as usize)?;
+    } else {
+        execute!(stdout, MoveRight(n))?;
+    }
+    Ok(())
+}
+
 // Move the cursor to a location from where it should start printing. Calculations are based on
 // offsets from the config.
 fn adjust_offset(stdout: &mut impl Write, config: &Config) -> ViuResult {
+    adjust_offset_with_x(stdout, config, config.x)
+}
+
+// Same as adjust_offset, but only moves vertically. Used by printers that handle the x offset
+// on every line themselves.
+fn adjust_vertical_offset(stdout: &mut impl Write, config: &Config) -> ViuResult {
+    adjust_offset_with_x(stdout, config, 0)
+}
+
+fn adjust_offset_with_x(stdout: &mut impl Write, config: &Config, x: u16) -> ViuResult {
+    if config.use_current_position {
+        return move_right(stdout, x, config);
+    }
+    if config.pager_safe {
+        // there is no going up, and offsets are relative to wherever the output is
+        for _ in 0..config.y.max(0) {
+            newline(stdout, config)?;
+        }
+        return move_right(stdout, x, config);
+    }
     if config.absolute_offset {
         if config.y >= 0 {
             // If absolute_offset, move to (x,y).
-            execute!(stdout, MoveTo(config.x, config.y as u16))?;
+            execute!(stdout, move_to(x, config.y as u16))?;
         } else {
             //Negative values do not make sense.
             return Err(ViuError::InvalidConfiguration(
@@ -194,19 +607,19 @@ fn adjust_offset(stdout: &mut impl Write, config: &Config) -> ViuResult {
     } else {
         if config.y < 0 {
             // MoveUp if negative
-            execute!(stdout, MoveToPreviousLine(-config.y as u16))?;
+            execute!(stdout, MoveToPreviousLine(config.y.unsigned_abs()))?;
         } else {
             // Move down y lines
             for _ in 0..config.y {
                 // writeln! is used instead of MoveDown to force scrolldown
                 // observed when config.y > 0 and cursor is on the last terminal line
-                writeln!(stdout)?;
+                newline(stdout, config)?;
             }
         }
 
         // Some terminals interpret 0 as 1, see MoveRight documentation
-        if config.x > 0 {
-            execute!(stdout, MoveRight(config.x))?;
+        if x > 0 {
+            execute!(stdout, MoveRight(x))?;
         }
     }
     Ok(())
@@ -289,6 +702,20 @@ mod tests {
         assert_eq!(new_img.height(), 8);
     }
 
+    #[test]
+    fn test_resize_none_some_wide() {
+        // the width bound must not keep the height constraint from applying
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(2000, 40));
+        let new_img = resize(&img, None, Some(5));
+        assert_eq!(new_img.width(), 500);
+        assert_eq!(new_img.height(), 10);
+
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(5000, 12));
+        let new_img = resize(&img, None, Some(5));
+        assert_eq!(new_img.width(), 4166);
+        assert_eq!(new_img.height(), 10);
+    }
+
     #[test]
     fn test_resize_some_some() {
         let width = Some(15);
@@ -328,6 +755,100 @@ mod tests {
         assert_eq!(h, 20);
     }
 
+    #[test]
+    fn fit_for_config_reserve_prompt_row() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(160, 480));
+        let mut config = Config::default();
+        assert_eq!(fit_for_config(&img, &config).unwrap(), (16, 23));
+
+        config.reserve_prompt_row = false;
+        assert_eq!(fit_for_config(&img, &config).unwrap(), (16, 24));
+        assert_eq!(
+            resize_to_cells(&img, fit_for_config(&img, &config).unwrap(), config.filter).height(),
+            48
+        );
+    }
+
+    #[test]
+    fn test_cap_to_max() {
+        let mut config = Config {
+            max_width: Some(20),
+            max_height: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(cap_to_max((10, 5), &config), (10, 5));
+        assert_eq!(cap_to_max((80, 20), &config), (20, 5));
+        assert_eq!(cap_to_max((30, 40), &config), (7, 10));
+
+        config.max_height = None;
+        assert_eq!(cap_to_max((40, 40), &config), (20, 20));
+    }
+
+    #[test]
+    fn test_min_size() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4));
+        let mut config = Config {
+            min_width: Some(16),
+            ..Default::default()
+        };
+        assert_eq!(fit_for_config(&img, &config).unwrap(), (16, 8));
+        assert_eq!(
+            resize_to_cells(&img, fit_for_config(&img, &config).unwrap(), config.filter).width(),
+            16
+        );
+
+        // larger images fit normally
+        let large = DynamicImage::ImageRgba8(image::RgbaImage::new(40, 20));
+        assert_eq!(fit_for_config(&large, &config).unwrap(), (40, 10));
+
+        // the larger scale wins, and the maximum bounds take precedence
+        config.min_height = Some(10);
+        assert_eq!(fit_for_config(&img, &config).unwrap(), (20, 10));
+        config.max_width = Some(12);
+        assert_eq!(fit_for_config(&img, &config).unwrap(), (12, 6));
+    }
+
+    #[test]
+    fn test_fit_for_config_zero_width() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(20, 10));
+        let mut config = Config {
+            width: Some(0),
+            height: Some(5),
+            ..Default::default()
+        };
+        let err = fit_for_config(&img, &config).unwrap_err();
+        assert!(matches!(err, ViuError::InvalidConfiguration(_)));
+
+        config.width = None;
+        config.cols = Some(0);
+        assert!(fit_for_config(&img, &config).is_err());
+        assert!(protocol_fit(&img, &config).is_err());
+
+        config.cols = Some(4);
+        config.rows = Some(0);
+        assert!(fit_for_config(&img, &config).is_err());
+    }
+
+    #[test]
+    fn test_thumbnail_config() {
+        let config = Config::thumbnail(20, 10);
+        assert_eq!(config.max_width, Some(20));
+        assert_eq!(config.max_height, Some(10));
+        assert_eq!(config.width, None);
+        assert_eq!(config.height, None);
+        assert_eq!(config.filter, FilterType::CatmullRom);
+
+        for (w, h) in [(4000, 3000), (300, 2000), (5000, 100)] {
+            let img = DynamicImage::ImageRgba8(image::RgbaImage::new(w, h));
+            let (cols, rows) = fit_for_config(&img, &config).unwrap();
+            assert!(cols <= 20 && rows <= 10, "{}x{} did not fit", cols, rows);
+        }
+
+        // small images are not scaled up
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(6, 4));
+        assert_eq!(fit_for_config(&img, &config).unwrap(), (6, 2));
+    }
+
     #[test]
     fn find_best_fit_some_none() {
         let width = Some(100);
@@ -409,6 +930,78 @@ mod tests {
         assert_eq!((80, 12), fit_dimensions(80, 24, 80, 24));
     }
 
+    #[test]
+    fn test_physical_to_cells() {
+        // 25.4mm is exactly 96 pixels at 96 DPI
+        assert_eq!((12, 6), physical_to_cells((25.4, 25.4), 96.0, (8.0, 16.0)));
+        assert_eq!((10, 10), physical_to_cells((25.4, 50.8), 96.0, (9.6, 19.2)));
+        // a denser screen takes more pixels, and so cells, for the same size
+        assert_eq!(
+            (24, 12),
+            physical_to_cells((25.4, 25.4), 192.0, (8.0, 16.0))
+        );
+        // never smaller than a single cell
+        assert_eq!((1, 1), physical_to_cells((0.1, 0.0), 96.0, (8.0, 16.0)));
+    }
+
+    #[test]
+    fn test_protocol_bounds_without_pixel_size() {
+        let config = Config {
+            width: Some(20),
+            physical_size: Some((25.4, 25.4)),
+            ..Default::default()
+        };
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(10, 10));
+        assert_eq!((Some(20), None), protocol_bounds(&img, &config));
+    }
+
+    #[test]
+    fn test_size_bounds_rows_and_cols() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(300, 100));
+        let mut config = Config {
+            width: Some(4),
+            height: Some(4),
+            rows: Some(10),
+            ..Default::default()
+        };
+        assert_eq!((Some(60), Some(10)), size_bounds(&img, &config));
+
+        config.rows = None;
+        config.cols = Some(30);
+        assert_eq!((Some(30), Some(5)), size_bounds(&img, &config));
+
+        config.rows = Some(7);
+        assert_eq!((Some(30), Some(7)), size_bounds(&img, &config));
+
+        config.rows = None;
+        config.cols = None;
+        assert_eq!((Some(4), Some(4)), size_bounds(&img, &config));
+    }
+
+    #[test]
+    fn test_visible_rows() {
+        let mut config = Config {
+            vertical_overflow: VerticalOverflow::Clip,
+            y: 4,
+            ..Default::default()
+        };
+        assert_eq!(visible_rows(10, &config).unwrap(), 10);
+        assert_eq!(visible_rows(30, &config).unwrap(), 20);
+
+        config.absolute_offset = false;
+        assert_eq!(visible_rows(30, &config).unwrap(), 24);
+
+        config.vertical_overflow = VerticalOverflow::Scroll;
+        assert_eq!(visible_rows(30, &config).unwrap(), 30);
+
+        config.vertical_overflow = VerticalOverflow::Error;
+        assert_eq!(visible_rows(24, &config).unwrap(), 24);
+        assert!(matches!(
+            visible_rows(25, &config),
+            Err(ViuError::InvalidConfiguration(_))
+        ));
+    }
+
     #[test]
     fn test_zero_offset() {
         let config = Config {
@@ -454,6 +1047,37 @@ mod tests {
         test_adjust_offset_output(&config, "\x1b[2F\x1b[1C");
     }
 
+    #[test]
+    fn test_adjust_offset_current_position() {
+        let mut config = Config {
+            absolute_offset: true,
+            y: 4,
+            use_current_position: true,
+            ..Default::default()
+        };
+        test_adjust_offset_output(&config, "");
+
+        config.absolute_offset = false;
+        config.y = -2;
+        config.x = 3;
+        test_adjust_offset_output(&config, "\x1b[3C");
+    }
+
+    #[test]
+    fn test_adjust_offset_pager_safe() {
+        let mut config = Config {
+            x: 2,
+            y: 3,
+            pager_safe: true,
+            ..Default::default()
+        };
+        test_adjust_offset_output(&config, "\n\n\n  ");
+
+        config.absolute_offset = false;
+        config.y = -4;
+        test_adjust_offset_output(&config, "  ");
+    }
+
     #[test]
     fn test_invalid_adjust_offset() {
         let config = Config {
@@ -466,4 +1090,49 @@ mod tests {
         let err = adjust_offset(&mut vec, &config).unwrap_err();
         assert!(matches!(err, ViuError::InvalidConfiguration { .. }));
     }
+
+    #[test]
+    fn test_metadata_trailer() {
+        let trailer = metadata_trailer(Some((640, 480)), (80, 30), PrinterType::Block.name());
+        let fields = trailer
+            .strip_prefix("\x1b]1337;ViuerMetadata=")
+            .and_then(|rest| rest.strip_suffix('\x07'))
+            .unwrap();
+        let fields: Vec<(&str, &str)> = fields
+            .split(';')
+            .map(|field| field.split_once('=').unwrap())
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("original", "640x480"),
+                ("rendered", "80x30"),
+                ("printer", "block")
+            ]
+        );
+
+        assert_eq!(
+            metadata_trailer(None, (3, 1), "kitty"),
+            "\x1b]1337;ViuerMetadata=rendered=3x1;printer=kitty\x07"
+        );
+    }
+
+    #[test]
+    fn test_parse_cursor_position() {
+        assert_eq!(parse_cursor_position("\x1b[1;1R"), Some((0, 0)));
+        assert_eq!(parse_cursor_position("\x1b[12;40R"), Some((39, 11)));
+        assert_eq!(parse_cursor_position("\x1b[0;40R"), None);
+        assert_eq!(parse_cursor_position("\x1b[?62;4c"), None);
+    }
+
+    #[test]
+    fn test_probed_cache() {
+        let probed = Probed::new();
+        assert!(probed.get(|| true));
+
+        // the result is cached until cleared
+        assert!(probed.get(|| false));
+        probed.clear();
+        assert!(!probed.get(|| false));
+    }
 }
@@ -1,8 +1,10 @@
 use crate::error::ViuResult;
-use crate::printer::{adjust_offset, find_best_fit, Printer};
+use crate::printer::{
+    adjust_offset, fit_for_config, resize_exact, Printer, PrinterCapabilities, Probed,
+};
+use crate::query::{query_terminal, query_timeout};
 use crate::Config;
-use console::{Key, Term};
-use image::{imageops::FilterType, DynamicImage, GenericImageView};
+use image::{DynamicImage, GenericImageView};
 use lazy_static::lazy_static;
 use sixel_rs::encoder::{Encoder, QuickFrameBuilder};
 use sixel_rs::optflags::EncodePolicy;
@@ -11,12 +13,12 @@ use std::io::Write;
 pub struct SixelPrinter;
 
 lazy_static! {
-    static ref SIXEL_SUPPORT: bool = check_sixel_support();
+    pub(crate) static ref SIXEL_SUPPORT: Probed<bool> = Probed::new();
 }
 
 /// Returns the terminal's support for Sixel.
 pub fn is_sixel_supported() -> bool {
-    *SIXEL_SUPPORT
+    SIXEL_SUPPORT.get(check_sixel_support)
 }
 
 impl Printer for SixelPrinter {
@@ -26,11 +28,11 @@ impl Printer for SixelPrinter {
         img: &DynamicImage,
         config: &Config,
     ) -> ViuResult<(u32, u32)> {
-        let (w, h) = find_best_fit(img, config.width, config.height);
+        let (w, h) = fit_for_config(img, config)?;
+        let config = &*config.anchored((w, h));
 
         //TODO: the max 1000 width is an xterm bug workaround, other terminals may not be affected
-        let resized_img =
-            img.resize_exact(std::cmp::min(6 * w, 1000), 12 * h, FilterType::Triangle);
+        let resized_img = resize_exact(img, (std::cmp::min(6 * w, 1000), 12 * h), config.filter);
 
         let (width, height) = resized_img.dimensions();
 
@@ -38,6 +40,8 @@ impl Printer for SixelPrinter {
         let raw = rgba.as_raw();
 
         adjust_offset(stdout, config)?;
+        // the encoder writes to the terminal directly, after the cursor was moved
+        stdout.flush()?;
 
         let encoder = Encoder::new()?;
 
@@ -50,32 +54,28 @@ impl Printer for SixelPrinter {
             .pixels(raw.to_vec());
 
         encoder.encode_bytes(frame)?;
+        stdout.flush()?;
 
         Ok((w, h))
     }
+    fn capabilities(&self) -> PrinterCapabilities {
+        PrinterCapabilities {
+            truecolor: false,
+            transparency: false,
+            pixels_per_cell: None,
+            animation: false,
+        }
+    }
 }
 
 // Check if Sixel is within the terminal's attributes
 // see https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h2-Sixel-Graphics
 // and https://vt100.net/docs/vt510-rm/DA1.html
-fn check_device_attrs() -> ViuResult<bool> {
-    let mut term = Term::stderr();
-
-    write!(&mut term, "\x1b[c")?;
-    term.flush()?;
-
-    let mut response = String::new();
-
-    while let Ok(key) = term.read_key() {
-        if let Key::Char(c) = key {
-            response.push(c);
-            if c == 'c' {
-                break;
-            }
-        }
+fn check_device_attrs() -> bool {
+    match query_terminal("\x1b[c", query_timeout()) {
+        Some(response) => response.contains(";4;") || response.contains(";4c"),
+        None => false,
     }
-
-    Ok(response.contains(";4;") || response.contains(";4c"))
 }
 
 // Check if Sixel protocol can be used
@@ -83,9 +83,7 @@ fn check_sixel_support() -> bool {
     if let Ok(term) = std::env::var("TERM") {
         match term.as_str() {
             "mlterm" | "yaft-256color" | "foot" | "foot-extra" => return true,
-            "st-256color" | "xterm" | "xterm-256color" => {
-                return check_device_attrs().unwrap_or(false)
-            }
+            "st-256color" | "xterm" | "xterm-256color" => return check_device_attrs(),
             _ => {
                 if let Ok(term_program) = std::env::var("TERM_PROGRAM") {
                     return term_program == "MacTerm";
@@ -0,0 +1,209 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Environment variable overriding the timeout, in milliseconds
+const QUERY_TIMEOUT_ENV: &str = "VIUER_QUERY_TIMEOUT_MS";
+
+static QUERY_TIMEOUT_MS: AtomicU64 = AtomicU64::new(DEFAULT_QUERY_TIMEOUT.as_millis() as u64);
+
+/// Set how long viuer waits for the terminal to answer a query, like the checks for the graphics
+/// protocols. The default is 500ms.
+///
+/// The `VIUER_QUERY_TIMEOUT_MS` environment variable takes precedence, so that users can adjust
+/// it for slow connections without the application's help.
+pub fn set_query_timeout(timeout: Duration) {
+    QUERY_TIMEOUT_MS.store(timeout.as_millis() as u64, Ordering::Relaxed);
+}
+
+// The timeout for queries to the terminal, see [set_query_timeout].
+pub(crate) fn query_timeout() -> Duration {
+    std::env::var(QUERY_TIMEOUT_ENV)
+        .ok()
+        .and_then(|ms| ms.trim().parse().ok())
+        .map_or_else(
+            || Duration::from_millis(QUERY_TIMEOUT_MS.load(Ordering::Relaxed)),
+            Duration::from_millis,
+        )
+}
+
+/// Send an escape sequence to the terminal and wait for its answer, for at most `timeout`.
+///
+/// The answer is complete once it ends with a string terminator (`ESC \` or `BEL`), or with
+/// the final character of a control sequence. Returns None if the terminal does not answer in
+/// time, e.g. because it does not support the query or is not interactive. The controlling
+/// terminal is read without echo for the duration of the query and restored afterwards. Always
+/// None on platforms other than Unix.
+///
+/// ## Example
+/// ```no_run
+/// use std::time::Duration;
+/// use viuer::query_terminal;
+///
+/// // primary device attributes
+/// match query_terminal("\x1b[c", Duration::from_millis(200)) {
+///     Some(answer) => println!("{:?}", answer),
+///     None => println!("no answer"),
+/// }
+/// ```
+#[cfg(unix)]
+pub fn query_terminal(request: &str, timeout: Duration) -> Option<String> {
+    use std::io::Read;
+
+    let tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+    let _mode = tty::PollingMode::enable(&tty).ok()?;
+    let mut output = &tty;
+    query_with(&mut output, request, timeout, |_| {
+        let mut buf = [0; 256];
+        match (&tty).read(&mut buf) {
+            // empty if nothing arrived within the polling interval
+            Ok(n) => Some(String::from_utf8_lossy(&buf[..n]).into_owned()),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => Some(String::new()),
+            Err(_) => None,
+        }
+    })
+}
+
+/// Always None, the terminal cannot be queried on this platform.
+#[cfg(not(unix))]
+pub fn query_terminal(_request: &str, _timeout: Duration) -> Option<String> {
+    None
+}
+
+// Send the request and collect the chunks of the answer produced by `read` until it is
+// complete, or until the timeout expires. `read` is given the time left and returns within
+// about that time, with an empty chunk if nothing arrived, or None when nothing more can be
+// read.
+fn query_with(
+    output: &mut impl Write,
+    request: &str,
+    timeout: Duration,
+    mut read: impl FnMut(Duration) -> Option<String>,
+) -> Option<String> {
+    output.write_all(request.as_bytes()).ok()?;
+    output.flush().ok()?;
+
+    let deadline = Instant::now() + timeout;
+    let mut response = String::new();
+    while !is_complete(&response) {
+        let remaining = deadline.checked_duration_since(Instant::now())?;
+        response.push_str(&read(remaining)?);
+    }
+    Some(response)
+}
+
+#[cfg(unix)]
+mod tty {
+    use std::io;
+    use std::os::unix::io::{AsRawFd, RawFd};
+
+    // Input mode of the terminal without line buffering or echo, where reads return after at
+    // most a tenth of a second even if nothing arrived. The previous mode is restored on drop.
+    pub(super) struct PollingMode {
+        fd: RawFd,
+        previous: libc::termios,
+    }
+
+    impl PollingMode {
+        pub(super) fn enable(tty: &impl AsRawFd) -> io::Result<Self> {
+            let fd = tty.as_raw_fd();
+            // SAFETY: termios is plain data, filled in by tcgetattr before it is used
+            let mut previous: libc::termios = unsafe { std::mem::zeroed() };
+            if unsafe { libc::tcgetattr(fd, &mut previous) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut polling = previous;
+            polling.c_lflag &= !(libc::ICANON | libc::ECHO);
+            polling.c_cc[libc::VMIN] = 0;
+            polling.c_cc[libc::VTIME] = 1;
+            if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &polling) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(PollingMode { fd, previous })
+        }
+    }
+
+    impl Drop for PollingMode {
+        fn drop(&mut self) {
+            unsafe { libc::tcsetattr(self.fd, libc::TCSANOW, &self.previous) };
+        }
+    }
+}
+
+// Whether the answer ends with a string terminator, or is a control sequence that ended with
+// its final character.
+fn is_complete(response: &str) -> bool {
+    if response.ends_with('\x07') || response.ends_with("\x1b\\") {
+        return true;
+    }
+    match response.strip_prefix("\x1b[") {
+        Some(params) => params.ends_with(|c| ('@'..='~').contains(&c)),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_times_out() {
+        let timeout = Duration::from_millis(50);
+        let start = Instant::now();
+        let mut output = Vec::new();
+        let response = query_with(&mut output, "\x1b[c", timeout, |remaining| {
+            // the terminal keeps silent
+            std::thread::sleep(remaining.min(Duration::from_millis(10)));
+            Some(String::new())
+        });
+
+        assert_eq!(response, None);
+        assert!(start.elapsed() >= timeout);
+        assert!(start.elapsed() < timeout + Duration::from_millis(500));
+        assert_eq!(output, b"\x1b[c");
+    }
+
+    #[test]
+    fn test_query_collects_answer() {
+        let mut chunks = vec!["\x1b[", "?62;", "4c", "ignored"].into_iter();
+        let response = query_with(&mut Vec::new(), "\x1b[c", Duration::from_secs(5), |_| {
+            chunks.next().map(str::to_owned)
+        });
+        assert_eq!(response.as_deref(), Some("\x1b[?62;4c"));
+
+        // the input ends before the answer is complete
+        let mut chunks = vec!["\x1b_Gi=31;OK"].into_iter();
+        let response = query_with(&mut Vec::new(), "", Duration::from_secs(5), |_| {
+            chunks.next().map(str::to_owned)
+        });
+        assert_eq!(response, None);
+    }
+
+    #[test]
+    fn test_is_complete() {
+        assert!(is_complete("\x1b_Gi=31;OK\x1b\\"));
+        assert!(is_complete("\x1b]11;rgb:0000/0000/0000\x07"));
+        assert!(is_complete("\x1b[?62;4c"));
+        assert!(!is_complete("\x1b[?62;4"));
+        assert!(!is_complete("\x1b_Gi=31;OK"));
+        assert!(!is_complete(""));
+    }
+
+    #[test]
+    fn test_query_timeout_setting() {
+        set_query_timeout(Duration::from_millis(120));
+        std::env::remove_var(QUERY_TIMEOUT_ENV);
+        assert_eq!(query_timeout(), Duration::from_millis(120));
+        std::env::set_var(QUERY_TIMEOUT_ENV, "30");
+        assert_eq!(query_timeout(), Duration::from_millis(30));
+        std::env::remove_var(QUERY_TIMEOUT_ENV);
+        set_query_timeout(DEFAULT_QUERY_TIMEOUT);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::error::ViuResult;
+use crate::printer::{render_cells, BlockPrinter, Cell};
+use crate::Config;
+use image::DynamicImage;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use termcolor::WriteColor;
+
+/// Stateful renderer for applications that redraw an image repeatedly, e.g. on every tick of
+/// an event loop.
+///
+/// It remembers what was drawn last and skips rendering when neither the image nor the config
+/// have changed since, unless `force` is set.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{Config, Renderer};
+///
+/// let img = image::DynamicImage::ImageRgba8(image::RgbaImage::new(20, 10));
+/// let conf = Config::default();
+/// let mut renderer = Renderer::new();
+/// loop {
+///     // only the first iteration prints anything
+///     renderer.render_frame(&img, &conf, false).expect("Image printing failed.");
+/// }
+/// ```
+#[derive(Default)]
+pub struct Renderer {
+    last: Option<(u64, (u32, u32))>,
+    cells: Option<Vec<Vec<Cell>>>,
+}
+
+impl Renderer {
+    /// Create a renderer which has not drawn anything yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Print the image like [crate::print] unless it is identical to the previously rendered
+    /// one. Returns the dimensions of the image in terminal cells either way.
+    pub fn render_frame(
+        &mut self,
+        img: &DynamicImage,
+        config: &Config,
+        force: bool,
+    ) -> ViuResult<(u32, u32)> {
+        self.render_if_changed(img, config, force, || crate::print(img, config))
+    }
+
+    /// Same as [Renderer::render_frame], but the half block output is written to the given
+    /// [WriteColor] instead of the terminal.
+    pub fn render_frame_to(
+        &mut self,
+        stdout: &mut impl WriteColor,
+        img: &DynamicImage,
+        config: &Config,
+        force: bool,
+    ) -> ViuResult<(u32, u32)> {
+        self.render_if_changed(img, config, force, || {
+            BlockPrinter::write(stdout, img, config)
+        })
+    }
+
+    /// Resolve the cells of the image like [crate::render_cells] and return the ones that differ
+    /// from the previous call as `(col, row, cell)`, for callers that draw the cells
+    /// themselves, e.g. into a ratatui buffer. Nothing is printed.
+    ///
+    /// All cells are returned on the first call and whenever the number of cells changes. Cells
+    /// of a larger previous frame are not reported, callers have to clear them.
+    pub fn cell_changes(
+        &mut self,
+        img: &DynamicImage,
+        config: &Config,
+    ) -> ViuResult<Vec<(u32, u32, Cell)>> {
+        let cells = render_cells(img, config)?;
+        let previous = self.cells.take().filter(|previous| {
+            previous.len() == cells.len()
+                && previous.iter().zip(&cells).all(|(a, b)| a.len() == b.len())
+        });
+
+        let mut changes = Vec::new();
+        for (row, cells_row) in cells.iter().enumerate() {
+            for (col, cell) in cells_row.iter().enumerate() {
+                let unchanged = previous.as_ref().is_some_and(|p| p[row][col] == *cell);
+                if !unchanged {
+                    changes.push((col as u32, row as u32, *cell));
+                }
+            }
+        }
+        self.cells = Some(cells);
+        Ok(changes)
+    }
+
+    /// Forget the previously rendered image, so that the next frame is always drawn and all of
+    /// its cells are reported as changed.
+    pub fn invalidate(&mut self) {
+        self.last = None;
+        self.cells = None;
+    }
+
+    fn render_if_changed(
+        &mut self,
+        img: &DynamicImage,
+        config: &Config,
+        force: bool,
+        render: impl FnOnce() -> ViuResult<(u32, u32)>,
+    ) -> ViuResult<(u32, u32)> {
+        let hash = content_hash(img, config);
+        if let Some((last_hash, dimensions)) = self.last {
+            if !force && last_hash == hash {
+                return Ok(dimensions);
+            }
+        }
+
+        // forget the previous state in case rendering fails half way
+        self.last = None;
+        let dimensions = render()?;
+        self.last = Some((hash, dimensions));
+        Ok(dimensions)
+    }
+}
+
+// Hash everything that affects the output: the pixels, their layout and the configuration.
+fn content_hash(img: &DynamicImage, config: &Config) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (img.width(), img.height()).hash(&mut hasher);
+    format!("{:?}", img.color()).hash(&mut hasher);
+    img.as_bytes().hash(&mut hasher);
+    format!("{:?}", config).hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TrueColorMode;
+    use termcolor::Ansi;
+
+    fn render(renderer: &mut Renderer, img: &DynamicImage, config: &Config, force: bool) -> usize {
+        let mut buf = Ansi::new(vec![]);
+        renderer
+            .render_frame_to(&mut buf, img, config, force)
+            .unwrap();
+        buf.get_ref().len()
+    }
+
+    #[test]
+    fn test_identical_frame_is_skipped() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4));
+        let config = Config {
+            truecolor: TrueColorMode::Never,
+            ..Default::default()
+        };
+        let mut renderer = Renderer::new();
+
+        assert!(render(&mut renderer, &img, &config, false) > 0);
+        assert_eq!(render(&mut renderer, &img, &config, false), 0);
+        assert!(render(&mut renderer, &img, &config, true) > 0);
+
+        renderer.invalidate();
+        assert!(render(&mut renderer, &img, &config, false) > 0);
+    }
+
+    #[test]
+    fn test_changed_frame_is_rendered() {
+        let mut img = DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4));
+        let mut config = Config {
+            truecolor: TrueColorMode::Never,
+            ..Default::default()
+        };
+        let mut renderer = Renderer::new();
+        render(&mut renderer, &img, &config, false);
+
+        config.x = 2;
+        assert!(render(&mut renderer, &img, &config, false) > 0);
+
+        img.as_mut_rgba8()
+            .unwrap()
+            .put_pixel(0, 0, image::Rgba([1, 2, 3, 255]));
+        assert!(render(&mut renderer, &img, &config, false) > 0);
+    }
+
+    #[test]
+    fn test_cell_changes() {
+        let mut img = image::RgbaImage::from_pixel(3, 4, image::Rgba([10, 10, 10, 255]));
+        let config = Config {
+            width: Some(3),
+            ..Default::default()
+        };
+        let mut renderer = Renderer::new();
+
+        let changes = renderer
+            .cell_changes(&DynamicImage::ImageRgba8(img.clone()), &config)
+            .unwrap();
+        assert_eq!(changes.len(), 6);
+
+        img.put_pixel(1, 0, image::Rgba([200, 0, 0, 255]));
+        img.put_pixel(2, 3, image::Rgba([0, 200, 0, 255]));
+        let changes = renderer
+            .cell_changes(&DynamicImage::ImageRgba8(img.clone()), &config)
+            .unwrap();
+        let positions: Vec<(u32, u32)> = changes.iter().map(|(col, row, _)| (*col, *row)).collect();
+        assert_eq!(positions, vec![(1, 0), (2, 1)]);
+        assert_eq!(changes[0].2.bg, Some((200, 0, 0)));
+        assert_eq!(changes[1].2.fg, Some((0, 200, 0)));
+
+        let img = DynamicImage::ImageRgba8(img);
+        assert!(renderer.cell_changes(&img, &config).unwrap().is_empty());
+        renderer.invalidate();
+        assert_eq!(renderer.cell_changes(&img, &config).unwrap().len(), 6);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::utils::{headless, terminal_size, terminal_size_pixels, truecolor_in};
+use crate::{choose_printer_with, Config, Probes, TERMINAL_PROBES};
+use std::fmt;
+
+/// Summary of what viuer detected about the terminal, for bug reports. See
+/// [capabilities_report].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapabilitiesReport {
+    /// Version of viuer.
+    pub version: &'static str,
+    /// Value of `TERM`, if set.
+    pub term: Option<String>,
+    /// Value of `COLORTERM`, if set.
+    pub colorterm: Option<String>,
+    /// Value of `TERM_PROGRAM`, if set.
+    pub term_program: Option<String>,
+    /// Whether running inside tmux, detected through `TMUX`.
+    pub tmux: bool,
+    /// Whether there is no terminal to print to, see [crate::is_headless].
+    pub headless: bool,
+    /// Whether truecolor is available, as used by [crate::TrueColorMode::Auto].
+    pub truecolor: bool,
+    /// Size of the terminal in cells, see [crate::terminal_size].
+    pub size: (u16, u16),
+    /// Size of the terminal in pixels, if known, see [crate::terminal_size_pixels].
+    pub size_pixels: Option<(u16, u16)>,
+    /// Name of the printer [crate::print] would use with the config, like "kitty" or "block".
+    pub printer: &'static str,
+}
+
+/// Collect what viuer detects about the terminal and the printer it would choose with the
+/// config into a single report, which users can paste into bug reports.
+///
+/// Detecting the graphics protocols may query the terminal, just like printing does.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{capabilities_report, Config};
+///
+/// println!("{}", capabilities_report(&Config::default()));
+/// ```
+pub fn capabilities_report(config: &Config) -> CapabilitiesReport {
+    use std::io::IsTerminal;
+    report_with(
+        config,
+        |name| std::env::var(name).ok(),
+        std::io::stdout().is_terminal(),
+        crossterm::terminal::size()
+            .ok()
+            .filter(|&(w, h)| w > 0 && h > 0),
+        terminal_size_pixels(),
+        &TERMINAL_PROBES,
+    )
+}
+
+// The report given the environment variables, whether stdout is a terminal, the size of the
+// terminal in cells if it can be determined and in pixels, and the checks of the graphics
+// protocols.
+fn report_with(
+    config: &Config,
+    var: impl Fn(&str) -> Option<String>,
+    stdout_is_terminal: bool,
+    size: Option<(u16, u16)>,
+    size_pixels: Option<(u16, u16)>,
+    probes: &Probes,
+) -> CapabilitiesReport {
+    let term = var("TERM");
+    let colorterm = var("COLORTERM");
+    CapabilitiesReport {
+        version: env!("CARGO_PKG_VERSION"),
+        headless: headless(stdout_is_terminal, size.is_some(), term.as_deref()),
+        truecolor: truecolor_in(colorterm.as_deref()),
+        term,
+        colorterm,
+        term_program: var("TERM_PROGRAM"),
+        tmux: var("TMUX").is_some(),
+        size: size.unwrap_or_else(terminal_size),
+        size_pixels,
+        printer: choose_printer_with(config, probes).name(),
+    }
+}
+
+impl fmt::Display for CapabilitiesReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = |var: &Option<String>| var.clone().unwrap_or_else(|| "(unset)".to_owned());
+        let yes_no = |flag: bool| if flag { "yes" } else { "no" };
+
+        writeln!(f, "viuer {}", self.version)?;
+        writeln!(f, "TERM: {}", value(&self.term))?;
+        writeln!(f, "COLORTERM: {}", value(&self.colorterm))?;
+        writeln!(f, "TERM_PROGRAM: {}", value(&self.term_program))?;
+        writeln!(f, "tmux: {}", yes_no(self.tmux))?;
+        writeln!(f, "headless: {}", yes_no(self.headless))?;
+        writeln!(f, "truecolor: {}", yes_no(self.truecolor))?;
+        write!(f, "size: {}x{} cells", self.size.0, self.size.1)?;
+        match self.size_pixels {
+            Some((w, h)) => writeln!(f, ", {}x{} pixels", w, h)?,
+            None => writeln!(f, ", unknown pixels")?,
+        }
+        write!(f, "printer: {}", self.printer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KITTY: Probes = Probes {
+        kitty: || true,
+        #[cfg(feature = "sixel")]
+        sixel: || false,
+        iterm: || false,
+    };
+    const NO_PROTOCOLS: Probes = Probes {
+        kitty: || false,
+        #[cfg(feature = "sixel")]
+        sixel: || false,
+        iterm: || false,
+    };
+
+    #[test]
+    fn test_capabilities_report() {
+        let env = |name: &str| match name {
+            "TERM" => Some("xterm-kitty".to_owned()),
+            "TMUX" => Some("/tmp/tmux-1000/default,1,0".to_owned()),
+            _ => None,
+        };
+        let config = Config::default();
+        let report = report_with(&config, env, true, Some((120, 40)), None, &KITTY);
+        assert_eq!(report.term.as_deref(), Some("xterm-kitty"));
+        assert!(report.tmux);
+        assert!(!report.headless);
+        assert!(!report.truecolor);
+        assert_eq!(report.size, (120, 40));
+
+        let text = report.to_string();
+        assert!(text.starts_with(&format!("viuer {}\n", env!("CARGO_PKG_VERSION"))));
+        assert!(text.contains("TERM: xterm-kitty\n"));
+        assert!(text.contains("COLORTERM: (unset)\n"));
+        assert!(text.contains("tmux: yes\n"));
+        assert!(text.contains("headless: no\n"));
+        assert!(text.contains("size: 120x40 cells, unknown pixels\n"));
+        assert!(text.ends_with("printer: kitty"));
+    }
+
+    #[test]
+    fn test_capabilities_report_printer() {
+        let env = |name: &str| match name {
+            "COLORTERM" => Some("truecolor".to_owned()),
+            _ => None,
+        };
+        let report = |config: &Config, probes: &Probes| {
+            report_with(config, env, false, None, Some((800, 600)), probes)
+        };
+
+        let config = Config::default();
+        let headless = report(&config, &NO_PROTOCOLS);
+        assert!(headless.headless);
+        assert!(headless.truecolor);
+        assert_eq!(headless.size_pixels, Some((800, 600)));
+        assert_eq!(headless.printer, "block");
+        assert_eq!(report(&config, &KITTY).printer, "kitty");
+
+        let config = Config {
+            use_kitty: false,
+            ..Default::default()
+        };
+        assert_eq!(report(&config, &KITTY).printer, "block");
+        let config = Config {
+            use_ascii: true,
+            ..Default::default()
+        };
+        assert_eq!(report(&config, &KITTY).printer, "ascii");
+    }
+}
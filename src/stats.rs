@@ -0,0 +1,116 @@
+use crate::error::ViuResult;
+use crate::{Config, Writer};
+use image::DynamicImage;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use termcolor::Ansi;
+
+/// Time spent on the steps of printing an image and the size of the output, see
+/// [print_with_stats].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Time spent decoding the file, zero for images decoded by the caller.
+    pub decode: Duration,
+    /// Time spent resizing the image and applying the adjustments of the config to its pixels.
+    pub resize: Duration,
+    /// Time spent turning the pixels into cells and escape sequences.
+    pub render: Duration,
+    /// Number of bytes written to the output.
+    pub bytes_written: usize,
+}
+
+/// Print the image with half blocks like [crate::print] does without a graphics protocol, and
+/// return how long the steps took together with the dimensions in terminal cells.
+///
+/// The output is rendered in memory before it is written, as ANSI escape sequences like with
+/// `force_ansi`, so that its size can be measured. [crate::print] is not slowed down by the
+/// measurements.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{print_with_stats, Config};
+///
+/// let img = image::open("img.jpg").expect("Could not open image.");
+/// let (_, stats) = print_with_stats(&img, &Config::default()).expect("Image printing failed.");
+/// eprintln!("rendered in {:?}", stats.resize + stats.render);
+/// ```
+pub fn print_with_stats(
+    img: &DynamicImage,
+    config: &Config,
+) -> ViuResult<((u32, u32), RenderStats)> {
+    write_with_stats(
+        &mut Writer::from_config(config),
+        img,
+        config,
+        Duration::ZERO,
+    )
+}
+
+/// Decode the file and print it like [print_with_stats], including the time spent decoding in
+/// the stats.
+pub fn print_from_file_with_stats<P: AsRef<Path>>(
+    filename: P,
+    config: &Config,
+) -> ViuResult<((u32, u32), RenderStats)> {
+    let start = Instant::now();
+    let img = crate::decode::decode_file(filename)?;
+    write_with_stats(
+        &mut Writer::from_config(config),
+        &img,
+        config,
+        start.elapsed(),
+    )
+}
+
+fn write_with_stats(
+    out: &mut impl Write,
+    img: &DynamicImage,
+    config: &Config,
+    decode: Duration,
+) -> ViuResult<((u32, u32), RenderStats)> {
+    let mut stats = RenderStats {
+        decode,
+        ..Default::default()
+    };
+    let mut buffer = Ansi::new(Vec::new());
+    let dimensions =
+        crate::printer::BlockPrinter::write_timed(&mut buffer, img, config, &mut stats)?;
+    let bytes = buffer.into_inner();
+    out.write_all(&bytes)?;
+    out.flush()?;
+    stats.bytes_written = bytes.len();
+    Ok((dimensions, stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TrueColorMode;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_stats_match_output() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(12, 8, |x, y| {
+            Rgba([x as u8 * 20, y as u8 * 30, 90, 255])
+        }));
+        let config = Config {
+            absolute_offset: false,
+            truecolor: TrueColorMode::Force,
+            ..Default::default()
+        };
+
+        let mut out = Vec::new();
+        let decode = Duration::from_millis(3);
+        let (dimensions, stats) = write_with_stats(&mut out, &img, &config, decode).unwrap();
+        assert_eq!(dimensions, (12, 4));
+        assert_eq!(stats.decode, decode);
+        assert!(stats.resize >= Duration::ZERO);
+        assert!(stats.render >= Duration::ZERO);
+        assert_eq!(stats.bytes_written, out.len());
+
+        let mut expected = Ansi::new(Vec::new());
+        crate::printer::BlockPrinter::write(&mut expected, &img, &config).unwrap();
+        assert_eq!(out, expected.into_inner());
+    }
+}
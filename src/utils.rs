@@ -1,16 +1,152 @@
+use crate::color::BASIC_PALETTE;
+use crate::printer::Probed;
+use lazy_static::lazy_static;
 use std::env;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 const DEFAULT_TERM_SIZE: (u16, u16) = (80, 24);
 
+// The fallback terminal size, with the width in the upper and the height in the lower 16 bits.
+static FALLBACK_TERM_SIZE: AtomicU32 =
+    AtomicU32::new(pack_size(DEFAULT_TERM_SIZE.0, DEFAULT_TERM_SIZE.1));
+
+lazy_static! {
+    pub(crate) static ref DARK_BACKGROUND: Probed<Option<bool>> = Probed::new();
+}
+
+// Convert a count of cells to a terminal coordinate, saturating instead of wrapping around when
+// it does not fit.
+pub(crate) fn saturating_u16(n: impl TryInto<u16>) -> u16 {
+    n.try_into().unwrap_or(u16::MAX)
+}
+
+// Like saturating_u16, for signed offsets.
+pub(crate) fn saturating_i16(n: impl TryInto<i16>) -> i16 {
+    n.try_into().unwrap_or(i16::MAX)
+}
+
+const fn pack_size(width: u16, height: u16) -> u32 {
+    (width as u32) << 16 | height as u32
+}
+
 pub fn truecolor_available() -> bool {
-    if let Ok(value) = env::var("COLORTERM") {
-        value.contains("truecolor") || value.contains("24bit")
-    } else {
-        false
+    truecolor_in(env::var("COLORTERM").ok().as_deref())
+}
+
+// Whether the value of `COLORTERM` announces truecolor.
+pub(crate) fn truecolor_in(colorterm: Option<&str>) -> bool {
+    colorterm.is_some_and(|value| value.contains("truecolor") || value.contains("24bit"))
+}
+
+/// Guess whether the terminal is likely to render half blocks with visible gaps between rows,
+/// which makes images printed by the block printer look striped.
+///
+/// This is a heuristic based on the `TERM` and `TERM_PROGRAM` environment variables, as the
+/// font itself cannot be queried. Callers can use it to warn users or choose another printer,
+/// like the ASCII one.
+pub fn likely_block_font_gaps() -> bool {
+    block_font_gaps(
+        env::var("TERM").ok().as_deref(),
+        env::var("TERM_PROGRAM").ok().as_deref(),
+    )
+}
+
+// Terminals known to use bitmap fonts or line spacing that leave gaps between block glyphs.
+pub(crate) fn block_font_gaps(term: Option<&str>, term_program: Option<&str>) -> bool {
+    if term_program == Some("Apple_Terminal") {
+        return true;
+    }
+    match term {
+        Some(term) => ["linux", "cons25", "vt100", "vt220", "xterm-mono"]
+            .iter()
+            .any(|known| term == *known || term.starts_with(&format!("{}-", known))),
+        None => false,
     }
 }
 
-/// Try to get the terminal size. If unsuccessful, fallback to a default (80x24).
+/// Whether there is likely no terminal to print to, e.g. in CI or when the output goes to a log
+/// file, so that printing images can be skipped altogether.
+///
+/// True if stdout is not a terminal, the size of the terminal cannot be determined either and
+/// `TERM` is unset or `dumb`.
+pub fn is_headless() -> bool {
+    use std::io::IsTerminal;
+    headless(
+        std::io::stdout().is_terminal(),
+        crossterm::terminal::size().is_ok_and(|(w, h)| w > 0 && h > 0),
+        env::var("TERM").ok().as_deref(),
+    )
+}
+
+pub(crate) fn headless(stdout_is_terminal: bool, size_known: bool, term: Option<&str>) -> bool {
+    !stdout_is_terminal && !size_known && matches!(term, None | Some("") | Some("dumb"))
+}
+
+/// Guess whether the terminal has a dark background. Returns None if it cannot be told, in which
+/// case callers should assume a dark one.
+///
+/// The terminal is asked for its background color with an OSC 11 query. If it does not answer,
+/// the color is read from the `COLORFGBG` environment variable instead, which many terminals set
+/// to the palette indices of their default foreground and background colors. The color is
+/// classified by its luminance. The result is cached until
+/// [crate::refresh_terminal_capabilities] is called.
+#[cfg(not(test))]
+pub fn is_dark_background() -> Option<bool> {
+    DARK_BACKGROUND.get(|| {
+        use std::io::IsTerminal;
+        let queried = if std::io::stderr().is_terminal() {
+            let answer =
+                crate::query::query_terminal("\x1b]11;?\x1b\\", crate::query::query_timeout());
+            answer.as_deref().and_then(background_from_osc11)
+        } else {
+            None
+        };
+        queried
+            .or_else(|| background_from_colorfgbg(&env::var("COLORFGBG").ok()?))
+            .map(is_dark)
+    })
+}
+
+/// The background is never known when running the tests
+#[cfg(test)]
+pub fn is_dark_background() -> Option<bool> {
+    None
+}
+
+// The answer to an OSC 11 query looks like "ESC ] 11 ; rgb:RRRR/GGGG/BBBB ST", with one to four
+// hex digits per channel.
+fn background_from_osc11(answer: &str) -> Option<(u8, u8, u8)> {
+    let start = answer.find("rgb:")? + "rgb:".len();
+    let spec = answer[start..].trim_end_matches(['\x07', '\x1b', '\\']);
+    let mut channels = spec.split('/').map(|hex| {
+        if hex.is_empty() || hex.len() > 4 {
+            return None;
+        }
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        let max = (1 << (4 * hex.len())) - 1;
+        Some((value * 255 / max) as u8)
+    });
+    let rgb = (channels.next()??, channels.next()??, channels.next()??);
+    match channels.next() {
+        None => Some(rgb),
+        Some(_) => None,
+    }
+}
+
+// COLORFGBG looks like "15;0" or "15;default;0", the background index coming last.
+fn background_from_colorfgbg(value: &str) -> Option<(u8, u8, u8)> {
+    let index: usize = value.rsplit(';').next()?.trim().parse().ok()?;
+    BASIC_PALETTE.get(index).copied()
+}
+
+// A color is dark when its relative luminance is below the middle gray.
+fn is_dark((r, g, b): (u8, u8, u8)) -> bool {
+    let luminance = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+    luminance < 128.0
+}
+
+/// Try to get the terminal size. If unsuccessful, fallback to a default (80x24), which can be
+/// changed with [set_default_terminal_size].
 ///
 /// Uses [crossterm::terminal::size].
 /// ## Example
@@ -34,27 +170,139 @@ pub fn truecolor_available() -> bool {
 /// ```
 #[cfg(not(test))]
 pub fn terminal_size() -> (u16, u16) {
-    match crossterm::terminal::size() {
-        Ok(s) => s,
-        Err(_) => DEFAULT_TERM_SIZE,
-    }
+    size_or_default(crossterm::terminal::size())
+}
+
+fn size_or_default(size: std::io::Result<(u16, u16)>) -> (u16, u16) {
+    size.unwrap_or_else(|_| {
+        let packed = FALLBACK_TERM_SIZE.load(Ordering::Relaxed);
+        ((packed >> 16) as u16, packed as u16)
+    })
 }
 
-// Return a constant when running the tests
+/// Set the size [terminal_size] falls back to when the terminal cannot be queried, e.g. for a
+/// fixed-size embedded display. The default is 80x24.
+pub fn set_default_terminal_size(width: u16, height: u16) {
+    FALLBACK_TERM_SIZE.store(pack_size(width, height), Ordering::Relaxed);
+}
+
+/// Return a constant when running the tests
 #[cfg(test)]
 pub fn terminal_size() -> (u16, u16) {
     DEFAULT_TERM_SIZE
 }
 
+/// Try to get the terminal size in pixels. Returns None if the terminal does not report it.
+///
+/// Uses [crossterm::terminal::window_size].
+#[cfg(not(test))]
+pub fn terminal_size_pixels() -> Option<(u16, u16)> {
+    match crossterm::terminal::window_size() {
+        Ok(s) if s.width > 0 && s.height > 0 => Some((s.width, s.height)),
+        _ => None,
+    }
+}
+
+/// Pixel size is never known when running the tests
+#[cfg(test)]
+pub fn terminal_size_pixels() -> Option<(u16, u16)> {
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_truecolor() {
+        // the automatic mode is checked here too, as both depend on the environment
+        let auto = crate::Config::default();
         env::set_var("COLORTERM", "truecolor");
         assert!(truecolor_available());
+        assert!(auto.use_truecolor());
         env::set_var("COLORTERM", "");
         assert!(!truecolor_available());
+        assert!(!auto.use_truecolor());
+    }
+
+    #[test]
+    fn test_block_font_gaps() {
+        assert!(block_font_gaps(Some("linux"), None));
+        assert!(block_font_gaps(Some("vt100"), None));
+        assert!(block_font_gaps(
+            Some("xterm-256color"),
+            Some("Apple_Terminal")
+        ));
+
+        assert!(!block_font_gaps(Some("xterm-256color"), None));
+        assert!(!block_font_gaps(Some("xterm-kitty"), None));
+        assert!(!block_font_gaps(Some("xterm-256color"), Some("iTerm.app")));
+        assert!(!block_font_gaps(None, None));
+    }
+
+    #[test]
+    fn test_background_classification() {
+        assert!(is_dark((0, 0, 0)));
+        assert!(is_dark((40, 42, 54)));
+        assert!(is_dark((0, 0, 238)));
+        assert!(!is_dark((255, 255, 255)));
+        assert!(!is_dark((253, 246, 227)));
+        assert!(!is_dark((0, 205, 0)));
+
+        assert_eq!(background_from_colorfgbg("15;0"), Some((0, 0, 0)));
+        assert_eq!(
+            background_from_colorfgbg("0;default;15"),
+            Some((255, 255, 255))
+        );
+        assert_eq!(background_from_colorfgbg("15;default"), None);
+        assert_eq!(background_from_colorfgbg("0;16"), None);
+        assert_eq!(background_from_colorfgbg(""), None);
+
+        assert_eq!(
+            background_from_osc11("\x1b]11;rgb:ffff/ffff/ffff\x1b\\"),
+            Some((255, 255, 255))
+        );
+        assert_eq!(
+            background_from_osc11("\x1b]11;rgb:28/2a/36\x07"),
+            Some((40, 42, 54))
+        );
+        assert_eq!(
+            background_from_osc11("\x1b]11;rgb:f/0/8\x07"),
+            Some((255, 0, 136))
+        );
+        assert_eq!(background_from_osc11("\x1b]11;rgb:ffff/ffff\x07"), None);
+        assert_eq!(background_from_osc11("\x1b]11;rgb:12345/0/0\x07"), None);
+        assert_eq!(background_from_osc11("\x1b[?62;4c"), None);
+    }
+
+    #[test]
+    fn test_default_terminal_size() {
+        let ok = Ok((120, 40));
+        assert_eq!(size_or_default(ok), (120, 40));
+
+        let failed = || Err(std::io::Error::other("not a terminal"));
+        set_default_terminal_size(40, 30);
+        assert_eq!(size_or_default(failed()), (40, 30));
+        set_default_terminal_size(80, 24);
+        assert_eq!(size_or_default(failed()), DEFAULT_TERM_SIZE);
+    }
+
+    #[test]
+    fn test_headless() {
+        use std::io::IsTerminal;
+
+        // a file is not a terminal
+        let file = tempfile::tempfile().unwrap();
+        assert!(!file.is_terminal());
+        assert!(headless(file.is_terminal(), false, Some("dumb")));
+        assert!(headless(file.is_terminal(), false, None));
+        // output redirected, but the terminal is still around
+        assert!(!headless(file.is_terminal(), true, Some("dumb")));
+        assert!(!headless(file.is_terminal(), false, Some("xterm-256color")));
+
+        // a terminal is never headless, whatever TERM says
+        assert!(!headless(true, false, Some("dumb")));
+        assert!(!headless(true, false, None));
+        assert!(!headless(true, true, Some("xterm-256color")));
     }
 }
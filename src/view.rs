@@ -0,0 +1,213 @@
+use crate::error::ViuResult;
+use crate::printer::{move_right, newline};
+use crate::writer::Writer;
+use crate::Config;
+use crossterm::cursor::{RestorePosition, SavePosition};
+use crossterm::execute;
+use image::DynamicImage;
+use std::io::Write;
+use unicode_width::UnicodeWidthStr;
+
+/// Horizontal alignment of a caption below an image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CaptionAlign {
+    /// Start at the left edge of the image.
+    #[default]
+    Left,
+    /// Center below the image.
+    Center,
+    /// End at the right edge of the image.
+    Right,
+}
+
+/// An image printed together with an optional single line caption below it.
+///
+/// The caption is aligned within the width of the image and padded to it, so that whatever was
+/// printed there before is cleared. It follows the image wherever the config places it, e.g.
+/// with `align` or `pane`. A caption wider than the image is printed in full.
+///
+/// ## Example
+/// ```no_run
+/// use viuer::{CaptionAlign, Config, ImageView};
+///
+/// let img = image::open("img.jpg").expect("Could not open image.");
+/// let conf = Config::default();
+/// let (cols, rows) = ImageView::new(&img, &conf)
+///     .caption("img.jpg")
+///     .align(CaptionAlign::Center)
+///     .render()
+///     .expect("Image printing failed.");
+/// ```
+pub struct ImageView<'a> {
+    img: &'a DynamicImage,
+    config: &'a Config,
+    caption: Option<String>,
+    align: CaptionAlign,
+}
+
+impl<'a> ImageView<'a> {
+    /// Create a view of the image, printed according to the config and without a caption.
+    pub fn new(img: &'a DynamicImage, config: &'a Config) -> Self {
+        Self {
+            img,
+            config,
+            caption: None,
+            align: CaptionAlign::Left,
+        }
+    }
+
+    /// Set the caption printed below the image. Line breaks are replaced by spaces.
+    pub fn caption(mut self, caption: impl Into<String>) -> Self {
+        self.caption = Some(caption.into().replace(['\r', '\n'], " "));
+        self
+    }
+
+    /// Set the alignment of the caption. Defaults to [CaptionAlign::Left].
+    pub fn align(mut self, align: CaptionAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Print the image like [crate::print], followed by the caption. Returns the dimensions
+    /// taken up by both in terminal cells.
+    pub fn render(&self) -> ViuResult<(u32, u32)> {
+        let mut stdout = Writer::from_config(self.config);
+        self.render_to(&mut stdout, crate::print)
+    }
+
+    fn render_to(
+        &self,
+        stdout: &mut impl Write,
+        draw: impl FnOnce(&DynamicImage, &Config) -> ViuResult<(u32, u32)>,
+    ) -> ViuResult<(u32, u32)> {
+        // the cursor has to stay below the image for the caption, it is restored afterwards
+        let config = Config {
+            restore_cursor: false,
+            ..self.config.clone()
+        };
+        if self.config.restore_cursor {
+            execute!(stdout, SavePosition)?;
+        }
+
+        let (mut cols, mut rows) = draw(self.img, &config)?;
+        if let Some(caption) = &self.caption {
+            let line = align_caption(caption, cols, self.align);
+            // the caption starts at the column the image was moved to by the placement options
+            let x = config.in_pane().anchored((cols, rows)).x;
+            move_right(stdout, x, &config)?;
+            write!(stdout, "{}", line)?;
+            newline(stdout, &config)?;
+            cols = cols.max(line.width() as u32);
+            rows += 1;
+        }
+
+        if self.config.restore_cursor {
+            execute!(stdout, RestorePosition)?;
+        }
+        stdout.flush()?;
+        Ok((cols, rows))
+    }
+}
+
+// Pad the caption with spaces to the given width, placing it according to the alignment.
+fn align_caption(caption: &str, width: u32, align: CaptionAlign) -> String {
+    let free = (width as usize).saturating_sub(caption.width());
+    let left = match align {
+        CaptionAlign::Left => 0,
+        CaptionAlign::Center => free / 2,
+        CaptionAlign::Right => free,
+    };
+    format!("{}{}{}", " ".repeat(left), caption, " ".repeat(free - left))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_view_caption() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(10, 10));
+        let config = Config {
+            x: 2,
+            ..Default::default()
+        };
+        let view = ImageView::new(&img, &config)
+            .caption("cat\n")
+            .align(CaptionAlign::Center);
+
+        let mut out = Vec::new();
+        let dimensions = view.render_to(&mut out, |_, _| Ok((10, 5))).unwrap();
+        assert_eq!(dimensions, (10, 6));
+        assert_eq!(String::from_utf8(out).unwrap(), "\x1b[2C   cat    \n");
+
+        // without a caption, only the image is counted
+        let mut out = Vec::new();
+        let view = ImageView::new(&img, &config);
+        assert_eq!(
+            view.render_to(&mut out, |_, _| Ok((10, 5))).unwrap(),
+            (10, 5)
+        );
+        assert!(out.is_empty());
+
+        // the caption follows the line ending and pager safety of the config
+        let config = Config {
+            x: 2,
+            pager_safe: true,
+            line_ending: crate::LineEnding::CrLf,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        let view = ImageView::new(&img, &config).caption("cat");
+        view.render_to(&mut out, |_, _| Ok((4, 2))).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "  cat \r\n");
+    }
+
+    #[test]
+    fn test_image_view_caption_placement() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(10, 10));
+        let caption_of = |config: &Config| {
+            let mut out = Vec::new();
+            let view = ImageView::new(&img, config).caption("cat");
+            view.render_to(&mut out, |_, _| Ok((10, 5))).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        // centered in the 80 columns wide terminal
+        let config = Config {
+            align: Some(crate::Anchor::Center),
+            ..Default::default()
+        };
+        assert_eq!(caption_of(&config), "\x1b[35Ccat       \n");
+
+        // offset within the pane
+        let config = Config {
+            x: 2,
+            pane: Some(crate::Rect {
+                x: 30,
+                y: 0,
+                width: 40,
+                height: 20,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(caption_of(&config), "\x1b[32Ccat       \n");
+
+        // touching the right edge of the terminal
+        let config = Config {
+            right_to_left: true,
+            ..Default::default()
+        };
+        assert_eq!(caption_of(&config), "\x1b[70Ccat       \n");
+    }
+
+    #[test]
+    fn test_align_caption() {
+        assert_eq!(align_caption("ab", 5, CaptionAlign::Left), "ab   ");
+        assert_eq!(align_caption("ab", 5, CaptionAlign::Right), "   ab");
+        assert_eq!(align_caption("一", 4, CaptionAlign::Center), " 一 ");
+        assert_eq!(
+            align_caption("long caption", 4, CaptionAlign::Center),
+            "long caption"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use crate::printer::render_cells;
+use crate::Config;
+use ansi_colours::ansi256_from_rgb;
+use image::DynamicImage;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Color;
+use ratatui::widgets::Widget;
+
+/// A [Widget] drawing an image with half blocks into a `ratatui` [Buffer].
+///
+/// The cells are written directly into the buffer instead of being printed as escape
+/// sequences, so the image takes part in `ratatui`'s diffing like any other widget. The image
+/// is fitted into the area it is rendered in, further limited by the size options of the
+/// [Config]. The offset options are ignored, since the area determines the position.
+///
+/// ## Example
+/// ```no_run
+/// use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
+/// use viuer::{Config, ViuWidget};
+///
+/// let img = image::DynamicImage::ImageRgba8(image::RgbaImage::new(20, 10));
+/// let conf = Config::default();
+/// let mut buf = Buffer::empty(Rect::new(0, 0, 40, 20));
+/// ViuWidget::new(&img, &conf).render(buf.area, &mut buf);
+/// ```
+pub struct ViuWidget<'a> {
+    img: &'a DynamicImage,
+    config: &'a Config,
+}
+
+impl<'a> ViuWidget<'a> {
+    /// Create a widget drawing the image according to the config.
+    pub fn new(img: &'a DynamicImage, config: &'a Config) -> Self {
+        Self { img, config }
+    }
+}
+
+impl Widget for ViuWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.is_empty() {
+            return;
+        }
+
+        let mut config = self.config.clone();
+        // fill the area unless another size was asked for, but never go beyond it
+        if config.width.is_none()
+            && config.height.is_none()
+            && config.cols.is_none()
+            && config.rows.is_none()
+        {
+            config.width = Some(area.width as u32);
+            config.height = Some(area.height as u32);
+        }
+        config.max_width = Some(
+            config
+                .max_width
+                .map_or(area.width as u32, |w| std::cmp::min(w, area.width as u32)),
+        );
+        config.max_height = Some(
+            config
+                .max_height
+                .map_or(area.height as u32, |h| std::cmp::min(h, area.height as u32)),
+        );
+        config.reserve_prompt_row = false;
+
+        // a widget cannot report errors, an invalid config leaves the area untouched
+        let cells = match render_cells(self.img, &config) {
+            Ok(cells) => cells,
+            Err(_) => return,
+        };
+
+        for (row, cells_row) in cells.iter().enumerate().take(area.height as usize) {
+            for (col, cell) in cells_row.iter().enumerate().take(area.width as usize) {
+                if cell.is_transparent() {
+                    continue;
+                }
+                let position = (area.x + col as u16, area.y + row as u16);
+                if let Some(buf_cell) = buf.cell_mut(position) {
+                    buf_cell.set_symbol(cell.glyph);
+                    if let Some(fg) = cell.fg {
+                        buf_cell.set_fg(to_color(fg, config.use_truecolor()));
+                    }
+                    buf_cell.set_bg(
+                        cell.bg
+                            .map_or(Color::Reset, |bg| to_color(bg, config.bg_truecolor())),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn to_color(rgb: (u8, u8, u8), truecolor: bool) -> Color {
+    if truecolor {
+        Color::Rgb(rgb.0, rgb.1, rgb.2)
+    } else {
+        Color::Indexed(ansi256_from_rgb(rgb))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TrueColorMode;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_widget_renders_cells() {
+        // red on top, blue at the bottom
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 4, |_, y| {
+            if y < 2 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 255, 255])
+            }
+        }));
+        let config = Config {
+            truecolor: TrueColorMode::Force,
+            ..Default::default()
+        };
+        let mut buf = Buffer::empty(Rect::new(0, 0, 6, 3));
+        ViuWidget::new(&img, &config).render(Rect::new(1, 1, 4, 2), &mut buf);
+
+        let cell = &buf[(1, 1)];
+        assert_eq!(cell.symbol(), "\u{2584}");
+        assert_eq!(cell.fg, Color::Rgb(255, 0, 0));
+        assert_eq!(cell.bg, Color::Rgb(255, 0, 0));
+
+        let cell = &buf[(1, 2)];
+        assert_eq!(cell.fg, Color::Rgb(0, 0, 255));
+        assert_eq!(cell.bg, Color::Rgb(0, 0, 255));
+
+        // nothing is drawn outside of the area
+        assert_eq!(buf[(0, 1)].symbol(), " ");
+        assert_eq!(buf[(5, 1)].symbol(), " ");
+        assert_eq!(buf[(1, 0)].symbol(), " ");
+    }
+
+    #[test]
+    fn test_widget_ansi256() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255])));
+        let config = Config {
+            truecolor: TrueColorMode::Never,
+            ..Default::default()
+        };
+        let mut buf = Buffer::empty(Rect::new(0, 0, 2, 1));
+        ViuWidget::new(&img, &config).render(buf.area, &mut buf);
+
+        assert_eq!(buf[(0, 0)].fg, Color::Indexed(196));
+    }
+}
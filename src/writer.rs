@@ -1,21 +1,39 @@
 use std::io::{stderr, stdout, Write};
 
 use crate::Config;
-#[derive(Clone, Copy)]
+
+/// Standard stream a [Writer] outputs to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputTarget {
+    /// standard output
+    Stdout,
+    /// standard error
+    Stderr,
+}
+
+#[derive(Clone, Copy, Debug)]
 /// Override Output stream
 pub struct Writer {
-    /// use stderr instead of stdout
-    use_stderr: bool,
+    /// stream the output goes to
+    target: OutputTarget,
 }
 
 impl Writer {
+    /// create new instance of writer that outputs to the given stream
+    pub fn from_target(target: OutputTarget) -> Writer {
+        Writer { target }
+    }
     /// create new instance of writer that outputs to stderr
     pub fn stderr() -> Writer {
-        Writer { use_stderr: true }
+        Writer::from_target(OutputTarget::Stderr)
     }
     /// create new instance of writer that outputs to stdout
     pub fn stdout() -> Writer {
-        Writer { use_stderr: false }
+        Writer::from_target(OutputTarget::Stdout)
+    }
+    /// stream this writer outputs to
+    pub fn target(&self) -> OutputTarget {
+        self.target
     }
     /// Create new instance of writer or use overwritten writer from config
     ///
@@ -23,25 +41,84 @@ impl Writer {
     pub(crate) fn from_config(config: &Config) -> Writer {
         match (config.override_writer, config.use_stderr) {
             (Some(writer), _) => writer,
-            (None, use_stderr) => Writer {
-                use_stderr: use_stderr,
-            },
+            (None, true) => Writer::stderr(),
+            (None, false) => Writer::stdout(),
+        }
+    }
+
+    // The one of the given streams this writer outputs to.
+    fn select<'a>(&self, out: &'a mut dyn Write, err: &'a mut dyn Write) -> &'a mut dyn Write {
+        match self.target {
+            OutputTarget::Stderr => err,
+            OutputTarget::Stdout => out,
         }
     }
 }
 
 impl Write for Writer {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        match self.use_stderr {
-            true => stderr().write(buf),
-            false => stdout().write(buf),
-        }
+        self.select(&mut stdout(), &mut stderr()).write(buf)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        match self.use_stderr {
-            true => stderr().flush(),
-            false => stdout().flush(),
+        self.select(&mut stdout(), &mut stderr()).flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writer_from_target() {
+        for target in [OutputTarget::Stdout, OutputTarget::Stderr] {
+            let mut writer = Writer::from_target(target);
+            assert_eq!(writer.target(), target);
+            assert_eq!(writer.write(b"").unwrap(), 0);
+            writer.flush().unwrap();
+
+            // an override takes precedence over use_stderr
+            let config = Config {
+                use_stderr: target == OutputTarget::Stdout,
+                override_writer: Some(writer),
+                ..Default::default()
+            };
+            assert_eq!(Writer::from_config(&config).target(), target);
+        }
+
+        assert_eq!(Writer::stdout().target(), OutputTarget::Stdout);
+        assert_eq!(Writer::stderr().target(), OutputTarget::Stderr);
+    }
+
+    #[test]
+    fn test_writer_renders_to_target() {
+        let img = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            1,
+            2,
+            image::Rgba([255, 0, 0, 255]),
+        ));
+        let config = Config {
+            absolute_offset: false,
+            truecolor: crate::TrueColorMode::Force,
+            ..Default::default()
+        };
+        for target in [OutputTarget::Stdout, OutputTarget::Stderr] {
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            let writer = Writer::from_target(target);
+            let mut stream = termcolor::Ansi::new(writer.select(&mut out, &mut err));
+            crate::printer::BlockPrinter::write(&mut stream, &img, &config).unwrap();
+
+            let (written, untouched) = match target {
+                OutputTarget::Stdout => (out, err),
+                OutputTarget::Stderr => (err, out),
+            };
+            assert!(untouched.is_empty());
+            let written = String::from_utf8(written).unwrap();
+            assert!(
+                written.contains("\x1b[38;2;255;0;0m\x1b[48;2;255;0;0m\u{2584}"),
+                "{:?}",
+                written
+            );
         }
     }
 }
@@ -0,0 +1,111 @@
+// Measures the memory used while playing an animation. It lives in its own test binary, since
+// counting allocations needs a global allocator.
+
+use image::codecs::gif::GifEncoder;
+use image::{Frame, Rgba, RgbaImage};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use viuer::{transcode_gif, Config, TrueColorMode};
+
+// Counts the bytes allocated by each thread, so that tests running in parallel do not interfere
+// with each other's measurements.
+struct TrackingAllocator;
+
+thread_local! {
+    static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+fn track(delta: isize) {
+    let _ = ALLOCATED.try_with(|allocated| {
+        let current = allocated.get() + delta;
+        allocated.set(current);
+        let _ = PEAK.try_with(|peak| peak.set(peak.get().max(current)));
+    });
+}
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            track(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        track(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            track(new_size as isize - layout.size() as isize);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static GLOBAL: TrackingAllocator = TrackingAllocator;
+
+// Number of frames in a transcoded animation, each a line with its delay and length followed by
+// that many bytes.
+fn count_records(mut transcoded: &[u8]) -> usize {
+    let line = |bytes: &[u8]| bytes.iter().position(|&b| b == b'\n').unwrap();
+    transcoded = &transcoded[line(transcoded) + 1..];
+    let mut count = 0;
+    while !transcoded.is_empty() {
+        let end = line(transcoded);
+        let record = std::str::from_utf8(&transcoded[..end]).unwrap();
+        let len: usize = record.split(' ').nth(1).unwrap().parse().unwrap();
+        transcoded = &transcoded[end + 1 + len..];
+        count += 1;
+    }
+    count
+}
+
+#[test]
+fn test_play_frames_bounded_memory() {
+    const FRAMES: usize = 16;
+    const SIZE: u32 = 256;
+    let frame_bytes = (SIZE * SIZE * 4) as isize;
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut bytes, 30);
+        for i in 0..FRAMES {
+            let buffer = RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+                Rgba([(x + i as u32) as u8, y as u8, 0, 255])
+            });
+            encoder.encode_frame(Frame::new(buffer)).unwrap();
+        }
+    }
+
+    let config = Config {
+        truecolor: TrueColorMode::Never,
+        width: Some(40),
+        ..Default::default()
+    };
+
+    // allocated upfront, so that the output does not count towards the peak
+    let mut out = Vec::with_capacity(1 << 22);
+    let baseline = ALLOCATED.with(Cell::get);
+    PEAK.with(|peak| peak.set(baseline));
+
+    // transcoding decodes and draws the frames one at a time, like playing them
+    transcode_gif(bytes.as_slice(), &mut out, &config).unwrap();
+    let peak = PEAK.with(Cell::get) - baseline;
+
+    assert_eq!(count_records(&out), FRAMES);
+    // at least the decoder's canvas is alive during playback
+    assert!(peak >= frame_bytes);
+    // collecting all frames would need at least FRAMES * frame_bytes
+    assert!(
+        peak < 6 * frame_bytes,
+        "peak memory {} exceeds bound {}",
+        peak,
+        6 * frame_bytes
+    );
+}